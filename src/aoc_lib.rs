@@ -1,5 +1,8 @@
 // Days
 // Expose parts of the library
+pub mod big_matrix;
 pub mod jazz_parser;
+pub mod ocr;
+pub mod params;
 
 pub type DayFn = fn(&str);
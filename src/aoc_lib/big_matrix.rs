@@ -0,0 +1,175 @@
+// Square matrices of big integers, for linear recurrences whose values outgrow any primitive type
+use num::{BigUint, One, Zero};
+
+pub type BigMatrix = Vec<Vec<BigUint>>;
+
+pub fn identity(size: usize) -> BigMatrix {
+    (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|col| {
+                    if row == col {
+                        BigUint::one()
+                    } else {
+                        BigUint::zero()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn product(lhs: &BigMatrix, rhs: &BigMatrix) -> BigMatrix {
+    let size = lhs.len();
+    let mut product = vec![vec![BigUint::zero(); size]; size];
+
+    for (row, lhs_row) in lhs.iter().enumerate() {
+        for (mid, lhs_value) in lhs_row.iter().enumerate() {
+            if lhs_value.is_zero() {
+                continue;
+            }
+            for (col, rhs_value) in rhs[mid].iter().enumerate() {
+                if !rhs_value.is_zero() {
+                    product[row][col] += lhs_value * rhs_value;
+                }
+            }
+        }
+    }
+
+    product
+}
+
+// Exponentiation by squaring, so it only takes log2(exp) matrix products
+pub fn power(matrix: &BigMatrix, mut exp: u64) -> BigMatrix {
+    let mut result = identity(matrix.len());
+    let mut base = matrix.clone();
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = product(&result, &base);
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = product(&base, &base);
+        }
+    }
+
+    result
+}
+
+pub fn apply(matrix: &BigMatrix, vector: &[BigUint]) -> Vec<BigUint> {
+    matrix
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(vector.iter())
+                .map(|(factor, value)| factor * value)
+                .sum()
+        })
+        .collect()
+}
+
+// Same as `power` with every value reduced modulo `modulus`, so they all fit in a u64. The
+// exponent can then be as large as needed, there is no value growth to worry about
+pub fn power_mod(matrix: &BigMatrix, mut exp: u64, modulus: u64) -> Vec<Vec<u64>> {
+    let size = matrix.len();
+    let big_modulus = BigUint::from(modulus);
+    let mut result: Vec<Vec<u64>> = (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|col| u64::from(row == col) % modulus)
+                .collect()
+        })
+        .collect();
+    let mut base: Vec<Vec<u64>> = matrix
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| (value % &big_modulus).try_into().unwrap())
+                .collect()
+        })
+        .collect();
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = product_mod(&result, &base, modulus);
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = product_mod(&base, &base, modulus);
+        }
+    }
+
+    result
+}
+
+fn product_mod(lhs: &[Vec<u64>], rhs: &[Vec<u64>], modulus: u64) -> Vec<Vec<u64>> {
+    let size = lhs.len();
+    let mut product = vec![vec![0u64; size]; size];
+
+    for (row, lhs_row) in lhs.iter().enumerate() {
+        for (mid, &lhs_value) in lhs_row.iter().enumerate() {
+            if lhs_value == 0 {
+                continue;
+            }
+            for (col, &rhs_value) in rhs[mid].iter().enumerate() {
+                let term = u128::from(lhs_value) * u128::from(rhs_value) % u128::from(modulus);
+                product[row][col] =
+                    ((u128::from(product[row][col]) + term) % u128::from(modulus)) as u64;
+            }
+        }
+    }
+
+    product
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(values: &[&[u32]]) -> BigMatrix {
+        values
+            .iter()
+            .map(|row| row.iter().map(|&v| BigUint::from(v)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn fibonacci_power() {
+        let fibonacci = matrix(&[&[1, 1], &[1, 0]]);
+
+        assert_eq!(power(&fibonacci, 0), identity(2));
+        assert_eq!(power(&fibonacci, 10), matrix(&[&[89, 55], &[55, 34]]));
+
+        let fib_300 = apply(&power(&fibonacci, 300), &[BigUint::one(), BigUint::zero()]);
+        assert_eq!(
+            fib_300[1].to_string(),
+            "222232244629420445529739893461909967206666939096499764990979600"
+        );
+    }
+
+    #[test]
+    fn modular_power() {
+        let fibonacci = matrix(&[&[1, 1], &[1, 0]]);
+        let modulus = 1_000_000_007u64;
+
+        let exact: Vec<Vec<u64>> = power(&fibonacci, 300)
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| (value % modulus).try_into().unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(power_mod(&fibonacci, 300, modulus), exact);
+
+        assert_eq!(
+            power_mod(&fibonacci, 0, modulus),
+            vec![vec![1, 0], vec![0, 1]]
+        );
+        assert_eq!(power_mod(&fibonacci, 5, 1), vec![vec![0, 0], vec![0, 0]]);
+
+        // F(10^9) mod 10^9 + 7, far too large to compute exactly
+        assert_eq!(power_mod(&fibonacci, 1_000_000_000, modulus)[0][1], 21);
+    }
+}
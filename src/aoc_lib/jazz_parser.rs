@@ -3,7 +3,7 @@ use nom::combinator::{map, opt};
 use nom::sequence::pair;
 use nom::IResult;
 
-pub fn _i64(input: &str) -> IResult<&str, i64> {
+pub fn i64(input: &str) -> IResult<&str, i64> {
     map(
         pair(opt(char('-')), digit1),
         |(sign, value): (Option<char>, &str)| {
@@ -27,6 +27,6 @@ pub fn u64(input: &str) -> IResult<&str, u64> {
     map(digit1, |s: &str| s.parse().unwrap())(input)
 }
 
-pub fn usize(input: &str) -> IResult<&str, usize> {
+pub fn _usize(input: &str) -> IResult<&str, usize> {
     map(digit1, |s: &str| s.parse().unwrap())(input)
 }
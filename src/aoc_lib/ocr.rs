@@ -0,0 +1,288 @@
+// Recognition of the block letters that some puzzles use to draw their answer. Glyphs are stored
+// with their empty columns trimmed, so they can be matched whatever the spacing between letters.
+const SMALL_HEIGHT: usize = 6;
+const LARGE_HEIGHT: usize = 10;
+
+const SMALL_GLYPHS: [(char, [&str; SMALL_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE_GLYPHS: [(char, [&str; LARGE_HEIGHT]); 15] = [
+    (
+        'A',
+        [
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        [
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        [
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        [
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        [
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        [
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        [
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        [
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+fn glyph_matches(rows: &[&str], grid: &[Vec<bool>], first_col: usize, last_col: usize) -> bool {
+    rows.len() == grid.len()
+        && rows.iter().zip(grid.iter()).all(|(glyph_row, grid_row)| {
+            glyph_row.len() == last_col - first_col
+                && glyph_row
+                    .chars()
+                    .zip(grid_row[first_col..last_col].iter())
+                    .all(|(glyph_pixel, lit)| (glyph_pixel == '#') == *lit)
+        })
+}
+
+fn match_glyph(grid: &[Vec<bool>], first_col: usize, last_col: usize) -> Option<char> {
+    match grid.len() {
+        SMALL_HEIGHT => SMALL_GLYPHS
+            .iter()
+            .find(|(_, rows)| glyph_matches(rows, grid, first_col, last_col))
+            .map(|(letter, _)| *letter),
+        LARGE_HEIGHT => LARGE_GLYPHS
+            .iter()
+            .find(|(_, rows)| glyph_matches(rows, grid, first_col, last_col))
+            .map(|(letter, _)| *letter),
+        _ => None,
+    }
+}
+
+/// Decodes a grid of lit pixels (rows of equal length) drawing block letters, either in the 4x6
+/// or in the 6x10 font. Fully empty rows and columns around the letters are ignored.
+pub fn decode(grid: &[Vec<bool>]) -> Result<String, &'static str> {
+    let lit_rows: Vec<usize> = grid
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| row.iter().any(|lit| *lit))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let (first_row, last_row) = match (lit_rows.first(), lit_rows.last()) {
+        (Some(first), Some(last)) => (*first, *last + 1),
+        _ => return Err("There are no letters to decode"),
+    };
+    let text_grid = &grid[first_row..last_row];
+
+    if text_grid.len() != SMALL_HEIGHT && text_grid.len() != LARGE_HEIGHT {
+        return Err("Letters height doesn't match any known font");
+    }
+
+    let width = text_grid
+        .iter()
+        .map(|row| row.len())
+        .max()
+        .unwrap_or_default();
+    if text_grid.iter().any(|row| row.len() != width) {
+        return Err("Grid rows have different lengths");
+    }
+
+    let lit_column = |col: usize| text_grid.iter().any(|row| row[col]);
+
+    let mut decoded = String::new();
+    let mut col: usize = 0;
+    while col < width {
+        if !lit_column(col) {
+            col += 1;
+            continue;
+        }
+
+        let first_col = col;
+        while col < width && lit_column(col) {
+            col += 1;
+        }
+
+        match match_glyph(text_grid, first_col, col) {
+            Some(letter) => decoded.push(letter),
+            None => return Err("Found a glyph that doesn't match any known letter"),
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Converts a drawing made of `#` (lit) and any other character (off) into a pixel grid
+#[cfg(test)]
+pub fn grid_from_str(drawing: &str) -> Vec<Vec<bool>> {
+    drawing
+        .lines()
+        .map(|line| line.trim().chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_font() {
+        let drawing = "###..####.####.#..#.####.####.#..#..##.
+        #..#....#.#....#.#..#....#....#..#.#..#
+        #..#...#..###..##...###..###..#..#.#...
+        ###...#...#....#.#..#....#....#..#.#...
+        #.#..#....#....#.#..#....#....#..#.#..#
+        #..#.####.####.#..#.####.#.....##...##.";
+
+        assert_eq!(
+            decode(&grid_from_str(drawing)),
+            Ok(String::from("RZEKEFUC"))
+        );
+    }
+
+    #[test]
+    fn every_small_glyph() {
+        let rows: Vec<String> = (0..SMALL_HEIGHT)
+            .map(|row| {
+                SMALL_GLYPHS
+                    .iter()
+                    .map(|(_, glyph)| glyph[row])
+                    .collect::<Vec<_>>()
+                    .join(".")
+            })
+            .collect();
+        let expected: String = SMALL_GLYPHS.iter().map(|(letter, _)| *letter).collect();
+
+        assert_eq!(decode(&grid_from_str(&rows.join("\n"))), Ok(expected));
+    }
+
+    #[test]
+    fn large_font() {
+        let drawing = "
+        #....#..######
+        #....#.......#
+        .#..#........#
+        .#..#.......#.
+        ..##.......#..
+        ..##......#...
+        .#..#....#....
+        .#..#...#.....
+        #....#..#.....
+        #....#..######
+        ";
+
+        assert_eq!(decode(&grid_from_str(drawing)), Ok(String::from("XZ")));
+    }
+
+    #[test]
+    fn decoding_failures() {
+        assert!(decode(&grid_from_str("....\n....")).is_err());
+        assert!(decode(&grid_from_str("####\n#..#\n####")).is_err());
+
+        let unknown = "#...#
+        #...#
+        #...#
+        #...#
+        #...#
+        #####";
+        assert!(decode(&grid_from_str(unknown)).is_err());
+    }
+}
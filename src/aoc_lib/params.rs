@@ -0,0 +1,206 @@
+// Puzzle constants that can be overridden from the command line with `--param key=value`, so the
+// same binary can run the small examples from the puzzle text as well as the full inputs. Each
+// day declares its own typed parameters with their default value, and lists them in `PARAMS` so
+// the overrides can be checked before anything runs.
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+#[derive(Debug, Default)]
+pub struct Overrides {
+    values: HashMap<String, String>,
+}
+
+impl Overrides {
+    pub fn new(pairs: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            values: pairs.into_iter().collect(),
+        }
+    }
+}
+
+// Parses a single `key=value` override, as given on the command line
+pub fn parse_override(raw: &str) -> Result<(String, String), String> {
+    match raw.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected key=value, got '{}'", raw)),
+    }
+}
+
+// Can only be done once, before running any day
+pub fn set_overrides(overrides: Overrides) -> Result<(), &'static str> {
+    OVERRIDES
+        .set(overrides)
+        .map_err(|_| "Parameter overrides were already set")
+}
+
+pub fn overrides() -> Option<&'static Overrides> {
+    OVERRIDES.get()
+}
+
+// A parameter with its type erased, so a day can list all of its parameters together
+pub trait ParamSpec {
+    fn name(&self) -> &'static str;
+    fn check(&self, raw: &str) -> Result<(), String>;
+}
+
+pub type DayParams = &'static [&'static dyn ParamSpec];
+
+// Every override has to name one of the day's parameters and hold a value of its type
+pub fn validate(pairs: &[(String, String)], declared: DayParams) -> Result<(), String> {
+    for (key, raw) in pairs {
+        match declared.iter().find(|param| param.name() == key) {
+            Some(param) => param.check(raw)?,
+            None if declared.is_empty() => {
+                return Err(format!(
+                    "unknown parameter '{}', this day has no parameters",
+                    key
+                ))
+            }
+            None => {
+                let names: Vec<&str> = declared.iter().map(|param| param.name()).collect();
+                return Err(format!(
+                    "unknown parameter '{}', expected one of: {}",
+                    key,
+                    names.join(", ")
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub struct Param<T> {
+    pub name: &'static str,
+    pub default: T,
+}
+
+impl<T> Param<T> {
+    pub const fn new(name: &'static str, default: T) -> Self {
+        Self { name, default }
+    }
+}
+
+impl<T> Param<T>
+where
+    T: FromStr + Clone,
+    T::Err: Display,
+{
+    fn parse(&self, raw: &str) -> Result<T, String> {
+        raw.parse().map_err(|e| {
+            format!(
+                "invalid value '{}' for parameter '{}': {}",
+                raw, self.name, e
+            )
+        })
+    }
+
+    pub fn get_from(&self, overrides: &Overrides) -> Result<T, String> {
+        match overrides.values.get(self.name) {
+            Some(raw) => self.parse(raw),
+            None => Ok(self.default.clone()),
+        }
+    }
+
+    // The overridden value if there is one, the default otherwise. Overrides are validated
+    // before running the day, so an invalid one here is a parameter missing from `PARAMS`
+    pub fn get(&self) -> T {
+        match overrides() {
+            Some(overrides) => self.get_from(overrides).unwrap_or_else(|e| panic!("{}", e)),
+            None => self.default.clone(),
+        }
+    }
+}
+
+impl<T> ParamSpec for Param<T>
+where
+    T: FromStr + Clone,
+    T::Err: Display,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn check(&self, raw: &str) -> Result<(), String> {
+        self.parse(raw).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROW: Param<i32> = Param::new("row", 2_000_000);
+    const ROUNDS: Param<usize> = Param::new("rounds", 20);
+    const STEPS: Param<u64> = Param::new("steps", 40);
+
+    const PARAMS: DayParams = &[&ROW, &ROUNDS];
+
+    fn pairs(raw: &[(&str, &str)]) -> Vec<(String, String)> {
+        raw.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn override_parsing() {
+        assert_eq!(
+            parse_override("row=10"),
+            Ok(("row".to_string(), "10".to_string()))
+        );
+        assert_eq!(
+            parse_override(" engine = recursive "),
+            Ok(("engine".to_string(), "recursive".to_string()))
+        );
+        assert_eq!(
+            parse_override("range=-5=5"),
+            Ok(("range".to_string(), "-5=5".to_string()))
+        );
+        assert!(parse_override("row").is_err());
+        assert!(parse_override("=10").is_err());
+    }
+
+    #[test]
+    fn typed_values() {
+        let overrides = Overrides::new(pairs(&[("row", "10"), ("rounds", "many")]));
+
+        assert_eq!(ROW.get_from(&overrides), Ok(10));
+        assert_eq!(
+            ROUNDS.get_from(&overrides),
+            Err(
+                "invalid value 'many' for parameter 'rounds': invalid digit found in string".into()
+            )
+        );
+        assert_eq!(STEPS.get_from(&overrides), Ok(40));
+
+        assert_eq!(STEPS.get_from(&Overrides::default()), Ok(40));
+    }
+
+    #[test]
+    fn override_validation() {
+        assert_eq!(
+            validate(&pairs(&[("row", "10"), ("rounds", "3")]), PARAMS),
+            Ok(())
+        );
+        assert_eq!(validate(&[], &[]), Ok(()));
+
+        assert_eq!(
+            validate(&pairs(&[("row", "abc")]), PARAMS),
+            Err("invalid value 'abc' for parameter 'row': invalid digit found in string".into())
+        );
+        assert_eq!(
+            validate(&pairs(&[("rows", "3")]), PARAMS),
+            Err("unknown parameter 'rows', expected one of: row, rounds".into())
+        );
+        assert_eq!(
+            validate(&pairs(&[("row", "3")]), &[]),
+            Err("unknown parameter 'row', this day has no parameters".into())
+        );
+    }
+}
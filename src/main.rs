@@ -1,9 +1,11 @@
 use std::time::{Duration, Instant};
 use std::{env, fs};
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 
 mod aoc_lib;
+use aoc_lib::params::{self, DayParams, Overrides};
 use aoc_lib::DayFn;
 
 mod year_2021;
@@ -20,6 +22,10 @@ struct CLIConfig {
     /// Year to get the day from
     #[clap(short, long, value_parser, default_value_t = 2021)]
     year: u16,
+
+    /// Override a puzzle constant of the day, e.g. `--param row=10`. Can be repeated
+    #[clap(short, long = "param", value_name = "KEY=VALUE", value_parser = params::parse_override)]
+    params: Vec<(String, String)>,
 }
 
 fn get_day(year: u16, day: u8) -> (Option<DayFn>, Option<DayFn>) {
@@ -34,6 +40,14 @@ fn get_day(year: u16, day: u8) -> (Option<DayFn>, Option<DayFn>) {
     };
 }
 
+fn get_params(year: u16, day: u8) -> DayParams {
+    match year {
+        2021 => year_2021::get_params(day),
+        2022 => year_2022::get_params(day),
+        _ => &[],
+    }
+}
+
 fn fmt_time(ms: f64) -> String {
     if ms <= 1.0 {
         let micro_sec = ms * 1000.0;
@@ -66,6 +80,13 @@ fn main() {
     // Get day string
     let user_config = CLIConfig::parse();
 
+    // Check the parameter overrides before doing anything
+    let day_params = get_params(user_config.year, user_config.day);
+    if let Err(e) = params::validate(&user_config.params, day_params) {
+        CLIConfig::command()
+            .error(ErrorKind::InvalidValue, e)
+            .exit();
+    }
     // Read input file
     let cwd = env::current_dir().unwrap();
     let filename = cwd
@@ -76,6 +97,8 @@ fn main() {
     println!();
     let input = fs::read_to_string(filename).expect("Error while reading");
 
+    params::set_overrides(Overrides::new(user_config.params)).unwrap();
+
     // Get corresponding function
     let to_run = get_day(user_config.year, user_config.day);
 
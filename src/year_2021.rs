@@ -24,6 +24,7 @@ mod day23;
 mod day24;
 mod day25;
 
+use crate::aoc_lib::params::DayParams;
 use crate::aoc_lib::DayFn;
 
 pub fn get_day(day: u8) -> (Option<DayFn>, Option<DayFn>) {
@@ -31,13 +32,13 @@ pub fn get_day(day: u8) -> (Option<DayFn>, Option<DayFn>) {
         1 => (Some(day01::part1), Some(day01::part2)),
         2 => (Some(day02::part1), Some(day02::part2)),
         3 => (Some(day03::part1), Some(day03::part2)),
-        4 => (Some(day04::part1), None),
+        4 => (Some(day04::part1), Some(day04::part2)),
         5 => (Some(day05::part1), Some(day05::part2)),
         6 => (Some(day06::part1), Some(day06::part2)),
         7 => (Some(day07::part1), Some(day07::part2)),
         8 => (Some(day08::part1), Some(day08::part2)),
         9 => (Some(day09::part1), Some(day09::part2)),
-        10 => (Some(day10::part1), None),
+        10 => (Some(day10::part1), Some(day10::part2)),
         11 => (Some(day11::part1), Some(day11::part2)),
         12 => (Some(day12::part1), Some(day12::part2)),
         13 => (Some(day13::part1), Some(day13::part2)),
@@ -59,3 +60,21 @@ pub fn get_day(day: u8) -> (Option<DayFn>, Option<DayFn>) {
         }
     }
 }
+
+// Parameters that can be overridden from the command line
+pub fn get_params(day: u8) -> DayParams {
+    match day {
+        5 => day05::PARAMS,
+        6 => day06::PARAMS,
+        9 => day09::PARAMS,
+        10 => day10::PARAMS,
+        12 => day12::PARAMS,
+        13 => day13::PARAMS,
+        14 => day14::PARAMS,
+        17 => day17::PARAMS,
+        19 => day19::PARAMS,
+        20 => day20::PARAMS,
+        22 => day22::PARAMS,
+        _ => &[],
+    }
+}
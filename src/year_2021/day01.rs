@@ -1,74 +1,120 @@
-use itertools::Itertools;
+use std::collections::VecDeque;
 
-pub fn part1(input: &str) {
-    let mut increase_amount = 0;
-    let mut line_input = input.split('\n');
-    let mut last_value: u32 = line_input
-        .next()
-        .expect("An empty input, really?")
-        .trim()
-        .parse::<u32>()
-        .expect("The first line is not an integer!");
-
-    for curr_value in line_input {
-        let num_opt = curr_value.trim().parse::<u32>();
-        let num_value = match num_opt {
-            Ok(val) => val,
-            Err(e) => {
-                println!(
-                    "String {} doesn't seem to contain a number! Error: {}",
-                    curr_value, e
-                );
-                last_value
-            }
-        };
+// Depths on every non-empty line, with invalid lines reported with their line number
+fn depth_readings(input: &str) -> impl Iterator<Item = Result<u32, String>> + '_ {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_idx, line)| {
+            line.trim().parse::<u32>().map_err(|e| {
+                format!(
+                    "Line {}: '{}' doesn't seem to contain a number ({})",
+                    line_idx + 1,
+                    line.trim(),
+                    e
+                )
+            })
+        })
+}
 
-        if num_value > last_value {
-            increase_amount += 1;
-        }
-        last_value = num_value;
+// Two consecutive windows share all of their values but the first of the older one and the last
+// of the newer one, so comparing those two is enough. Only the last `window_size` depths are kept
+fn count_window_increases<I>(readings: I, window_size: usize) -> Result<usize, String>
+where
+    I: Iterator<Item = Result<u32, String>>,
+{
+    if window_size == 0 {
+        return Err(String::from("Windows need at least one depth"));
     }
 
-    println!("Amount of increases: {}", increase_amount);
-}
+    let mut window: VecDeque<u32> = VecDeque::with_capacity(window_size + 1);
+    let mut increases = 0usize;
 
-fn sum_window(tuple: (&str, &str, &str)) -> Option<i32> {
-    let mut final_sum = 0;
-    for value in [tuple.0, tuple.1, tuple.2].iter() {
-        let num_opt = value.trim().parse::<i32>();
-        match num_opt {
-            Ok(val) => {
-                final_sum += val;
-            }
-            Err(e) => {
-                println!(
-                    "String {} doesn't seem to contain a number! Error: {}",
-                    value, e
-                );
-                return None;
+    for depth in readings {
+        window.push_back(depth?);
+        if window.len() > window_size {
+            let dropped = window.pop_front().unwrap();
+            if *window.back().unwrap() > dropped {
+                increases += 1;
             }
         }
     }
 
-    Some(final_sum)
+    Ok(increases)
+}
+
+pub fn part1(input: &str) {
+    match count_window_increases(depth_readings(input), 1) {
+        Ok(increases) => println!("Amount of increases: {}", increases),
+        Err(e) => println!("Couldn't read the sonar sweep: {}", e),
+    }
 }
 
 pub fn part2(input: &str) {
-    let mut line_input = input.split('\n').tuple_windows::<(_, _, _)>();
-    //let mut
-    let mut last_full_value =
-        sum_window(line_input.next().unwrap()).expect("I am expecting more than three values");
-    let mut increases = 0;
+    match count_window_increases(depth_readings(input), 3) {
+        Ok(increases) => println!("Amount of increases: {}", increases),
+        Err(e) => println!("Couldn't read the sonar sweep: {}", e),
+    }
+}
 
-    for curr_value in line_input {
-        let curr_num = sum_window(curr_value);
-        if let Some(num) = curr_num {
-            if num > last_full_value {
-                increases += 1;
-            }
-            last_full_value = num;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_SWEEP: &str = "199
+        200
+        208
+        210
+        200
+        207
+        240
+        269
+        260
+        263
+        ";
+
+    #[test]
+    fn single_measurements() {
+        assert_eq!(
+            count_window_increases(depth_readings(EXAMPLE_SWEEP), 1),
+            Ok(7)
+        );
+    }
+
+    #[test]
+    fn sliding_windows() {
+        assert_eq!(
+            count_window_increases(depth_readings(EXAMPLE_SWEEP), 3),
+            Ok(5)
+        );
+        assert_eq!(
+            count_window_increases(depth_readings(EXAMPLE_SWEEP), 9),
+            Ok(1)
+        );
+        assert_eq!(
+            count_window_increases(depth_readings(EXAMPLE_SWEEP), 10),
+            Ok(0)
+        );
+        assert!(count_window_increases(depth_readings(EXAMPLE_SWEEP), 0).is_err());
+
+        // Readings don't need to come from a string
+        let depths = (0..1000u32).map(|depth| Ok(depth % 7));
+        assert_eq!(count_window_increases(depths, 7), Ok(0));
     }
 
-    println!("Amount of increases: {}", increases);
+    #[test]
+    fn invalid_lines() {
+        let input_string = "199
+        200
+        2o8
+        210";
+
+        assert_eq!(
+            count_window_increases(depth_readings(input_string), 1),
+            Err(String::from(
+                "Line 3: '2o8' doesn't seem to contain a number (invalid digit found in string)"
+            ))
+        );
+    }
 }
@@ -1,73 +1,234 @@
-pub fn part1(input: &str) {
-    let line_input = input.lines();
-    let mut forward = 0;
-    let mut depth = 0;
-
-    for curr_value in line_input {
-        let mut direction_val_split = curr_value.split_whitespace();
-
-        let direction_string = direction_val_split
-            .next()
-            .expect("Where did our direction go?")
-            .to_lowercase();
-        let distance = direction_val_split
-            .next()
-            .expect("Where did the distance go?")
-            .trim()
-            .parse::<i32>()
-            .expect("Didn't receive a number");
-        match direction_string.as_str() {
-            "forward" => forward += distance,
-            "down" => depth += distance,
-            "up" => {
-                depth -= distance;
-                if depth < 0 {
-                    depth = 0;
-                }
+use nom::branch::alt;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{space0, space1};
+use nom::combinator::{all_consuming, map};
+use nom::sequence::{delimited, preceded, terminated};
+use nom::IResult;
+
+use crate::aoc_lib::jazz_parser;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubmarineCommand {
+    Forward(i64),
+    Down(i64),
+    Up(i64),
+}
+
+// Submarine commands parser
+fn submarine_command(input: &str) -> IResult<&str, SubmarineCommand> {
+    let direction = |name| terminated(tag_no_case(name), space1);
+
+    delimited(
+        space0,
+        alt((
+            map(
+                preceded(direction("forward"), jazz_parser::i64),
+                SubmarineCommand::Forward,
+            ),
+            map(
+                preceded(direction("down"), jazz_parser::i64),
+                SubmarineCommand::Down,
+            ),
+            map(
+                preceded(direction("up"), jazz_parser::i64),
+                SubmarineCommand::Up,
+            ),
+        )),
+        space0,
+    )(input)
+}
+
+fn parse_commands(input: &str) -> Result<Vec<SubmarineCommand>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_idx, line)| {
+            all_consuming(submarine_command)(line)
+                .map(|(_, command)| command)
+                .map_err(|_| format!("Line {}: unknown command '{}'", line_idx + 1, line.trim()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    horizontal: i64,
+    depth: i64,
+}
+
+impl Position {
+    pub fn travel_area(&self) -> i64 {
+        self.horizontal * self.depth
+    }
+}
+
+// Movement model of the submarine. It can't go any higher than the surface
+trait Submarine {
+    fn execute(&mut self, command: &SubmarineCommand);
+
+    fn position(&self) -> Position;
+}
+
+// Up and down change the depth directly
+#[derive(Debug, Default)]
+struct SimpleSubmarine {
+    position: Position,
+}
+
+impl Submarine for SimpleSubmarine {
+    fn execute(&mut self, command: &SubmarineCommand) {
+        match *command {
+            SubmarineCommand::Forward(distance) => self.position.horizontal += distance,
+            SubmarineCommand::Down(distance) => self.position.depth += distance,
+            SubmarineCommand::Up(distance) => {
+                self.position.depth = (self.position.depth - distance).max(0)
             }
-            _ => {
-                println!("Provided a non-handled direction: {}", direction_string);
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+// Up and down change the aim, and the depth changes when moving forward
+#[derive(Debug, Default)]
+struct AimedSubmarine {
+    position: Position,
+    aim: i64,
+}
+
+impl Submarine for AimedSubmarine {
+    fn execute(&mut self, command: &SubmarineCommand) {
+        match *command {
+            SubmarineCommand::Forward(distance) => {
+                self.position.horizontal += distance;
+                self.position.depth = (self.position.depth + distance * self.aim).max(0);
             }
+            SubmarineCommand::Down(distance) => self.aim += distance,
+            SubmarineCommand::Up(distance) => self.aim -= distance,
         }
     }
 
-    println!("Travel area: {}", forward * depth);
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+// Position of the submarine after each command
+fn pilot<S: Submarine>(submarine: &mut S, commands: &[SubmarineCommand]) -> Vec<Position> {
+    commands
+        .iter()
+        .map(|command| {
+            submarine.execute(command);
+            submarine.position()
+        })
+        .collect()
+}
+
+fn report_travel<S: Submarine>(input: &str, mut submarine: S) {
+    match parse_commands(input) {
+        Ok(commands) => {
+            pilot(&mut submarine, &commands);
+            println!("Travel area: {}", submarine.position().travel_area());
+        }
+        Err(e) => println!("Couldn't pilot the submarine: {}", e),
+    }
+}
+
+pub fn part1(input: &str) {
+    report_travel(input, SimpleSubmarine::default());
 }
 
 pub fn part2(input: &str) {
-    let line_input = input.lines();
-    let mut forward = 0;
-    let mut depth = 0;
-    let mut angle = 0;
-
-    for curr_value in line_input {
-        let mut direction_val_split = curr_value.split_whitespace();
-
-        let direction_string = direction_val_split
-            .next()
-            .expect("Where did our direction go?")
-            .to_lowercase();
-        let distance = direction_val_split
-            .next()
-            .expect("Where did the distance go?")
-            .trim()
-            .parse::<i32>()
-            .expect("Didn't receive a number");
-        match direction_string.as_str() {
-            "forward" => {
-                forward += distance;
-                depth += distance * angle;
-                if depth < 0 {
-                    depth = 0;
-                }
+    report_travel(input, AimedSubmarine::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_COMMANDS: &str = "forward 5
+        down 5
+        forward 8
+        up 3
+        down 8
+        forward 2";
+
+    fn positions(values: &[(i64, i64)]) -> Vec<Position> {
+        values
+            .iter()
+            .map(|&(horizontal, depth)| Position { horizontal, depth })
+            .collect()
+    }
+
+    #[test]
+    fn command_parsing() {
+        assert_eq!(
+            parse_commands("forward 5\n  DOWN 12 \n\nup 3"),
+            Ok(vec![
+                SubmarineCommand::Forward(5),
+                SubmarineCommand::Down(12),
+                SubmarineCommand::Up(3),
+            ])
+        );
+        assert_eq!(
+            parse_commands("forward 5\nbackward 2"),
+            Err(String::from("Line 2: unknown command 'backward 2'"))
+        );
+        assert_eq!(
+            parse_commands("up 2 meters"),
+            Err(String::from("Line 1: unknown command 'up 2 meters'"))
+        );
+    }
+
+    #[test]
+    fn simple_trace() {
+        let commands = parse_commands(EXAMPLE_COMMANDS).unwrap();
+        let mut submarine = SimpleSubmarine::default();
+
+        assert_eq!(
+            pilot(&mut submarine, &commands),
+            positions(&[(5, 0), (5, 5), (13, 5), (13, 2), (13, 10), (15, 10)])
+        );
+        assert_eq!(submarine.position().travel_area(), 150);
+    }
+
+    #[test]
+    fn aimed_trace() {
+        let commands = parse_commands(EXAMPLE_COMMANDS).unwrap();
+        let mut submarine = AimedSubmarine::default();
+
+        assert_eq!(
+            pilot(&mut submarine, &commands),
+            positions(&[(5, 0), (5, 0), (13, 40), (13, 40), (13, 40), (15, 60)])
+        );
+        assert_eq!(submarine.position().travel_area(), 900);
+    }
+
+    #[test]
+    fn surface_limit() {
+        let commands = parse_commands("down 2\nup 5\nforward 3").unwrap();
+
+        let mut simple = SimpleSubmarine::default();
+        pilot(&mut simple, &commands);
+        assert_eq!(
+            simple.position(),
+            Position {
+                horizontal: 3,
+                depth: 0
             }
-            "down" => angle += distance,
-            "up" => angle -= distance,
-            _ => {
-                println!("Provided a non-handled direction: {}", direction_string);
+        );
+
+        let mut aimed = AimedSubmarine::default();
+        pilot(&mut aimed, &commands);
+        assert_eq!(
+            aimed.position(),
+            Position {
+                horizontal: 3,
+                depth: 0
             }
-        }
+        );
     }
-
-    println!("Travel area: {}", forward * depth);
 }
@@ -1,106 +1,220 @@
-pub fn part1(input: &str) {
-    let mut line_input = input.lines().peekable();
-    let mut frequencies = vec![
-        0;
-        line_input
-            .peek()
-            .expect("I need at least one line, man.")
-            .len()
-    ];
-
-    for curr_value in line_input {
-        for (pos, bit) in curr_value.chars().enumerate() {
-            match bit {
-                '0' => frequencies[pos] -= 1,
-                '1' => frequencies[pos] += 1,
-                _ => {
-                    println!("Got character {} in a binary string", bit)
-                }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Criteria {
+    MostCommon,
+    LeastCommon,
+}
+
+// Which bit a criteria keeps, with `on_tie` used when there are as many ones as zeros
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BitCriteria {
+    criteria: Criteria,
+    on_tie: bool,
+}
+
+impl BitCriteria {
+    const fn new(criteria: Criteria, on_tie: bool) -> BitCriteria {
+        BitCriteria { criteria, on_tie }
+    }
+
+    fn selected_bit(&self, ones: usize, total: usize) -> bool {
+        let zeros = total - ones;
+        if ones == zeros {
+            self.on_tie
+        } else {
+            match self.criteria {
+                Criteria::MostCommon => ones > zeros,
+                Criteria::LeastCommon => ones < zeros,
             }
         }
     }
+}
 
-    println!("Final frequencies: {:?}", frequencies);
+const GAMMA_CRITERIA: BitCriteria = BitCriteria::new(Criteria::MostCommon, false);
+const EPSILON_CRITERIA: BitCriteria = BitCriteria::new(Criteria::LeastCommon, true);
+const OXYGEN_CRITERIA: BitCriteria = BitCriteria::new(Criteria::MostCommon, true);
+const CARBON_CRITERIA: BitCriteria = BitCriteria::new(Criteria::LeastCommon, false);
 
-    let mut gamma: u64 = 0;
-    let mut epsilon: u64 = 0;
-    for freq in frequencies {
-        gamma <<= 1;
-        epsilon <<= 1;
-        if freq > 0 {
-            gamma += 1;
-        } else {
-            epsilon += 1;
+// Report lines packed into integers, with the first character as the most significant bit
+#[derive(Debug)]
+struct DiagnosticReport {
+    words: Vec<u64>,
+    width: u32,
+}
+
+impl DiagnosticReport {
+    pub fn new(input: &str) -> Result<DiagnosticReport, String> {
+        let lines: Vec<&str> = input
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect();
+        let width = lines.first().map_or(0, |l| l.len());
+        if width > 64 {
+            return Err(format!("Words can have at most 64 bits, got {}", width));
+        }
+
+        let words = lines
+            .iter()
+            .enumerate()
+            .map(|(line_idx, line)| {
+                if line.len() != width {
+                    return Err(format!(
+                        "Line {} has {} bits instead of {}",
+                        line_idx + 1,
+                        line.len(),
+                        width
+                    ));
+                }
+                u64::from_str_radix(line, 2)
+                    .map_err(|_| format!("Line {}: {} isn't a binary string", line_idx + 1, line))
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+
+        Ok(DiagnosticReport {
+            words,
+            width: u32::try_from(width).unwrap(),
+        })
+    }
+
+    // Bits from the most significant one
+    fn bit_masks(&self) -> impl Iterator<Item = u64> {
+        (0..self.width).rev().map(|bit| 1u64 << bit)
+    }
+
+    fn count_ones(words: &[u64], mask: u64) -> usize {
+        words.iter().filter(|&&word| word & mask != 0).count()
+    }
+
+    // Word made of the bit each position selects across the whole report
+    pub fn rate(&self, criteria: BitCriteria) -> u64 {
+        self.bit_masks()
+            .filter(|&mask| {
+                criteria.selected_bit(Self::count_ones(&self.words, mask), self.words.len())
+            })
+            .fold(0u64, |rate, mask| rate | mask)
+    }
+
+    // Only keeps the words with the selected bit, one position at a time, until a single one is left
+    pub fn rating(&self, criteria: BitCriteria) -> Result<u64, &'static str> {
+        let mut candidates = self.words.clone();
+
+        for mask in self.bit_masks() {
+            if candidates.len() <= 1 {
+                break;
+            }
+
+            let keep_ones =
+                criteria.selected_bit(Self::count_ones(&candidates, mask), candidates.len());
+            candidates.retain(|&word| (word & mask != 0) == keep_ones);
         }
+
+        candidates
+            .first()
+            .cloned()
+            .ok_or("No word matches the bit criteria")
     }
 
-    let power_consumption = gamma * epsilon;
-    println!("Power consumption: {}", power_consumption);
+    pub fn power_consumption(&self) -> u64 {
+        self.rate(GAMMA_CRITERIA) * self.rate(EPSILON_CRITERIA)
+    }
+
+    pub fn life_support_rating(&self) -> Result<u64, &'static str> {
+        Ok(self.rating(OXYGEN_CRITERIA)? * self.rating(CARBON_CRITERIA)?)
+    }
 }
 
-pub fn check_for_one(binary_value: &str, bit_pos: usize) -> Option<bool> {
-    match binary_value.chars().nth(bit_pos).unwrap_or('x') {
-        '1' => Some(true),
-        '0' => Some(false),
-        _ => None,
+pub fn part1(input: &str) {
+    match DiagnosticReport::new(input) {
+        Ok(report) => println!("Power consumption: {}", report.power_consumption()),
+        Err(e) => println!("Couldn't read the diagnostic report: {}", e),
     }
 }
 
 pub fn part2(input: &str) {
-    let line_input = input.lines();
-
-    let (one_data, zero_data): (Vec<&str>, Vec<&str>) = line_input.partition(|line| {
-        check_for_one(line, 0)
-            .unwrap_or_else(|| panic!("Didn't get a proper binary string! Got {}", line))
-    });
-
-    let (mut oxygen_data, mut carbon_data) = if one_data.len() >= zero_data.len() {
-        (one_data, zero_data)
-    } else {
-        (zero_data, one_data)
-    };
-
-    let mut curr_bit = 1;
-
-    while oxygen_data.len() > 1 {
-        let (one, zero): (Vec<&str>, Vec<&str>) = oxygen_data.iter().partition(|line| {
-            check_for_one(line, curr_bit)
-                .unwrap_or_else(|| panic!("Didn't get a proper binary string! Got {}", line))
-        });
-        curr_bit += 1;
-        oxygen_data = if one.len() >= zero.len() { one } else { zero };
+    let life_support = DiagnosticReport::new(input)
+        .and_then(|report| report.life_support_rating().map_err(String::from));
+
+    match life_support {
+        Ok(rating) => println!("Life support rating: {}", rating),
+        Err(e) => println!("Couldn't read the diagnostic report: {}", e),
     }
+}
 
-    let oxygen_score = match isize::from_str_radix(oxygen_data[0], 2) {
-        Ok(score) => score,
-        Err(e) => {
-            panic!(
-                "Couldn't parse the value of {}, got error: {}",
-                oxygen_data[0], e
-            )
-        }
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_REPORT: &str = "00100
+        11110
+        10110
+        10111
+        10101
+        01111
+        00111
+        11100
+        10000
+        11001
+        00010
+        01010";
 
-    curr_bit = 1;
+    #[test]
+    fn power_consumption() {
+        let report = DiagnosticReport::new(EXAMPLE_REPORT).unwrap();
 
-    while carbon_data.len() > 1 {
-        let (one, zero): (Vec<&str>, Vec<&str>) = carbon_data.iter().partition(|line| {
-            check_for_one(line, curr_bit)
-                .unwrap_or_else(|| panic!("Didn't get a proper binary string! Got {}", line))
-        });
-        curr_bit += 1;
-        carbon_data = if one.len() >= zero.len() { zero } else { one };
+        assert_eq!(report.width, 5);
+        assert_eq!(report.rate(GAMMA_CRITERIA), 22);
+        assert_eq!(report.rate(EPSILON_CRITERIA), 9);
+        assert_eq!(report.power_consumption(), 198);
     }
 
-    let carbon_score = match isize::from_str_radix(carbon_data[0], 2) {
-        Ok(score) => score,
-        Err(e) => {
-            panic!(
-                "Couldn't parse the value of {}, got error: {}",
-                carbon_data[0], e
-            )
-        }
-    };
+    #[test]
+    fn life_support() {
+        let report = DiagnosticReport::new(EXAMPLE_REPORT).unwrap();
+
+        assert_eq!(report.rating(OXYGEN_CRITERIA), Ok(23));
+        assert_eq!(report.rating(CARBON_CRITERIA), Ok(10));
+        assert_eq!(report.life_support_rating(), Ok(230));
+    }
+
+    #[test]
+    fn tie_breaking() {
+        let report = DiagnosticReport::new("10\n01\n11\n00").unwrap();
 
-    println!("Life support rating: {}", oxygen_score * carbon_score);
+        // Every position is a tie
+        assert_eq!(report.rate(GAMMA_CRITERIA), 0b00);
+        assert_eq!(report.rate(EPSILON_CRITERIA), 0b11);
+        assert_eq!(report.rating(OXYGEN_CRITERIA), Ok(0b11));
+        assert_eq!(report.rating(CARBON_CRITERIA), Ok(0b00));
+        assert_eq!(
+            report.rating(BitCriteria::new(Criteria::MostCommon, false)),
+            Ok(0b00)
+        );
+        assert_eq!(
+            report.rating(BitCriteria::new(Criteria::LeastCommon, true)),
+            Ok(0b11)
+        );
+
+        // A bit nobody has can't be selected
+        let uniform = DiagnosticReport::new("10\n11").unwrap();
+        assert_eq!(
+            uniform.rating(BitCriteria::new(Criteria::LeastCommon, false)),
+            Err("No word matches the bit criteria")
+        );
+    }
+
+    #[test]
+    fn invalid_reports() {
+        assert_eq!(
+            DiagnosticReport::new("101\n11").unwrap_err(),
+            "Line 2 has 2 bits instead of 3"
+        );
+        assert_eq!(
+            DiagnosticReport::new("101\n121").unwrap_err(),
+            "Line 2: 121 isn't a binary string"
+        );
+
+        let wide = DiagnosticReport::new(&"1".repeat(64)).unwrap();
+        assert_eq!(wide.rate(GAMMA_CRITERIA), u64::MAX);
+        assert!(DiagnosticReport::new(&"1".repeat(65)).is_err());
+    }
 }
@@ -1,20 +1,56 @@
-use hashbrown::{HashMap, HashSet};
+use hashbrown::HashMap;
 
 use itertools::Itertools;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BingoRules {
+    pub board_size: usize,
+    pub diagonals: bool,
+}
+
+impl Default for BingoRules {
+    fn default() -> Self {
+        Self {
+            board_size: 5,
+            diagonals: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BingoWin {
+    pub board: usize,
+    // Index of the winning draw in the sequence of numbers called
+    pub draw: usize,
+    pub value: usize,
+    pub score: usize,
+}
+
+#[derive(Debug)]
 struct BingoBoard {
     value_to_location: HashMap<usize, (usize, usize)>,
-    column_hits: [u8; 5],
-    row_hits: [u8; 5],
+    column_hits: Vec<usize>,
+    row_hits: Vec<usize>,
+    // Main diagonal first, then the anti-diagonal
+    diagonal_hits: [usize; 2],
+    rules: BingoRules,
+    win: Option<BingoWin>,
 }
 
 impl BingoBoard {
-    fn final_score(&self, last_value: &usize) -> usize {
-        let mut board_score: usize = 0;
-        for value in self.value_to_location.keys() {
-            board_score += value;
+    fn new(rules: BingoRules) -> Self {
+        Self {
+            value_to_location: HashMap::new(),
+            column_hits: vec![0; rules.board_size],
+            row_hits: vec![0; rules.board_size],
+            diagonal_hits: [0; 2],
+            rules,
+            win: None,
         }
+    }
+
+    fn final_score(&self, last_value: &usize) -> usize {
+        let board_score: usize = self.value_to_location.keys().sum();
 
         board_score * last_value
     }
@@ -24,48 +60,65 @@ impl BingoBoard {
             .value_to_location
             .remove(value)
             .expect("Requested value is not present in this board!");
+        let size = self.rules.board_size;
 
         self.column_hits[col] += 1;
         self.row_hits[row] += 1;
-
-        for freq in self.column_hits.iter() {
-            if *freq >= 5 {
-                return Some(self.final_score(value));
+        if self.rules.diagonals {
+            if row == col {
+                self.diagonal_hits[0] += 1;
             }
-        }
-
-        for freq in self.row_hits.iter() {
-            if *freq >= 5 {
-                return Some(self.final_score(value));
+            if row + col == size - 1 {
+                self.diagonal_hits[1] += 1;
             }
         }
 
-        None
+        let complete = self.column_hits[col] >= size
+            || self.row_hits[row] >= size
+            || self.diagonal_hits.iter().any(|hits| *hits >= size);
+
+        if complete {
+            Some(self.final_score(value))
+        } else {
+            None
+        }
     }
 }
 
-pub fn part1(input: &str) {
-    let mut line_input = input.lines();
-    let numbers_called: Vec<usize> = line_input
-        .next()
-        .expect("Please give at least one line!")
-        .split(',')
-        .map(|val| {
-            val.parse::<usize>()
-                .expect("Didn't manage to parse the value!")
-        })
-        .collect();
-
-    let mut bingo_boards = Vec::<BingoBoard>::new();
-    let mut val_to_board = HashMap::<usize, HashSet<usize>>::new();
-
-    for line_chunk in line_input.chunks(6).into_iter() {
-        let mut next_board: BingoBoard = BingoBoard::default();
-        let board_idx = bingo_boards.len();
-
-        for (line, data) in line_chunk.enumerate() {
-            if line > 0 {
-                for (col, int_str) in data
+struct BingoGame {
+    numbers_called: Vec<usize>,
+    boards: Vec<BingoBoard>,
+    val_to_board: HashMap<usize, Vec<usize>>,
+    winners: Vec<BingoWin>,
+}
+
+impl BingoGame {
+    fn new(input: &str, rules: BingoRules) -> Self {
+        let mut line_input = input.lines();
+        let numbers_called: Vec<usize> = line_input
+            .next()
+            .expect("Please give at least one line!")
+            .trim()
+            .split(',')
+            .map(|val| {
+                val.parse::<usize>()
+                    .expect("Didn't manage to parse the value!")
+            })
+            .collect();
+
+        let mut boards = Vec::<BingoBoard>::new();
+        let mut val_to_board = HashMap::<usize, Vec<usize>>::new();
+
+        for board_lines in line_input
+            .filter(|line| !line.trim().is_empty())
+            .chunks(rules.board_size)
+            .into_iter()
+        {
+            let mut next_board = BingoBoard::new(rules);
+            let board_idx = boards.len();
+
+            for (row, data) in board_lines.enumerate() {
+                for (col, value) in data
                     .split_whitespace()
                     .map(|val| {
                         val.parse::<usize>()
@@ -73,47 +126,205 @@ pub fn part1(input: &str) {
                     })
                     .enumerate()
                 {
-                    if let Some(val_set) = val_to_board.get_mut(&int_str) {
-                        val_set.insert(board_idx);
-                    } else {
-                        val_to_board.insert(int_str, HashSet::from([board_idx]));
+                    assert!(col < rules.board_size, "Board row is too long!");
+                    // Boards are added in order, so a repeated value is already the last entry
+                    let value_boards = val_to_board.entry(value).or_default();
+                    if value_boards.last() != Some(&board_idx) {
+                        value_boards.push(board_idx);
                     }
-
-                    next_board
-                        .value_to_location
-                        .insert(int_str, (line - 1, col));
+                    next_board.value_to_location.insert(value, (row, col));
                 }
             }
+
+            boards.push(next_board);
         }
 
-        bingo_boards.push(next_board);
+        Self {
+            numbers_called,
+            boards,
+            val_to_board,
+            winners: Vec::new(),
+        }
     }
 
-    let mut last_score: usize = 0;
-    let mut first_score: Option<usize> = None;
-    let mut winning_boards = HashSet::<usize>::default();
-
-    for num in numbers_called {
-        let matching_boards = val_to_board
-            .get(&num)
-            .expect("We are trying to retrieve a value that we never inserted!")
-            .difference(&winning_boards);
-        let mut curr_winning_boards = HashSet::<usize>::default();
-        for board_idx in matching_boards {
-            if let Some(final_score) = bingo_boards[*board_idx].mark_value(&num) {
-                curr_winning_boards.insert(*board_idx);
-                if first_score.is_none() {
-                    first_score = Some(final_score);
+    pub fn play(&mut self) {
+        for (draw, num) in self.numbers_called.iter().enumerate() {
+            let Some(matching_boards) = self.val_to_board.get(num) else {
+                continue;
+            };
+
+            for board_idx in matching_boards.iter() {
+                let board = &mut self.boards[*board_idx];
+                if board.win.is_some() {
+                    continue;
+                }
+
+                if let Some(score) = board.mark_value(num) {
+                    let win = BingoWin {
+                        board: *board_idx,
+                        draw,
+                        value: *num,
+                        score,
+                    };
+                    board.win = Some(win);
+                    self.winners.push(win);
                 }
-                last_score = final_score;
             }
         }
-        winning_boards.extend(&curr_winning_boards);
     }
 
-    println!(
-        "The first board to win has this score: {}; The last board to win has this score: {}",
-        first_score.expect("This cannot be empty, really."),
-        last_score
-    );
+    pub fn winning_order(&self) -> &[BingoWin] {
+        &self.winners
+    }
+
+    pub fn board_win(&self, board: usize) -> Option<BingoWin> {
+        self.boards.get(board).and_then(|b| b.win)
+    }
+}
+
+pub fn part1(input: &str) {
+    let mut game = BingoGame::new(input, BingoRules::default());
+    game.play();
+
+    match game.winning_order().first() {
+        Some(win) => println!(
+            "The first board to win is #{} at draw {} with this score: {}",
+            win.board, win.draw, win.score
+        ),
+        None => println!("No board won, the squid wins."),
+    }
+}
+
+pub fn part2(input: &str) {
+    let mut game = BingoGame::new(input, BingoRules::default());
+    game.play();
+
+    match game.winning_order().last() {
+        Some(win) => println!(
+            "The last board to win is #{} at draw {} with this score: {}",
+            win.board, win.draw, win.score
+        ),
+        None => println!("No board won, the squid wins."),
+    }
+
+    for board in 0..game.boards.len() {
+        if game.board_win(board).is_none() {
+            println!("Board #{} never won", board);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static INPUT_STRING: &str =
+        "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+        22 13 17 11  0
+         8  2 23  4 24
+        21  9 14 16  7
+         6 10  3 18  5
+         1 12 20 15 19
+
+         3 15  0  2 22
+         9 18 13 17  5
+        19  8  7 25 23
+        20 11 10 24  4
+        14 21 16 12  6
+
+        14 21 17 24  4
+        10 16 15  9 19
+        18  8 23 26 20
+        22 11 13  6  5
+         2  0 12  3  7";
+
+    #[test]
+    fn winning_order() {
+        let mut game = BingoGame::new(INPUT_STRING, BingoRules::default());
+        game.play();
+
+        let order: Vec<usize> = game.winning_order().iter().map(|w| w.board).collect();
+        assert_eq!(order, vec![2, 0, 1]);
+
+        let first = game.winning_order()[0];
+        assert_eq!(first.value, 24);
+        assert_eq!(first.draw, 11);
+        assert_eq!(first.score, 4512);
+
+        let last = game.board_win(1).unwrap();
+        assert_eq!(last.value, 13);
+        assert_eq!(last.score, 1924);
+
+        assert!(game.board_win(3).is_none());
+    }
+
+    #[test]
+    fn diagonal_wins() {
+        let input_string = "1,5,9,3,7
+
+        1 2 3
+        4 5 6
+        7 8 9
+
+        2 8 3
+        9 5 4
+        7 6 1";
+
+        let rules = BingoRules {
+            board_size: 3,
+            diagonals: true,
+        };
+        let mut game = BingoGame::new(input_string, rules);
+        game.play();
+
+        let wins = game.winning_order();
+        assert_eq!(wins.len(), 2);
+        assert_eq!(wins[0].board, 0);
+        assert_eq!(wins[0].draw, 2);
+        assert_eq!(wins[0].score, (2 + 3 + 4 + 6 + 7 + 8) * 9);
+        assert_eq!(wins[1].board, 1);
+        assert_eq!(wins[1].draw, 4);
+        assert_eq!(wins[1].score, (2 + 8 + 6 + 4) * 7);
+
+        let mut no_diagonals = BingoGame::new(
+            input_string,
+            BingoRules {
+                diagonals: false,
+                ..rules
+            },
+        );
+        no_diagonals.play();
+        assert!(no_diagonals.winning_order().is_empty());
+    }
+
+    #[test]
+    fn repeated_board_values() {
+        let input_string = "5,6,3
+
+        1 5 5
+        4 2 6
+        7 8 3
+
+        9 9 9
+        9 9 9
+        9 9 9";
+
+        let rules = BingoRules {
+            board_size: 3,
+            diagonals: false,
+        };
+        let mut game = BingoGame::new(input_string, rules);
+        assert_eq!(game.val_to_board[&5], vec![0]);
+        assert_eq!(game.val_to_board[&9], vec![1]);
+
+        // Repeated values are a single cell, the last one in the board
+        game.play();
+        let wins = game.winning_order();
+        assert_eq!(wins.len(), 1);
+        assert_eq!(wins[0].board, 0);
+        assert_eq!(wins[0].value, 3);
+        assert_eq!(wins[0].draw, 2);
+        assert_eq!(wins[0].score, (1 + 4 + 2 + 7 + 8) * 3);
+    }
 }
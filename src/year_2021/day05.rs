@@ -1,3 +1,6 @@
+use std::env::temp_dir;
+use std::fs;
+
 use hashbrown::HashMap;
 
 use nom::bytes::complete::tag;
@@ -6,6 +9,13 @@ use nom::sequence::separated_pair;
 use nom::IResult;
 
 use crate::aoc_lib::jazz_parser;
+use crate::aoc_lib::params::{DayParams, Param};
+
+// Largest grid that gets allocated in full to count the overlaps, or rendered as a heatmap
+const DENSE_GRID_LIMIT: u64 = 1 << 24;
+
+const EXPORT_HEATMAP: Param<bool> = Param::new("export", false);
+pub const PARAMS: DayParams = &[&EXPORT_HEATMAP];
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 enum Direction {
@@ -14,14 +24,14 @@ enum Direction {
     Diagonal,
 }
 
-#[derive(PartialEq, Debug, Default, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Default, Clone, Copy)]
 struct Point {
-    x: usize,
-    y: usize,
+    x: i64,
+    y: i64,
 }
 
 impl Point {
-    fn new(x: usize, y: usize) -> Point {
+    fn new(x: i64, y: i64) -> Point {
         Point { x, y }
     }
 }
@@ -42,50 +52,68 @@ impl Line {
         } else {
             Direction::Diagonal
         };
-        let (actual_start, actual_end) = match line_dir {
-            Direction::Vertical => {
-                if start.y > end.y {
-                    (end, start)
-                } else {
-                    (start, end)
-                }
-            }
-            _ => {
-                if start.x > end.x {
-                    (end, start)
-                } else {
-                    (start, end)
-                }
-            }
-        };
-
-        if line_dir == Direction::Diagonal {
-            let x_delta = if actual_end.x < actual_start.x {
-                actual_start.x - actual_end.x
-            } else {
-                actual_end.x - actual_start.x
-            };
-            let y_delta = if actual_end.y < actual_start.y {
-                actual_start.y - actual_end.y
-            } else {
-                actual_end.y - actual_start.y
-            };
-            assert_eq!(
-                x_delta, y_delta,
-                "Diagonal lines should have a 45 degree slope!"
-            );
-        }
 
         Line {
-            start: *actual_start,
-            end: *actual_end,
+            start: *start,
+            end: *end,
             direction: line_dir,
         }
     }
+
+    pub fn points(&self) -> LinePoints {
+        let delta_x = (self.end.x - self.start.x).abs();
+        let delta_y = -(self.end.y - self.start.y).abs();
+
+        LinePoints {
+            current: Some(self.start),
+            end: self.end,
+            delta: (delta_x, delta_y),
+            step: (
+                (self.end.x - self.start.x).signum(),
+                (self.end.y - self.start.y).signum(),
+            ),
+            error: delta_x + delta_y,
+        }
+    }
+}
+
+// Bresenham's rasterisation, which works for any slope and always includes both ends
+struct LinePoints {
+    current: Option<Point>,
+    end: Point,
+    delta: (i64, i64),
+    step: (i64, i64),
+    error: i64,
+}
+
+impl Iterator for LinePoints {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let point = self.current?;
+
+        self.current = if point == self.end {
+            None
+        } else {
+            let mut next = point;
+            let double_error = 2 * self.error;
+            if double_error >= self.delta.1 {
+                self.error += self.delta.1;
+                next.x += self.step.0;
+            }
+            if double_error <= self.delta.0 {
+                self.error += self.delta.0;
+                next.y += self.step.1;
+            }
+            Some(next)
+        };
+
+        Some(point)
+    }
 }
 
 fn point(input: &str) -> IResult<&str, Point> {
-    let parser = separated_pair(jazz_parser::usize, tag(","), jazz_parser::usize);
+    let parser = separated_pair(jazz_parser::i64, tag(","), jazz_parser::i64);
     map(parser, |s| {
         // FIXME: unwrap() may panic if the value is out of range
         Point::new(s.0, s.1)
@@ -100,79 +128,210 @@ fn segment(input: &str) -> IResult<&str, Line> {
     })(input)
 }
 
-pub fn part1(input: &str) {
-    let intersections = intersection_check(input, false);
-    println!("Number of intersections: {}", intersections);
+#[derive(PartialEq, Debug, Clone, Copy)]
+struct Bounds {
+    min: Point,
+    max: Point,
 }
 
-pub fn part2(input: &str) {
-    let intersections = intersection_check(input, true);
-    println!("Number of intersections: {}", intersections);
+impl Bounds {
+    fn of_lines(lines: &[Line]) -> Option<Bounds> {
+        let points = lines.iter().flat_map(|l| [l.start, l.end]);
+
+        points.fold(None, |bounds: Option<Bounds>, p| {
+            Some(match bounds {
+                None => Bounds { min: p, max: p },
+                Some(b) => Bounds {
+                    min: Point::new(b.min.x.min(p.x), b.min.y.min(p.y)),
+                    max: Point::new(b.max.x.max(p.x), b.max.y.max(p.y)),
+                },
+            })
+        })
+    }
+
+    fn width(&self) -> u64 {
+        self.max.x.abs_diff(self.min.x) + 1
+    }
+
+    fn height(&self) -> u64 {
+        self.max.y.abs_diff(self.min.y) + 1
+    }
+
+    fn contains(&self, p: &Point) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x) && (self.min.y..=self.max.y).contains(&p.y)
+    }
+
+    // Position of the point in a row-major grid covering the bounds, if it's inside them
+    fn flat_index(&self, p: &Point) -> Option<usize> {
+        if !self.contains(p) {
+            return None;
+        }
+
+        let col = p.x.abs_diff(self.min.x);
+        let row = p.y.abs_diff(self.min.y);
+        Some(usize::try_from(row * self.width() + col).unwrap())
+    }
 }
 
-fn intersection_check(input: &str, enable_diagonals: bool) -> i32 {
-    let line_input = input.lines();
+// How many lines go through every point. A dense grid is used whenever the bounds are small enough
+enum Occupation {
+    Sparse(HashMap<Point, usize>),
+    Dense(Vec<usize>),
+}
+
+struct VentMap {
+    bounds: Option<Bounds>,
+    occupation: Occupation,
+    overlaps: usize,
+}
+
+impl VentMap {
+    pub fn sparse() -> VentMap {
+        VentMap {
+            bounds: None,
+            occupation: Occupation::Sparse(HashMap::new()),
+            overlaps: 0,
+        }
+    }
 
-    let mut occupation_map = HashMap::<(usize, usize), usize>::new();
-    let mut intersecting_points = 0;
+    // Every point marked later on has to be within the bounds
+    pub fn dense(bounds: Bounds) -> VentMap {
+        let cells = usize::try_from(bounds.width() * bounds.height()).unwrap();
 
-    for line in line_input {
-        let segment = segment(line).expect("Didn't manage to parse the input!").1;
+        VentMap {
+            bounds: Some(bounds),
+            occupation: Occupation::Dense(vec![0; cells]),
+            overlaps: 0,
+        }
+    }
 
-        match segment.direction {
-            Direction::Horizontal => {
-                for col in segment.start.x..=segment.end.x {
-                    let int_count = occupation_map.entry((col, segment.end.y)).or_insert(0);
-                    *int_count += 1;
-                    if *int_count == 2 {
-                        intersecting_points += 1;
-                    }
-                }
+    pub fn for_lines(lines: &[Line]) -> VentMap {
+        match Bounds::of_lines(lines) {
+            Some(bounds) if bounds.width() * bounds.height() <= DENSE_GRID_LIMIT => {
+                VentMap::dense(bounds)
             }
-            Direction::Vertical => {
-                for row in segment.start.y..=segment.end.y {
-                    let int_count = occupation_map.entry((segment.end.x, row)).or_insert(0);
-                    *int_count += 1;
-                    if *int_count == 2 {
-                        intersecting_points += 1;
-                    }
-                }
+            _ => VentMap::sparse(),
+        }
+    }
+
+    pub fn mark(&mut self, p: Point) {
+        let int_count = match (&mut self.occupation, &self.bounds) {
+            (Occupation::Dense(counts), Some(bounds)) => {
+                let idx = bounds
+                    .flat_index(&p)
+                    .expect("Points marked on a dense grid have to be within its bounds");
+                &mut counts[idx]
+            }
+            (Occupation::Sparse(counts), _) => counts.entry(p).or_insert(0),
+            (Occupation::Dense(_), None) => unreachable!("Dense grids always have bounds"),
+        };
+
+        *int_count += 1;
+        if *int_count == 2 {
+            self.overlaps += 1;
+        }
+    }
+
+    pub fn count_at(&self, p: &Point) -> usize {
+        match (&self.occupation, &self.bounds) {
+            (Occupation::Dense(counts), Some(bounds)) => {
+                bounds.flat_index(p).map_or(0, |idx| counts[idx])
             }
-            Direction::Diagonal => {
-                if enable_diagonals {
-                    let mut row = segment.start.y;
-                    let row_direction = segment.end.y > row;
-                    let mut col = segment.start.x;
-
-                    while col <= segment.end.x {
-                        let int_count = occupation_map.entry((col, row)).or_insert(0);
-                        *int_count += 1;
-                        if *int_count == 2 {
-                            intersecting_points += 1;
-                        }
-
-                        col += 1;
-                        row = if row_direction {
-                            row + 1
-                        } else {
-                            row.checked_sub(1).unwrap_or_default()
-                        };
-                    }
-                }
+            (Occupation::Sparse(counts), _) => counts.get(p).cloned().unwrap_or_default(),
+            (Occupation::Dense(_), None) => unreachable!("Dense grids always have bounds"),
+        }
+    }
+
+    // Heatmap like the ones in the puzzle, with '+' for points with more than 9 lines. Areas too
+    // big for a dense grid are too big to render as well
+    pub fn render_heatmap(&self, bounds: &Bounds) -> Result<String, &'static str> {
+        if bounds.width() * bounds.height() > DENSE_GRID_LIMIT {
+            return Err("The area is too large to render as a heatmap");
+        }
+        if let Some(map_bounds) = &self.bounds {
+            if !map_bounds.contains(&bounds.min) || !map_bounds.contains(&bounds.max) {
+                return Err("The area goes past the bounds of the map");
             }
         }
+
+        Ok((bounds.min.y..=bounds.max.y)
+            .map(|y| {
+                (bounds.min.x..=bounds.max.x)
+                    .map(|x| match self.count_at(&Point::new(x, y)) {
+                        0 => '.',
+                        count @ 1..=9 => char::from_digit(count as u32, 10).unwrap(),
+                        _ => '+',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n"))
     }
+}
 
-    intersecting_points
+fn vent_lines(input: &str) -> Vec<Line> {
+    input
+        .lines()
+        .map(|line| {
+            segment(line.trim())
+                .expect("Didn't manage to parse the input!")
+                .1
+        })
+        .collect()
+}
+
+fn draw_vents(lines: &[Line], enable_diagonals: bool) -> VentMap {
+    let mut vent_map = VentMap::for_lines(lines);
+
+    for segment in lines
+        .iter()
+        .filter(|l| enable_diagonals || l.direction != Direction::Diagonal)
+    {
+        for p in segment.points() {
+            vent_map.mark(p);
+        }
+    }
+
+    vent_map
+}
+
+fn intersection_check(input: &str, enable_diagonals: bool) -> usize {
+    draw_vents(&vent_lines(input), enable_diagonals).overlaps
+}
+
+pub fn part1(input: &str) {
+    let intersections = intersection_check(input, false);
+    println!("Number of intersections: {}", intersections);
+}
+
+pub fn part2(input: &str) {
+    let lines = vent_lines(input);
+    let vent_map = draw_vents(&lines, true);
+    println!("Number of intersections: {}", vent_map.overlaps);
+
+    if !EXPORT_HEATMAP.get() {
+        return;
+    }
+    let heatmap = match Bounds::of_lines(&lines).map(|bounds| vent_map.render_heatmap(&bounds)) {
+        Some(Ok(heatmap)) => heatmap,
+        Some(Err(e)) => {
+            println!("Can't export the overlap heatmap: {}", e);
+            return;
+        }
+        None => return,
+    };
+    let heatmap_path = temp_dir().join("day05_heatmap.txt");
+    match fs::write(&heatmap_path, heatmap) {
+        Ok(()) => println!("Overlap heatmap exported to {}", heatmap_path.display()),
+        Err(e) => println!("Failed to export the overlap heatmap: {}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn non_diagonal() {
-        let input_string = "0,9 -> 5,9
+    const EXAMPLE_VENTS: &str = "0,9 -> 5,9
 8,0 -> 0,8
 9,4 -> 3,4
 2,2 -> 2,1
@@ -183,26 +342,141 @@ mod tests {
 0,0 -> 8,8
 5,5 -> 8,2";
 
-        let intersections = intersection_check(input_string, false);
+    #[test]
+    fn non_diagonal() {
+        let intersections = intersection_check(EXAMPLE_VENTS, false);
 
         assert_eq!(intersections, 5);
     }
 
     #[test]
     fn diagonal() {
-        let input_string = "0,9 -> 5,9
-8,0 -> 0,8
-9,4 -> 3,4
-2,2 -> 2,1
-7,0 -> 7,4
-6,4 -> 2,0
-0,9 -> 2,9
-3,4 -> 1,4
-0,0 -> 8,8
-5,5 -> 8,2";
-
-        let intersections = intersection_check(input_string, true);
+        let intersections = intersection_check(EXAMPLE_VENTS, true);
 
         assert_eq!(intersections, 12);
     }
+
+    #[test]
+    fn heatmap() {
+        let lines = vent_lines(EXAMPLE_VENTS);
+        let bounds = Bounds::of_lines(&lines).unwrap();
+
+        assert_eq!(
+            draw_vents(&lines, true).render_heatmap(&bounds).unwrap(),
+            "1.1....11.\n\
+             .111...2..\n\
+             ..2.1.111.\n\
+             ...1.2.2..\n\
+             .112313211\n\
+             ...1.2....\n\
+             ..1...1...\n\
+             .1.....1..\n\
+             1.......1.\n\
+             222111...."
+        );
+    }
+
+    #[test]
+    fn any_slope() {
+        let (_, line) = segment("0,0 -> 4,2").unwrap();
+        let points: Vec<(i64, i64)> = line.points().map(|p| (p.x, p.y)).collect();
+        assert_eq!(points, vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+
+        for input in [
+            "3,-7 -> -5,2",
+            "-2,-2 -> -2,-9",
+            "10,4 -> -3,-1",
+            "1,1 -> 1,1",
+        ] {
+            let (_, line) = segment(input).unwrap();
+            let points: Vec<Point> = line.points().collect();
+            let longest_delta = (line.end.x - line.start.x)
+                .abs()
+                .max((line.end.y - line.start.y).abs());
+
+            assert_eq!(points.len() as i64, longest_delta + 1);
+            assert_eq!(points.first(), Some(&line.start));
+            assert_eq!(points.last(), Some(&line.end));
+            for (a, b) in points.iter().zip(points.iter().skip(1)) {
+                assert!((a.x - b.x).abs() <= 1 && (a.y - b.y).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_coordinates() {
+        let input_string = "-3,-3 -> 3,3
+-3,3 -> 3,-3
+0,-5 -> 0,5
+-4,1 -> 4,-3";
+
+        let lines = vent_lines(input_string);
+        let bounds = Bounds::of_lines(&lines).unwrap();
+        assert_eq!(bounds.min, Point::new(-4, -5));
+
+        let dense = draw_vents(&lines, true);
+        let mut sparse = VentMap::sparse();
+        for p in lines.iter().flat_map(|l| l.points()) {
+            sparse.mark(p);
+        }
+
+        assert!(matches!(dense.occupation, Occupation::Dense(_)));
+        assert_eq!(dense.overlaps, sparse.overlaps);
+        assert_eq!(dense.count_at(&Point::new(0, 0)), 3);
+        assert_eq!(
+            dense.render_heatmap(&bounds).unwrap(),
+            sparse.render_heatmap(&bounds).unwrap()
+        );
+        assert_eq!(intersection_check(input_string, false), 0);
+    }
+
+    #[test]
+    fn outside_dense_bounds() {
+        let lines = vent_lines("0,0 -> 2,2\n0,2 -> 2,0");
+        let bounds = Bounds::of_lines(&lines).unwrap();
+        let vent_map = draw_vents(&lines, true);
+
+        assert!(matches!(vent_map.occupation, Occupation::Dense(_)));
+        assert_eq!(vent_map.count_at(&Point::new(1, 1)), 2);
+        // Mirrored or past the end of the grid, these used to hit other points or panic
+        for (x, y) in [(-1, 1), (1, -2), (3, 0), (0, 3), (5, 5), (-10, -10)] {
+            assert_eq!(vent_map.count_at(&Point::new(x, y)), 0);
+        }
+
+        assert_eq!(bounds.flat_index(&Point::new(2, 2)), Some(8));
+        assert_eq!(bounds.flat_index(&Point::new(-1, 0)), None);
+
+        let wider = Bounds {
+            min: Point::new(-1, -1),
+            max: Point::new(3, 3),
+        };
+        assert!(vent_map.render_heatmap(&wider).is_err());
+        assert_eq!(vent_map.render_heatmap(&bounds).unwrap(), "1.1\n.2.\n1.1");
+    }
+
+    #[test]
+    fn large_area() {
+        let input_string = "0,0 -> 200000,200000
+0,200000 -> 200000,0
+100000,-5 -> 100000,5";
+
+        let lines = vent_lines(input_string);
+        let bounds = Bounds::of_lines(&lines).unwrap();
+        let vent_map = draw_vents(&lines, true);
+
+        assert!(matches!(vent_map.occupation, Occupation::Sparse(_)));
+        assert_eq!(vent_map.overlaps, 1);
+        assert_eq!(vent_map.count_at(&Point::new(100000, 100000)), 2);
+        assert_eq!(vent_map.count_at(&Point::new(100000, 0)), 1);
+        assert!(vent_map.render_heatmap(&bounds).is_err());
+
+        let corner = Bounds {
+            min: Point::new(99998, 99998),
+            max: Point::new(100002, 100002),
+        };
+        assert_eq!(
+            vent_map.render_heatmap(&corner).unwrap(),
+            "1...1\n.1.1.\n..2..\n.1.1.\n1...1"
+        );
+    }
 }
@@ -1,48 +1,176 @@
-use std::{collections::VecDeque, vec};
+use std::env::temp_dir;
+use std::fs;
 
-fn lanternfish_evolution(input: &str, time_horizon: u16) -> u64 {
-    let start_lives = input.split(',');
+use num::{BigUint, Zero};
 
-    let mut starting_lifetimes_count = vec![0u64; 9];
-    for lifetime in start_lives {
-        let life_num: usize = lifetime
-            .trim()
-            .parse()
-            .expect("We got a string in the CSV that's not a number");
-        starting_lifetimes_count[life_num] += 1;
+use crate::aoc_lib::big_matrix::{self, BigMatrix};
+use crate::aoc_lib::params::{DayParams, Param};
+
+const DAYS: Param<u64> = Param::new("days", 80);
+const MANY_DAYS: Param<u64> = Param::new("days2", 256);
+const EXPORT_GROWTH: Param<bool> = Param::new("export", false);
+pub const PARAMS: DayParams = &[&DAYS, &MANY_DAYS, &EXPORT_GROWTH];
+
+// Fish whose timer goes below zero restart at `reset_timer` and spawn a new fish at `spawn_timer`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct LanternfishModel {
+    reset_timer: usize,
+    spawn_timer: usize,
+}
+
+impl Default for LanternfishModel {
+    fn default() -> Self {
+        LanternfishModel::new(6, 8)
+    }
+}
+
+impl LanternfishModel {
+    pub fn new(reset_timer: usize, spawn_timer: usize) -> LanternfishModel {
+        LanternfishModel {
+            reset_timer,
+            spawn_timer,
+        }
+    }
+
+    // Amount of different timer values a fish can have
+    pub fn timer_states(&self) -> usize {
+        self.reset_timer.max(self.spawn_timer) + 1
+    }
+
+    // Histogram with the amount of fish for each timer value
+    pub fn parse_school(&self, input: &str) -> Vec<BigUint> {
+        let mut histogram = vec![BigUint::zero(); self.timer_states()];
+        for lifetime in input.split(',') {
+            let life_num: usize = lifetime
+                .trim()
+                .parse()
+                .expect("We got a string in the CSV that's not a number");
+            let timer_count = histogram
+                .get_mut(life_num)
+                .expect("We got a fish timer that's longer than any the model allows");
+            *timer_count += 1u32;
+        }
+
+        histogram
+    }
+
+    // The element at (row, col) is how many fish with timer `row` a fish with timer `col` becomes
+    // after one day
+    fn transition(&self) -> BigMatrix {
+        let states = self.timer_states();
+        let mut transition = vec![vec![BigUint::zero(); states]; states];
+        for timer in 1..states {
+            transition[timer - 1][timer] += 1u32;
+        }
+        transition[self.reset_timer][0] += 1u32;
+        transition[self.spawn_timer][0] += 1u32;
+
+        transition
+    }
+
+    pub fn histogram_at(&self, school: &[BigUint], day: u64) -> Vec<BigUint> {
+        big_matrix::apply(&big_matrix::power(&self.transition(), day), school)
+    }
+
+    pub fn population_at(&self, school: &[BigUint], day: u64) -> BigUint {
+        self.histogram_at(school, day).into_iter().sum()
+    }
+
+    // Histograms for every day, starting with the school as it is given
+    pub fn daily_histograms(&self, school: &[BigUint]) -> DailyHistograms {
+        DailyHistograms {
+            model: *self,
+            histogram: school.to_vec(),
+        }
     }
+}
+
+struct DailyHistograms {
+    model: LanternfishModel,
+    histogram: Vec<BigUint>,
+}
+
+impl Iterator for DailyHistograms {
+    type Item = Vec<BigUint>;
 
-    let mut lives_count: VecDeque<u64> = starting_lifetimes_count.into_iter().collect();
+    fn next(&mut self) -> Option<Self::Item> {
+        let today = self.histogram.clone();
 
-    for _ in 0..time_horizon {
-        lives_count.rotate_left(1);
-        lives_count[6] += lives_count[8];
+        self.histogram.rotate_left(1);
+        let spawning = self.histogram.pop().unwrap();
+        self.histogram.push(BigUint::zero());
+        self.histogram[self.model.reset_timer] += &spawning;
+        self.histogram[self.model.spawn_timer] += spawning;
+
+        Some(today)
     }
+}
+
+fn lanternfish_evolution(input: &str, time_horizon: u64) -> BigUint {
+    let model = LanternfishModel::default();
+    let school = model.parse_school(input);
 
-    lives_count.into_iter().sum()
+    model.population_at(&school, time_horizon)
+}
+
+// Growth curve as CSV, with the total population and the fish count for every timer each day
+fn growth_curve_csv(histograms: impl Iterator<Item = Vec<BigUint>>) -> String {
+    let mut csv: Vec<String> = Vec::new();
+    for (day, histogram) in histograms.enumerate() {
+        if day == 0 {
+            let timers = (0..histogram.len()).map(|timer| format!(",timer_{}", timer));
+            csv.push(format!("day,population{}", timers.collect::<String>()));
+        }
+
+        let total: BigUint = histogram.iter().sum();
+        let counts = histogram.iter().map(|count| format!(",{}", count));
+        csv.push(format!("{},{}{}", day, total, counts.collect::<String>()));
+    }
+
+    csv.join("\n")
 }
 
 pub fn part1(input: &str) {
-    let final_population = lanternfish_evolution(input, 80);
-    println!("Final population: {}", final_population);
+    let days = DAYS.get();
+    let final_population = lanternfish_evolution(input, days);
+    println!("Final population after {} days: {}", days, final_population);
 }
 
 pub fn part2(input: &str) {
-    let final_population = lanternfish_evolution(input, 256);
-    println!("Final population: {}", final_population);
+    let days = MANY_DAYS.get();
+    let final_population = lanternfish_evolution(input, days);
+    println!("Final population after {} days: {}", days, final_population);
+
+    if !EXPORT_GROWTH.get() {
+        return;
+    }
+
+    let model = LanternfishModel::default();
+    let histograms = model
+        .daily_histograms(&model.parse_school(input))
+        .take(usize::try_from(days).unwrap() + 1);
+    let csv_path = temp_dir().join("day06_growth.csv");
+    match fs::write(&csv_path, growth_curve_csv(histograms)) {
+        Ok(()) => println!("Growth curve exported to {}", csv_path.display()),
+        Err(e) => println!("Failed to export the growth curve: {}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn histogram(counts: &[u64]) -> Vec<BigUint> {
+        counts.iter().map(|&count| BigUint::from(count)).collect()
+    }
+
     #[test]
     fn normal_conditions() {
         let input_string = "3,4,3,1,2";
 
         let population_count = lanternfish_evolution(input_string, 80);
 
-        assert_eq!(population_count, 5934u64);
+        assert_eq!(population_count, BigUint::from(5934u64));
     }
 
     #[test]
@@ -51,6 +179,73 @@ mod tests {
 
         let population_count = lanternfish_evolution(input_string, 256);
 
-        assert_eq!(population_count, 26984457539u64);
+        assert_eq!(population_count, BigUint::from(26984457539u64));
+    }
+
+    #[test]
+    fn daily_histograms() {
+        let model = LanternfishModel::default();
+        let school = model.parse_school("3,4,3,1,2");
+
+        let mut days = model.daily_histograms(&school);
+        assert_eq!(days.next(), Some(histogram(&[0, 1, 1, 2, 1, 0, 0, 0, 0])));
+        assert_eq!(days.next(), Some(histogram(&[1, 1, 2, 1, 0, 0, 0, 0, 0])));
+        assert_eq!(days.next(), Some(histogram(&[1, 2, 1, 0, 0, 0, 1, 0, 1])));
+
+        // Stepping day by day and exponentiating the transition agree
+        for (day, daily) in model.daily_histograms(&school).enumerate().take(300) {
+            assert_eq!(daily, model.histogram_at(&school, day as u64));
+        }
+
+        let far_future = model.population_at(&school, 10_000);
+        assert!(far_future.bits() > 64);
+        assert_eq!(
+            far_future,
+            model
+                .daily_histograms(&school)
+                .nth(10_000)
+                .unwrap()
+                .iter()
+                .sum()
+        );
+    }
+
+    #[test]
+    fn custom_timers() {
+        // Fish reset to 2 and newborns start at 3, simulated one fish at a time
+        let model = LanternfishModel::new(2, 3);
+        let mut fish: Vec<usize> = vec![0, 3, 1];
+        let school = model.parse_school("0,3,1");
+
+        for day in 0..25 {
+            assert_eq!(model.population_at(&school, day), BigUint::from(fish.len()));
+
+            let newborns = fish.iter().filter(|&&timer| timer == 0).count();
+            for timer in fish.iter_mut() {
+                *timer = if *timer == 0 { 2 } else { *timer - 1 };
+            }
+            fish.extend(std::iter::repeat_n(3, newborns));
+        }
+
+        // Fish that spawn clones of themselves
+        let doubling = LanternfishModel::new(0, 0);
+        let school = doubling.parse_school("0");
+        assert_eq!(
+            doubling.population_at(&school, 100),
+            BigUint::from(1u32) << 100
+        );
+    }
+
+    #[test]
+    fn growth_curve() {
+        let model = LanternfishModel::default();
+        let school = model.parse_school("3,4,3,1,2");
+
+        assert_eq!(
+            growth_curve_csv(model.daily_histograms(&school).take(2)),
+            "day,population,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5,timer_6,timer_7,timer_8\n\
+             0,5,0,1,1,2,1,0,0,0,0\n\
+             1,5,1,1,2,1,0,0,0,0,0"
+        );
     }
 }
@@ -1,91 +1,154 @@
-use std::convert::TryInto;
-
 use itertools::sorted;
 
-fn midpoint_binary_search(
-    sorted_positions: &[u32],
-    consumption_function: fn(&u32, &u32) -> u32,
-) -> u32 {
-    let mut left_location: u32 = *sorted_positions
-        .first()
-        .expect("Expecting this vector to be larger than 0");
-    let mut right_location: u32 = *sorted_positions
-        .last()
-        .expect("Expecting this vector to be larger than 0");
-
-    let mut left_consumption: u64 = sorted_positions.iter().fold(0u64, |mut sum, val| {
-        sum += u64::from(consumption_function(val, &left_location));
-        sum
-    });
-    let mut right_consumption: u64 = sorted_positions.iter().fold(0u64, |mut sum, val| {
-        sum += u64::from(consumption_function(val, &right_location));
-        sum
-    });
-
-    while left_consumption != right_consumption {
-        let mid_location = left_location + ((right_location - left_location) / 2);
-
-        if left_consumption <= right_consumption {
-            right_location = mid_location;
-            right_consumption = sorted_positions.iter().fold(0u64, |mut sum, val| {
-                sum += u64::from(consumption_function(val, &right_location));
-                sum
-            });
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Alignment {
+    position: u32,
+    fuel: u64,
+}
+
+// Fuel needed to move a crab, with `align` finding where all the crabs should meet. Any function
+// from start and target to fuel is a cost model, optimised with a generic search that only
+// requires the total fuel to be convex over the target position
+trait CostModel {
+    fn cost(&self, start: u32, target: u32) -> u64;
+
+    fn total_fuel(&self, sorted_positions: &[u32], target: u32) -> u64 {
+        sorted_positions
+            .iter()
+            .map(|&start| self.cost(start, target))
+            .sum()
+    }
+
+    fn align(&self, sorted_positions: &[u32]) -> Option<Alignment> {
+        convex_search(self, sorted_positions)
+    }
+}
+
+impl<F: Fn(u32, u32) -> u64> CostModel for F {
+    fn cost(&self, start: u32, target: u32) -> u64 {
+        self(start, target)
+    }
+}
+
+// Ternary search over the positions range. The leftmost optimum is returned in case of ties
+fn convex_search<M: CostModel + ?Sized>(model: &M, sorted_positions: &[u32]) -> Option<Alignment> {
+    let mut left_location = *sorted_positions.first()?;
+    let mut right_location = *sorted_positions.last()?;
+
+    while right_location - left_location > 2 {
+        let third = (right_location - left_location) / 3;
+        let left_third = left_location + third;
+        let right_third = right_location - third;
+
+        let left_fuel = model.total_fuel(sorted_positions, left_third);
+        let right_fuel = model.total_fuel(sorted_positions, right_third);
+        if left_fuel < right_fuel {
+            right_location = right_third - 1;
+        } else if left_fuel > right_fuel {
+            left_location = left_third + 1;
         } else {
-            left_location = if right_location - left_location == 1 {
-                mid_location + 1
-            } else {
-                mid_location
-            };
-            left_consumption = sorted_positions.iter().fold(0u64, |mut sum, val| {
-                sum += u64::from(consumption_function(val, &left_location));
-                sum
-            });
+            // Both are optimal if the minimum is flat, which can extend left of `left_third`
+            right_location = right_third;
         }
     }
 
-    left_location
+    best_alignment(model, sorted_positions, left_location..=right_location)
 }
 
-fn simple_delta(start: &u32, target: &u32) -> u32 {
-    if start > target {
-        start - target
-    } else {
-        target - start
+fn best_alignment<M: CostModel + ?Sized>(
+    model: &M,
+    sorted_positions: &[u32],
+    candidates: impl Iterator<Item = u32>,
+) -> Option<Alignment> {
+    candidates
+        .map(|position| Alignment {
+            position,
+            fuel: model.total_fuel(sorted_positions, position),
+        })
+        .min_by_key(|alignment| (alignment.fuel, alignment.position))
+}
+
+// Each step costs 1 fuel, so the median is optimal
+struct LinearCost;
+
+impl CostModel for LinearCost {
+    fn cost(&self, start: u32, target: u32) -> u64 {
+        u64::from(start.abs_diff(target))
+    }
+
+    fn align(&self, sorted_positions: &[u32]) -> Option<Alignment> {
+        let median = *sorted_positions.get((sorted_positions.len().max(1) - 1) / 2)?;
+
+        Some(Alignment {
+            position: median,
+            fuel: self.total_fuel(sorted_positions, median),
+        })
     }
 }
 
-fn linear_delta(start: &u32, target: &u32) -> u32 {
-    let delta = simple_delta(start, target);
-    let upped: u64 = u64::from(delta) * u64::from(delta + 1);
-    (upped / 2).try_into().unwrap()
+// Each step costs 1 more fuel than the previous one. The optimum is less than half a step away
+// from the mean, so it's either the mean rounded down or up
+struct TriangularCost;
+
+impl CostModel for TriangularCost {
+    fn cost(&self, start: u32, target: u32) -> u64 {
+        let delta = u64::from(start.abs_diff(target));
+        delta * (delta + 1) / 2
+    }
+
+    fn align(&self, sorted_positions: &[u32]) -> Option<Alignment> {
+        let crabs = sorted_positions.len() as u64;
+        let sum: u64 = sorted_positions.iter().map(|&p| u64::from(p)).sum();
+        let mean_floor = u32::try_from(sum.checked_div(crabs)?).unwrap();
+
+        best_alignment(self, sorted_positions, mean_floor..=mean_floor + 1)
+    }
+}
+
+fn crab_positions(input: &str) -> Vec<u32> {
+    let initial_horizontal = input
+        .split(',')
+        .map(|hor| hor.trim())
+        .filter(|hor| !hor.is_empty())
+        .map(|hor| {
+            hor.parse::<u32>()
+                .expect("Given a non-number as horizontal position!")
+        });
+
+    sorted(initial_horizontal).collect()
 }
 
-fn min_crab_fuel(input: &str, consumption_function: fn(&u32, &u32) -> u32) -> u64 {
-    let initial_horizontal = input.split(',').map(|hor| {
-        hor.trim()
-            .parse::<u32>()
-            .expect("Given a non-number as horizontal position!")
-    });
-    let sorted_horizontal: Vec<u32> = sorted(initial_horizontal).collect();
-
-    let mid_point = midpoint_binary_search(&sorted_horizontal, consumption_function);
-    let fuel_cost = sorted_horizontal.iter().fold(0u64, |mut sum, val| {
-        sum += u64::from(consumption_function(val, &mid_point));
-        sum
-    });
-
-    fuel_cost
+fn min_crab_fuel(input: &str, cost_model: &impl CostModel) -> Option<Alignment> {
+    cost_model.align(&crab_positions(input))
+}
+
+fn report_alignment<M: CostModel>(input: &str, cost_model: &M) {
+    match (
+        min_crab_fuel(input, cost_model),
+        convex_search(cost_model, &crab_positions(input)),
+    ) {
+        (Some(exact), Some(searched)) => {
+            println!(
+                "Estimated minimum cost: {} (aligning at {})",
+                exact.fuel, exact.position
+            );
+            if exact.fuel != searched.fuel {
+                println!(
+                    "The generic search disagrees: {} (aligning at {})",
+                    searched.fuel, searched.position
+                );
+            }
+        }
+        _ => println!("There are no crabs to align"),
+    }
 }
 
 pub fn part1(input: &str) {
-    let min_consumption = min_crab_fuel(input, simple_delta);
-    println!("Estimated minimum cost: {}", min_consumption);
+    report_alignment(input, &LinearCost);
 }
 
 pub fn part2(input: &str) {
-    let min_consumption = min_crab_fuel(input, linear_delta);
-    println!("Estimated minimum geometric cost: {}", min_consumption);
+    report_alignment(input, &TriangularCost);
 }
 
 #[cfg(test)]
@@ -96,17 +159,101 @@ mod tests {
     fn base_case() {
         let input_string = "16,1,2,0,4,2,7,1,2,14";
 
-        let min_fuel_needed = min_crab_fuel(input_string, simple_delta);
+        let min_fuel_needed = min_crab_fuel(input_string, &LinearCost);
 
-        assert_eq!(min_fuel_needed, 37u64);
+        assert_eq!(
+            min_fuel_needed,
+            Some(Alignment {
+                position: 2,
+                fuel: 37
+            })
+        );
     }
 
     #[test]
     fn linear_rate() {
         let input_string = "16,1,2,0,4,2,7,1,2,14";
 
-        let min_fuel_needed = min_crab_fuel(input_string, linear_delta);
+        let min_fuel_needed = min_crab_fuel(input_string, &TriangularCost);
+
+        assert_eq!(
+            min_fuel_needed,
+            Some(Alignment {
+                position: 5,
+                fuel: 168
+            })
+        );
+    }
+
+    #[test]
+    fn cross_checked_models() {
+        let inputs = [
+            "16,1,2,0,4,2,7,1,2,14",
+            "0,0,0,1000",
+            "3",
+            "1,2",
+            "0,1,1,2,3,5,8,13,21,34,55,89,144,233,377,610,987",
+        ];
+
+        for input in inputs {
+            let positions = crab_positions(input);
+            let all_targets = || 0..=*positions.last().unwrap();
 
-        assert_eq!(min_fuel_needed, 168u64);
+            for (model, generic) in [
+                (
+                    &LinearCost as &dyn CostModel,
+                    convex_search(&LinearCost, &positions),
+                ),
+                (&TriangularCost, convex_search(&TriangularCost, &positions)),
+            ] {
+                let brute_force = best_alignment(model, &positions, all_targets()).unwrap();
+                assert_eq!(model.align(&positions).unwrap().fuel, brute_force.fuel);
+                assert_eq!(generic, Some(brute_force));
+            }
+        }
+
+        assert_eq!(min_crab_fuel("", &LinearCost), None);
+    }
+
+    #[test]
+    fn custom_cost() {
+        let positions = crab_positions("16,1,2,0,4,2,7,1,2,14");
+        let squared = |start: u32, target: u32| u64::from(start.abs_diff(target)).pow(2);
+
+        // Squared distances are optimal at the mean, which is 4.9
+        assert_eq!(
+            squared.align(&positions),
+            Some(Alignment {
+                position: 5,
+                fuel: 291
+            })
+        );
+        assert_eq!(
+            (|start: u32, target: u32| LinearCost.cost(start, target)).align(&positions),
+            LinearCost.align(&positions)
+        );
+    }
+
+    #[test]
+    fn flat_optimum() {
+        // Moves within the dead zone are free, so the total fuel is flat around the optimum
+        for dead_zone in [0, 3, 10, 40] {
+            let dead_zone_cost = move |start: u32, target: u32| {
+                u64::from(start.abs_diff(target).saturating_sub(dead_zone))
+            };
+
+            for input in [
+                "0,100",
+                "16,1,2,0,4,2,7,1,2,14",
+                "5,5,5,90",
+                "0,30,31,32,33,99",
+            ] {
+                let positions = crab_positions(input);
+                let all_targets = 0..=*positions.last().unwrap();
+                let leftmost = best_alignment(&dead_zone_cost, &positions, all_targets);
+
+                assert_eq!(dead_zone_cost.align(&positions), leftmost);
+            }
+        }
     }
 }
@@ -1,6 +1,4 @@
-use hashbrown::HashSet;
 use std::convert::TryFrom;
-use std::iter::FromIterator;
 
 use nom::bytes::complete::tag;
 use nom::character::complete::{alphanumeric1, multispace1};
@@ -21,121 +19,197 @@ fn display(input: &str) -> IResult<&str, (Vec<&str>, Vec<&str>)> {
     })(input)
 }
 
-fn cypher_crack(cypher: Vec<&str>, digits: Vec<&str>) -> u32 {
-    let mut mappings: Vec<HashSet<char>> = vec![Default::default(); 10];
-
-    let mut potential_six = Vec::<HashSet<char>>::new();
-    let mut potential_five = Vec::<HashSet<char>>::new();
-
-    // This problem requires us to go through a lot of pain. We need to extract all the known combinations, then use set
-    // arithmetic to get the important bits to create the other values
-    for digit_code in cypher {
-        match digit_code.len() {
-            2 => mappings[1].extend(digit_code.chars()),
-            3 => mappings[7].extend(digit_code.chars()),
-            4 => mappings[4].extend(digit_code.chars()),
-            5 => potential_five.push(HashSet::from_iter(digit_code.chars())),
-            6 => potential_six.push(HashSet::from_iter(digit_code.chars())),
-            7 => mappings[8].extend(digit_code.chars()),
-            _ => {}
-        }
+// Segments lit for each digit when the display is wired correctly
+const SEVEN_SEGMENT_DIGITS: [(char, &str); 10] = [
+    ('0', "abcefg"),
+    ('1', "cf"),
+    ('2', "acdeg"),
+    ('3', "acdfg"),
+    ('4', "bcdf"),
+    ('5', "abdfg"),
+    ('6', "abdefg"),
+    ('7', "acf"),
+    ('8', "abcdefg"),
+    ('9', "abcdfg"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+struct DisplayReading {
+    // Pairs of scrambled wire and the segment it's connected to
+    wiring: Vec<(char, char)>,
+    output: String,
+}
+
+// Symbols a display can show, as masks over its segments
+#[derive(Debug)]
+struct SegmentTable {
+    segments: Vec<char>,
+    symbols: Vec<(char, u32)>,
+}
+
+impl SegmentTable {
+    pub fn new(segments: &str, symbols: &[(char, &str)]) -> SegmentTable {
+        let mut table = SegmentTable {
+            segments: segments.chars().collect(),
+            symbols: Vec::new(),
+        };
+        assert!(
+            table.segments.len() <= 32,
+            "Displays can have at most 32 segments"
+        );
+
+        table.symbols = symbols
+            .iter()
+            .map(|(symbol, lit)| {
+                let mask = table
+                    .segment_mask(lit)
+                    .expect("Symbols can only use the display segments");
+                (*symbol, mask)
+            })
+            .collect();
+
+        table
     }
 
-    let mut nine_idx = 0usize;
-    let mut lower_left_code = HashSet::new();
-    for (idx, six_lines) in potential_six.iter().enumerate() {
-        if six_lines.is_superset(&mappings[4]) {
-            mappings[9].extend(six_lines);
-            nine_idx = idx;
-            let lower_left_set: Vec<_> = mappings[8].difference(&mappings[9]).collect();
-            assert_eq!(
-                lower_left_set.len(),
-                1,
-                "We should have a single code in the difference between 8 and 9!"
-            );
-            lower_left_code.insert(*lower_left_set[0]);
-        }
+    pub fn seven_segment_digits() -> SegmentTable {
+        SegmentTable::new("abcdefg", &SEVEN_SEGMENT_DIGITS)
     }
-    potential_six.swap_remove(nine_idx);
-
-    let (zero_idx, six_idx) = if potential_six[0].is_superset(&mappings[1]) {
-        (0, 1)
-    } else {
-        (1, 0)
-    };
-    mappings[0].extend(&potential_six[zero_idx]);
-    mappings[6].extend(&potential_six[six_idx]);
-
-    let mut five_idx = 0usize;
-    for (idx, five_lines) in potential_five.iter().enumerate() {
-        let potential_six_encode = five_lines.union(&lower_left_code);
-        let mut potential_six_set: HashSet<char> = HashSet::new();
-        potential_six_set.extend(potential_six_encode);
-        if mappings[6].eq(&potential_six_set) {
-            mappings[5].extend(five_lines);
-            five_idx = idx;
-        }
+
+    fn segment_mask(&self, pattern: &str) -> Result<u32, &'static str> {
+        pattern.chars().try_fold(0u32, |mask, wire| {
+            let idx = self
+                .segments
+                .iter()
+                .position(|s| *s == wire)
+                .ok_or("The pattern uses a wire the display doesn't have")?;
+            Ok(mask | (1 << idx))
+        })
     }
-    potential_five.swap_remove(five_idx);
 
-    let centre_code_data: Vec<_> = mappings[8].difference(&mappings[0]).collect();
-    assert_eq!(
-        centre_code_data.len(),
-        1,
-        "We should have a single code in the difference between 8 and 0!"
-    );
-    let upper_left_data: Vec<_> = mappings[4].difference(&mappings[1]).collect();
-    let upper_left_code = if upper_left_data[0] == centre_code_data[0] {
-        *upper_left_data[1]
-    } else {
-        *upper_left_data[0]
-    };
-    let mut opposite_one_set = HashSet::new();
-    opposite_one_set.insert(upper_left_code);
-    opposite_one_set.extend(lower_left_code.iter());
-
-    let potential_three_diff = mappings[8].difference(&opposite_one_set);
-    let mut potential_three_set: HashSet<char> = HashSet::new();
-    potential_three_set.extend(potential_three_diff);
-    let (three_idx, two_idx) = if potential_five[0].eq(&potential_three_set) {
-        (0, 1)
-    } else {
-        (1, 0)
-    };
-    mappings[3].extend(&potential_five[three_idx]);
-    mappings[2].extend(&potential_five[two_idx]);
-
-    let mut final_value = 0u32;
-
-    for obf_digit in digits {
-        final_value *= 10;
-        let mut obf_digit_set: HashSet<char> = HashSet::new();
-        obf_digit_set.extend(obf_digit.chars());
-
-        for (open_digit, code) in mappings.iter().enumerate() {
-            if obf_digit_set.eq(code) {
-                final_value += u32::try_from(open_digit).unwrap();
+    // Amounts of lit segments that only one symbol has
+    pub fn unique_lengths(&self) -> Vec<u32> {
+        let lengths: Vec<u32> = self.symbols.iter().map(|(_, m)| m.count_ones()).collect();
+
+        lengths
+            .iter()
+            .filter(|&&len| lengths.iter().filter(|&&other| other == len).count() == 1)
+            .cloned()
+            .collect()
+    }
+
+    // Finds how the wires connect to the segments so every pattern is a symbol, and then reads
+    // the output with that wiring. Fails if there's no such wiring or more than one
+    pub fn solve(
+        &self,
+        patterns: &[&str],
+        outputs: &[&str],
+    ) -> Result<DisplayReading, &'static str> {
+        let pattern_masks: Vec<u32> = patterns
+            .iter()
+            .map(|pattern| self.segment_mask(pattern))
+            .collect::<Result<_, _>>()?;
+
+        let mut wiring: Vec<Option<usize>> = vec![None; self.segments.len()];
+        let mut solutions: Vec<Vec<Option<usize>>> = Vec::new();
+        self.search_wiring(&pattern_masks, &mut wiring, 0, &mut solutions);
+
+        let wiring = match solutions.len() {
+            0 => return Err("The patterns don't match any wiring of the display"),
+            1 => solutions.pop().unwrap(),
+            _ => return Err("The patterns match more than one wiring of the display"),
+        };
+
+        let output = outputs
+            .iter()
+            .map(|pattern| {
+                let mask = self.segment_mask(pattern)?;
+                let lit = self.rewire(mask, &wiring);
+                self.symbols
+                    .iter()
+                    .find(|(_, symbol_mask)| *symbol_mask == lit)
+                    .map(|(symbol, _)| *symbol)
+                    .ok_or("An output pattern isn't any of the display symbols")
+            })
+            .collect::<Result<String, _>>()?;
+
+        Ok(DisplayReading {
+            wiring: wiring
+                .iter()
+                .enumerate()
+                .map(|(wire, segment)| (self.segments[wire], self.segments[segment.unwrap()]))
+                .collect(),
+            output,
+        })
+    }
+
+    fn rewire(&self, mask: u32, wiring: &[Option<usize>]) -> u32 {
+        wiring
+            .iter()
+            .enumerate()
+            .filter(|(wire, _)| mask & (1 << wire) != 0)
+            .filter_map(|(_, segment)| *segment)
+            .fold(0u32, |lit, segment| lit | (1 << segment))
+    }
+
+    // A pattern is still possible if some symbol with as many segments lights every segment of
+    // its wired wires and none of the segments wired to the other known wires
+    fn consistent(&self, pattern: u32, wiring: &[Option<usize>]) -> bool {
+        let known_wires = wiring
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.is_some())
+            .fold(0u32, |mask, (wire, _)| mask | (1 << wire));
+        let lit = self.rewire(pattern & known_wires, wiring);
+        let unlit = self.rewire(!pattern & known_wires, wiring);
+
+        self.symbols.iter().any(|(_, symbol)| {
+            symbol.count_ones() == pattern.count_ones()
+                && symbol & lit == lit
+                && symbol & unlit == 0
+        })
+    }
+
+    // Backtracking over the wires, stopping as soon as a second solution shows up
+    fn search_wiring(
+        &self,
+        patterns: &[u32],
+        wiring: &mut Vec<Option<usize>>,
+        used_segments: u32,
+        solutions: &mut Vec<Vec<Option<usize>>>,
+    ) {
+        let wire = match wiring.iter().position(|segment| segment.is_none()) {
+            Some(wire) => wire,
+            None => {
+                solutions.push(wiring.clone());
+                return;
+            }
+        };
+
+        for segment in (0..self.segments.len()).filter(|s| used_segments & (1 << s) == 0) {
+            wiring[wire] = Some(segment);
+            if patterns.iter().all(|p| self.consistent(*p, wiring)) {
+                self.search_wiring(patterns, wiring, used_segments | (1 << segment), solutions);
+            }
+            wiring[wire] = None;
+
+            if solutions.len() > 1 {
+                return;
             }
         }
     }
-
-    final_value
 }
 
 fn lcd_simple_digit_count(input: &str) -> u64 {
     let input_lines = input.lines();
+    let simple_lengths = SegmentTable::seven_segment_digits().unique_lengths();
 
     let mut count = 0u64;
 
     for line in input_lines {
         let (_, (_, digits)) = display(line.trim()).expect("Something went super wrong!");
         for digit_data in digits {
-            let activation_count = digit_data.len();
-            if activation_count == 2
-                || activation_count == 3
-                || activation_count == 4
-                || activation_count == 7
-            {
+            let activation_count = u32::try_from(digit_data.len()).unwrap();
+            if simple_lengths.contains(&activation_count) {
                 count += 1;
             }
         }
@@ -144,18 +218,21 @@ fn lcd_simple_digit_count(input: &str) -> u64 {
     count
 }
 
-fn output_decrypt_sum(input: &str) -> u64 {
+fn output_decrypt_sum(input: &str) -> Result<u64, String> {
+    let table = SegmentTable::seven_segment_digits();
     let input_lines = input.lines();
 
     let mut final_sum = 0u64;
 
-    for line in input_lines {
+    for (line_idx, line) in input_lines.enumerate() {
         let (_, (cypher, digits)) = display(line.trim()).expect("Something went super wrong!");
-        let encoded_value = cypher_crack(cypher, digits);
-        final_sum += u64::from(encoded_value);
+        let reading = table
+            .solve(&cypher, &digits)
+            .map_err(|e| format!("Line {}: {}", line_idx + 1, e))?;
+        final_sum += reading.output.parse::<u64>().unwrap();
     }
 
-    final_sum
+    Ok(final_sum)
 }
 
 pub fn part1(input: &str) {
@@ -164,8 +241,10 @@ pub fn part1(input: &str) {
 }
 
 pub fn part2(input: &str) {
-    let decrypted_sum = output_decrypt_sum(input);
-    println!("Sum of all the encrypted values: {}", decrypted_sum);
+    match output_decrypt_sum(input) {
+        Ok(decrypted_sum) => println!("Sum of all the encrypted values: {}", decrypted_sum),
+        Err(e) => println!("Couldn't decrypt the displays: {}", e),
+    }
 }
 
 #[cfg(test)]
@@ -207,6 +286,100 @@ mod tests {
 
         let simple_count = output_decrypt_sum(input_string);
 
-        assert_eq!(simple_count, 61229u64);
+        assert_eq!(simple_count, Ok(61229u64));
+    }
+
+    #[test]
+    fn discovered_wiring() {
+        let table = SegmentTable::seven_segment_digits();
+        let (_, (cypher, digits)) = display(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        )
+        .unwrap();
+
+        assert_eq!(
+            table.solve(&cypher, &digits),
+            Ok(DisplayReading {
+                wiring: vec![
+                    ('a', 'c'),
+                    ('b', 'f'),
+                    ('c', 'g'),
+                    ('d', 'a'),
+                    ('e', 'b'),
+                    ('f', 'd'),
+                    ('g', 'e'),
+                ],
+                output: String::from("5353"),
+            })
+        );
+        assert_eq!(table.unique_lengths(), vec![2, 4, 3, 7]);
+    }
+
+    #[test]
+    fn solver_errors() {
+        let table = SegmentTable::seven_segment_digits();
+
+        assert_eq!(
+            table.solve(&["ab", "abc"], &["ab"]),
+            Err("The patterns match more than one wiring of the display")
+        );
+        assert_eq!(
+            table.solve(&["a", "abcdefg"], &["abcdefg"]),
+            Err("The patterns don't match any wiring of the display")
+        );
+        assert_eq!(
+            table.solve(&["ab", "xyz"], &["ab"]),
+            Err("The pattern uses a wire the display doesn't have")
+        );
+
+        let digits: Vec<&str> = SEVEN_SEGMENT_DIGITS.iter().map(|(_, p)| *p).collect();
+        assert_eq!(
+            table.solve(&digits, &["abcdef"]),
+            Err("An output pattern isn't any of the display symbols")
+        );
+        assert_eq!(
+            table.solve(&digits, &["cf", "abcdfg"]).map(|r| r.output),
+            Ok(String::from("19"))
+        );
+    }
+
+    #[test]
+    fn hex_display() {
+        let mut hex_symbols = SEVEN_SEGMENT_DIGITS.to_vec();
+        hex_symbols.extend([
+            ('A', "abcdef"),
+            ('b', "bdefg"),
+            ('C', "abeg"),
+            ('d', "cdefg"),
+            ('E', "abdeg"),
+            ('F', "abde"),
+        ]);
+        let table = SegmentTable::new("abcdefg", &hex_symbols);
+
+        // Segment `s` is lit through the wire `scramble(s)`
+        let wires = "gfdbaec";
+        let scramble = |pattern: &str| -> String {
+            pattern
+                .chars()
+                .map(|segment| {
+                    let wire_idx = (segment as u8 - b'a') as usize;
+                    wires.chars().nth(wire_idx).unwrap()
+                })
+                .collect()
+        };
+        let patterns: Vec<String> = hex_symbols.iter().map(|(_, p)| scramble(p)).collect();
+        let outputs: Vec<String> = ["abeg", "abcefg", "cdefg", "abdeg"]
+            .iter()
+            .map(|p| scramble(p))
+            .collect();
+
+        let patterns: Vec<&str> = patterns.iter().map(|p| p.as_str()).collect();
+        let outputs: Vec<&str> = outputs.iter().map(|p| p.as_str()).collect();
+        let reading = table.solve(&patterns, &outputs).unwrap();
+
+        assert_eq!(reading.output, "C0dE");
+        for (wire, segment) in reading.wiring {
+            assert_eq!(scramble(&segment.to_string()), wire.to_string());
+        }
     }
 }
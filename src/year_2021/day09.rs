@@ -1,9 +1,23 @@
-use hashbrown::HashSet;
+use ansi_term::Colour;
+use hashbrown::HashMap;
 use std::convert::TryInto;
+use std::env::temp_dir;
 use std::fmt;
+use std::fs;
 
 use itertools::Itertools;
 
+use crate::aoc_lib::params::{DayParams, Param};
+
+const EXPORT_MAP: Param<bool> = Param::new("export", false);
+
+pub const PARAMS: DayParams = &[&EXPORT_MAP];
+
+// Bright 256-colour backgrounds that keep black digits readable
+const BASIN_PALETTE: [u8; 8] = [117, 156, 216, 183, 229, 152, 210, 192];
+
+const DEFAULT_WALL_HEIGHT: u8 = 9;
+
 struct RiskGrid {
     data: Vec<u8>,
     rows: usize,
@@ -40,113 +54,156 @@ impl RiskGrid {
             .expect("Provided location is out of the grid bounds!")
     }
 
+    fn neighbours(&self, location: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        let (row, col) = location;
+        let (rows, columns) = (self.rows, self.columns);
+
+        [
+            row.checked_sub(1).map(|r| (r, col)),
+            (row + 1 < rows).then_some((row + 1, col)),
+            col.checked_sub(1).map(|c| (row, c)),
+            (col + 1 < columns).then_some((row, col + 1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    // Locations lower than all of their neighbours
     pub fn find_local_minima(&self) -> Vec<((usize, usize), u8)> {
-        let grid_locations = (0..self.rows).cartesian_product(0..self.columns);
+        (0..self.rows)
+            .cartesian_product(0..self.columns)
+            .filter(|&loc| {
+                let risk = self.get_risk(&loc);
+                self.neighbours(loc).all(|n| self.get_risk(&n) > risk)
+            })
+            .map(|loc| (loc, self.get_risk(&loc)))
+            .collect()
+    }
 
-        let mut visit_stack: Vec<(usize, usize)> = Vec::new();
-        for loc in grid_locations {
-            visit_stack.push(loc);
-        }
+    // Connected-component labelling in a single pass: every location is joined to the ones above
+    // and on its left, unless any of them is a wall
+    pub fn label_basins(&self, wall_height: u8) -> BasinMap {
+        let is_wall = |idx: usize| self.data[idx] >= wall_height;
+        let mut components = DisjointSet::new(self.data.len());
 
-        let mut already_visited: HashSet<(usize, usize)> = HashSet::new();
-        let mut minimum_risks: Vec<((usize, usize), u8)> = Vec::new();
-
-        // Time for that graph exploration. I am using depth-first visit, but I could have used breadth-first as well
-        while let Some(next_risk_loc) = visit_stack.pop() {
-            let mut minimum_found = true;
-            if !already_visited.contains(&next_risk_loc) {
-                if next_risk_loc.0 > 0 {
-                    let upper_location = (next_risk_loc.0 - 1, next_risk_loc.1);
-                    if self.get_risk(&next_risk_loc) >= self.get_risk(&upper_location) {
-                        minimum_found = false;
-                        visit_stack.push(upper_location);
-                    };
-                };
-
-                if next_risk_loc.0 < self.rows - 1 {
-                    let lower_location = (next_risk_loc.0 + 1, next_risk_loc.1);
-                    if self.get_risk(&next_risk_loc) >= self.get_risk(&lower_location) {
-                        minimum_found = false;
-                        visit_stack.push(lower_location);
-                    };
-                };
-
-                if next_risk_loc.1 > 0 {
-                    let left_location = (next_risk_loc.0, next_risk_loc.1 - 1);
-                    if self.get_risk(&next_risk_loc) >= self.get_risk(&left_location) {
-                        minimum_found = false;
-                        visit_stack.push(left_location);
-                    };
-                };
-
-                if next_risk_loc.1 < self.columns - 1 {
-                    let right_location = (next_risk_loc.0, next_risk_loc.1 + 1);
-                    if self.get_risk(&next_risk_loc) >= self.get_risk(&right_location) {
-                        minimum_found = false;
-                        visit_stack.push(right_location);
-                    };
-                };
-
-                if minimum_found {
-                    minimum_risks.push((next_risk_loc, self.get_risk(&next_risk_loc)));
-                }
-
-                already_visited.insert(next_risk_loc);
+        for (row, col) in (0..self.rows).cartesian_product(0..self.columns) {
+            let idx = row * self.columns + col;
+            if is_wall(idx) {
+                continue;
+            }
+            if row > 0 && !is_wall(idx - self.columns) {
+                components.union(idx, idx - self.columns);
+            }
+            if col > 0 && !is_wall(idx - 1) {
+                components.union(idx, idx - 1);
             }
         }
 
-        minimum_risks
-    }
-
-    // This will be a reverse graph search based on some starting locations
-    pub fn find_basin_sizes(&self, minima: &[(usize, usize)]) -> Vec<u64> {
-        let mut basins: Vec<u64> = Vec::new();
-        for initial_location in minima {
-            let mut visit_stack: Vec<(usize, usize)> = vec![*initial_location];
-
-            let mut already_visited: HashSet<(usize, usize)> = HashSet::new();
-
-            let mut basin_size = 0u64;
-
-            while let Some(next_basin_loc) = visit_stack.pop() {
-                if !already_visited.contains(&next_basin_loc) {
-                    basin_size += 1;
-                    if next_basin_loc.0 > 0 {
-                        let upper_location = (next_basin_loc.0 - 1, next_basin_loc.1);
-                        if self.get_risk(&upper_location) < 9u8 {
-                            visit_stack.push(upper_location);
-                        };
-                    };
-
-                    if next_basin_loc.0 < self.rows - 1 {
-                        let lower_location = (next_basin_loc.0 + 1, next_basin_loc.1);
-                        if self.get_risk(&lower_location) < 9u8 {
-                            visit_stack.push(lower_location);
-                        };
-                    };
-
-                    if next_basin_loc.1 > 0 {
-                        let left_location = (next_basin_loc.0, next_basin_loc.1 - 1);
-                        if self.get_risk(&left_location) < 9u8 {
-                            visit_stack.push(left_location);
-                        };
-                    };
-
-                    if next_basin_loc.1 < self.columns - 1 {
-                        let right_location = (next_basin_loc.0, next_basin_loc.1 + 1);
-                        if self.get_risk(&right_location) < 9u8 {
-                            visit_stack.push(right_location);
-                        };
-                    };
-
-                    already_visited.insert(next_basin_loc);
-                }
+        // Basins are numbered in the order their first location shows up
+        let mut root_labels: HashMap<usize, usize> = HashMap::new();
+        let mut labels: Vec<Option<usize>> = vec![None; self.data.len()];
+        let mut basins: Vec<Basin> = Vec::new();
+        for idx in (0..self.data.len()).filter(|&idx| !is_wall(idx)) {
+            let root = components.find(idx);
+            let label = *root_labels.entry(root).or_insert_with(|| {
+                basins.push(Basin {
+                    size: 0,
+                    low_point: ((idx / self.columns, idx % self.columns), self.data[idx]),
+                });
+                basins.len() - 1
+            });
+
+            let basin = &mut basins[label];
+            basin.size += 1;
+            if self.data[idx] < basin.low_point.1 {
+                basin.low_point = ((idx / self.columns, idx % self.columns), self.data[idx]);
             }
+            labels[idx] = Some(label);
+        }
+
+        BasinMap {
+            labels,
+            rows: self.rows,
+            columns: self.columns,
+            basins,
+        }
+    }
+}
+
+// Union-find over flat grid indices, with path halving and union by size
+struct DisjointSet {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub fn new(elements: usize) -> DisjointSet {
+        DisjointSet {
+            parents: (0..elements).collect(),
+            sizes: vec![1; elements],
+        }
+    }
+
+    pub fn find(&mut self, mut element: usize) -> usize {
+        while self.parents[element] != element {
+            self.parents[element] = self.parents[self.parents[element]];
+            element = self.parents[element];
+        }
+
+        element
+    }
 
-            basins.push(basin_size);
+    pub fn union(&mut self, first: usize, second: usize) {
+        let (mut first_root, mut second_root) = (self.find(first), self.find(second));
+        if first_root == second_root {
+            return;
+        }
+        if self.sizes[first_root] < self.sizes[second_root] {
+            std::mem::swap(&mut first_root, &mut second_root);
         }
 
-        basins
+        self.parents[second_root] = first_root;
+        self.sizes[first_root] += self.sizes[second_root];
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Basin {
+    size: u64,
+    low_point: ((usize, usize), u8),
+}
+
+struct BasinMap {
+    // Basin of every location, walls don't have any
+    labels: Vec<Option<usize>>,
+    rows: usize,
+    columns: usize,
+    basins: Vec<Basin>,
+}
+
+impl BasinMap {
+    pub fn get_label(&self, location: &(usize, usize)) -> Option<usize> {
+        self.labels[location.0 * self.columns + location.1]
+    }
+
+    // Risk map with a different background colour for every basin, walls are left uncoloured
+    pub fn render_ansi(&self, grid: &RiskGrid) -> String {
+        (0..self.rows)
+            .map(|row| {
+                (0..self.columns)
+                    .map(|col| {
+                        let risk = grid.get_risk(&(row, col));
+                        match self.get_label(&(row, col)) {
+                            Some(label) => Colour::Black
+                                .on(Colour::Fixed(BASIN_PALETTE[label % BASIN_PALETTE.len()]))
+                                .paint(risk.to_string())
+                                .to_string(),
+                            None => risk.to_string(),
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
     }
 }
 
@@ -177,12 +234,26 @@ pub fn part1(input: &str) {
 
 pub fn part2(input: &str) {
     let risk_grid = RiskGrid::new(input);
-    let (minima_locations, _): (Vec<_>, Vec<_>) =
-        risk_grid.find_local_minima().iter().cloned().unzip();
-    let basins = risk_grid.find_basin_sizes(&minima_locations);
-    let top_basins = basins.iter().sorted().rev().take(3);
+    let basin_map = risk_grid.label_basins(DEFAULT_WALL_HEIGHT);
+    let top_basins = basin_map
+        .basins
+        .iter()
+        .map(|b| b.size)
+        .sorted()
+        .rev()
+        .take(3);
     let basin_area: u64 = top_basins.product();
     println!("Product of top three largest basins: {}", basin_area);
+
+    if !EXPORT_MAP.get() {
+        return;
+    }
+
+    let map_path = temp_dir().join("day09_basins.ans");
+    match fs::write(&map_path, basin_map.render_ansi(&risk_grid)) {
+        Ok(()) => println!("Basin map exported to {}", map_path.display()),
+        Err(e) => println!("Failed to export the basin map: {}", e),
+    }
 }
 
 #[cfg(test)]
@@ -216,12 +287,106 @@ mod tests {
                                  9899965678";
 
         let risk_grid = RiskGrid::new(input_string);
-        let (minima_locations, _): (Vec<_>, Vec<_>) =
-            risk_grid.find_local_minima().iter().cloned().unzip();
-        let basins = risk_grid.find_basin_sizes(&minima_locations);
-        let top_basins = basins.iter().sorted().rev().take(3);
+        let basin_map = risk_grid.label_basins(DEFAULT_WALL_HEIGHT);
+        let top_basins = basin_map
+            .basins
+            .iter()
+            .map(|b| b.size)
+            .sorted()
+            .rev()
+            .take(3);
         let basin_area: u64 = top_basins.product();
 
         assert_eq!(basin_area, 1134u64);
+        assert_eq!(
+            basin_map.basins,
+            vec![
+                Basin {
+                    size: 3,
+                    low_point: ((0, 1), 1)
+                },
+                Basin {
+                    size: 9,
+                    low_point: ((0, 9), 0)
+                },
+                Basin {
+                    size: 14,
+                    low_point: ((2, 2), 5)
+                },
+                Basin {
+                    size: 9,
+                    low_point: ((4, 6), 5)
+                },
+            ]
+        );
+
+        let label_rows: Vec<String> = (0..risk_grid.rows)
+            .map(|row| {
+                (0..risk_grid.columns)
+                    .map(|col| match basin_map.get_label(&(row, col)) {
+                        Some(label) => (b'a' + label as u8) as char,
+                        None => '#',
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            label_rows,
+            vec![
+                "aa###bbbbb",
+                "a#ccc#b#bb",
+                "#ccccc#d#b",
+                "ccccc#ddd#",
+                "#c###ddddd"
+            ]
+        );
+    }
+
+    #[test]
+    fn wall_heights() {
+        let risk_grid = RiskGrid::new(
+            "2199943210
+            3987894921
+            9856789892
+            8767896789
+            9899965678",
+        );
+
+        let single_basin = risk_grid.label_basins(10);
+        assert_eq!(
+            single_basin.basins,
+            vec![Basin {
+                size: 50,
+                low_point: ((0, 9), 0)
+            }]
+        );
+
+        let lower_walls = risk_grid.label_basins(4);
+        let sizes: Vec<u64> = lower_walls.basins.iter().map(|b| b.size).collect();
+        assert_eq!(sizes, vec![3, 7]);
+
+        // Both arms only meet at the bottom, after their labels were already assigned
+        let u_shape = RiskGrid::new(
+            "1913
+            2924
+            3335",
+        );
+        let basin_map = u_shape.label_basins(DEFAULT_WALL_HEIGHT);
+        assert_eq!(basin_map.basins.len(), 1);
+        assert_eq!(basin_map.basins[0].size, 10);
+    }
+
+    #[test]
+    fn ansi_map() {
+        let risk_grid = RiskGrid::new(
+            "19
+            92",
+        );
+        let basin_map = risk_grid.label_basins(DEFAULT_WALL_HEIGHT);
+
+        assert_eq!(
+            basin_map.render_ansi(&risk_grid),
+            "\x1b[48;5;117;30m1\x1b[0m9\n9\x1b[48;5;156;30m2\x1b[0m"
+        );
     }
 }
@@ -1,82 +1,197 @@
-use hashbrown::HashMap;
+use crate::aoc_lib::params::{DayParams, Param};
 
-enum CheckResult {
-    Wrong(char),
-    Incomlete(Vec<char>),
+// Reports every corrupted line with a suggested repair, on top of the score
+const DETAILS: Param<bool> = Param::new("details", false);
+
+pub const PARAMS: DayParams = &[&DETAILS];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BracketPair {
+    open: char,
+    close: char,
+    error_score: u64,
+    completion_score: u64,
 }
 
-fn syntax_line_check(line: &str) -> CheckResult {
-    let parentheses_combo: HashMap<char, char> = [('(', ')'), ('<', '>'), ('{', '}'), ('[', ']')]
-        .iter()
-        .cloned()
-        .collect();
-    let mut parentheses_stack: Vec<char> = Vec::new();
-    for par in line.chars() {
-        if parentheses_combo.contains_key(&par) {
-            parentheses_stack.push(par);
-        } else if let Some(pot_open) = parentheses_stack.pop() {
-            let paired_character = parentheses_combo
-                .get(&pot_open)
-                .expect("Found an invalid character in the syntax");
-            if par != *paired_character {
-                return CheckResult::Wrong(par);
-            }
-        } else {
-            return CheckResult::Wrong(par);
+impl BracketPair {
+    const fn new(open: char, close: char, error_score: u64, completion_score: u64) -> Self {
+        Self {
+            open,
+            close,
+            error_score,
+            completion_score,
         }
     }
-    CheckResult::Incomlete(parentheses_stack)
 }
 
-// This function takes the remaining, incomplete, part of a syntax line and computes the autocompletion score
-fn compute_autocomplete_cost(input: &[char]) -> u64 {
-    let autocomplete_costs: HashMap<char, u64> =
-        [('(', 1u64), ('[', 2u64), ('{', 3u64), ('<', 4u64)]
-            .iter()
-            .cloned()
-            .collect();
-    let autocomplete_stack = input.iter().rev();
-    let mut autocomplete_cost = 0u64;
+const DEFAULT_BRACKETS: [BracketPair; 4] = [
+    BracketPair::new('(', ')', 3, 1),
+    BracketPair::new('[', ']', 57, 2),
+    BracketPair::new('{', '}', 1197, 3),
+    BracketPair::new('<', '>', 25137, 4),
+];
 
-    for curr_open in autocomplete_stack {
-        autocomplete_cost *= 5;
-        autocomplete_cost += autocomplete_costs.get(curr_open).unwrap();
-    }
+#[derive(Debug, PartialEq, Eq)]
+enum CheckResult {
+    Complete,
+    // Columns start from 1. `expected` is None if there was nothing left to close.
+    Corrupted {
+        column: usize,
+        found: char,
+        expected: Option<char>,
+    },
+    Incomplete {
+        completion: String,
+    },
+}
 
-    autocomplete_cost
+struct SyntaxChecker {
+    pairs: Vec<BracketPair>,
 }
 
-fn compute_syntax_scores(input: &str) -> (u64, u64) {
-    let error_score: HashMap<char, u64> =
-        [(')', 3u64), ('>', 25137u64), ('}', 1197u64), (']', 57u64)]
-            .iter()
-            .cloned()
-            .collect();
-    let mut syntax_score = 0u64;
-    let mut autocomplete_costs = Vec::new();
-    for syntax_line in input.split_whitespace() {
-        match syntax_line_check(syntax_line) {
-            CheckResult::Wrong(wrong_char) => syntax_score += error_score.get(&wrong_char).unwrap(),
-            CheckResult::Incomlete(remaining_string) => {
-                autocomplete_costs.push(compute_autocomplete_cost(&remaining_string));
+impl SyntaxChecker {
+    fn new(pairs: &[BracketPair]) -> Self {
+        Self {
+            pairs: pairs.to_vec(),
+        }
+    }
+
+    fn opening(&self, open: char) -> Option<&BracketPair> {
+        self.pairs.iter().find(|pair| pair.open == open)
+    }
+
+    fn closing(&self, close: char) -> Option<&BracketPair> {
+        self.pairs.iter().find(|pair| pair.close == close)
+    }
+
+    pub fn check_line(&self, line: &str) -> CheckResult {
+        let mut parentheses_stack: Vec<&BracketPair> = Vec::new();
+
+        for (idx, par) in line.chars().enumerate() {
+            if let Some(pair) = self.opening(par) {
+                parentheses_stack.push(pair);
+            } else {
+                let expected = parentheses_stack.pop().map(|pair| pair.close);
+                if expected != Some(par) {
+                    return CheckResult::Corrupted {
+                        column: idx + 1,
+                        found: par,
+                        expected,
+                    };
+                }
+            }
+        }
+
+        if parentheses_stack.is_empty() {
+            CheckResult::Complete
+        } else {
+            CheckResult::Incomplete {
+                completion: parentheses_stack.iter().rev().map(|p| p.close).collect(),
+            }
+        }
+    }
+
+    // Fixes every corruption, replacing or dropping the wrong closing characters, and then
+    // completes the line
+    pub fn suggest_repair(&self, line: &str) -> String {
+        let mut repaired: Vec<char> = line.chars().collect();
+
+        loop {
+            let current: String = repaired.iter().collect();
+            match self.check_line(&current) {
+                CheckResult::Complete => return current,
+                CheckResult::Incomplete { completion } => return current + &completion,
+                CheckResult::Corrupted {
+                    column, expected, ..
+                } => match expected {
+                    Some(closing) => repaired[column - 1] = closing,
+                    None => {
+                        repaired.remove(column - 1);
+                    }
+                },
             }
         }
     }
 
+    // Characters that are not closing brackets are worth nothing
+    pub fn error_score(&self, found: char) -> u64 {
+        self.closing(found).map_or(0, |pair| pair.error_score)
+    }
+
+    pub fn completion_score(&self, completion: &str) -> u64 {
+        completion.chars().fold(0u64, |total, closing| {
+            total * 5
+                + self
+                    .closing(closing)
+                    .map_or(0, |pair| pair.completion_score)
+        })
+    }
+}
+
+fn syntax_error_score(checker: &SyntaxChecker, input: &str) -> u64 {
+    input
+        .split_whitespace()
+        .map(|syntax_line| match checker.check_line(syntax_line) {
+            CheckResult::Corrupted { found, .. } => checker.error_score(found),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn autocomplete_score(checker: &SyntaxChecker, input: &str) -> Option<u64> {
+    let mut autocomplete_costs: Vec<u64> = input
+        .split_whitespace()
+        .filter_map(|syntax_line| match checker.check_line(syntax_line) {
+            CheckResult::Incomplete { completion } => Some(checker.completion_score(&completion)),
+            _ => None,
+        })
+        .collect();
+
     autocomplete_costs.sort_unstable();
 
-    (
-        syntax_score,
-        autocomplete_costs[autocomplete_costs.len() / 2],
-    )
+    autocomplete_costs
+        .get(autocomplete_costs.len() / 2)
+        .cloned()
 }
 
 pub fn part1(input: &str) {
-    let (syntax_error_score, autocomplete_cost) = compute_syntax_scores(input);
+    let checker = SyntaxChecker::new(&DEFAULT_BRACKETS);
+
     println!(
-        "Syntax error score: {}; Autocomplete cost: {}",
-        syntax_error_score, autocomplete_cost
+        "Syntax error score: {}",
+        syntax_error_score(&checker, input)
     );
+
+    if !DETAILS.get() {
+        return;
+    }
+
+    for (line_idx, syntax_line) in input.split_whitespace().enumerate() {
+        if let CheckResult::Corrupted {
+            column,
+            found,
+            expected: Some(expected),
+        } = checker.check_line(syntax_line)
+        {
+            println!(
+                "Line {}, column {}: expected {}, but found {} instead (suggested repair: {})",
+                line_idx + 1,
+                column,
+                expected,
+                found,
+                checker.suggest_repair(syntax_line)
+            );
+        }
+    }
+}
+
+pub fn part2(input: &str) {
+    let checker = SyntaxChecker::new(&DEFAULT_BRACKETS);
+
+    match autocomplete_score(&checker, input) {
+        Some(autocomplete_cost) => println!("Autocomplete cost: {}", autocomplete_cost),
+        None => println!("There are no incomplete lines to autocomplete"),
+    }
 }
 
 #[cfg(test)]
@@ -96,7 +211,8 @@ mod tests {
                             <{([([[(<>()){}]>(<<{{
                             <{([{{}}[<[[[<>{}]]]>[]]";
 
-        let (syntax_error_score, _) = compute_syntax_scores(input_string);
+        let syntax_error_score =
+            syntax_error_score(&SyntaxChecker::new(&DEFAULT_BRACKETS), input_string);
 
         assert_eq!(syntax_error_score, 26397u64);
     }
@@ -114,8 +230,90 @@ mod tests {
                             <{([([[(<>()){}]>(<<{{
                             <{([{{}}[<[[[<>{}]]]>[]]";
 
-        let (_, autocomplete_cost) = compute_syntax_scores(input_string);
+        let autocomplete_cost =
+            autocomplete_score(&SyntaxChecker::new(&DEFAULT_BRACKETS), input_string);
+
+        assert_eq!(autocomplete_cost, Some(288957u64));
+    }
 
-        assert_eq!(autocomplete_cost, 288957u64);
+    #[test]
+    fn line_reports() {
+        let checker = SyntaxChecker::new(&DEFAULT_BRACKETS);
+
+        assert_eq!(
+            checker.check_line("{([(<{}[<>[]}>{[]{[(<()>"),
+            CheckResult::Corrupted {
+                column: 13,
+                found: '}',
+                expected: Some(']'),
+            }
+        );
+        assert_eq!(
+            checker.check_line("()]"),
+            CheckResult::Corrupted {
+                column: 3,
+                found: ']',
+                expected: None,
+            }
+        );
+        assert_eq!(
+            checker.check_line("[({(<(())[]>[[{[]{<()<>>"),
+            CheckResult::Incomplete {
+                completion: String::from("}}]])})]"),
+            }
+        );
+        assert_eq!(checker.completion_score("}}]])})]"), 288957);
+        assert_eq!(checker.check_line("<{}[]>"), CheckResult::Complete);
+    }
+
+    #[test]
+    fn repair_suggestions() {
+        let checker = SyntaxChecker::new(&DEFAULT_BRACKETS);
+
+        assert_eq!(
+            checker.suggest_repair("[<>({}){}[([])<>]]"),
+            "[<>({}){}[([])<>]]"
+        );
+        assert_eq!(checker.suggest_repair("[(()"), "[(())]");
+        assert_eq!(checker.suggest_repair("[(}<"), "[()<>]");
+        assert_eq!(checker.suggest_repair("())"), "()");
+
+        let repaired = checker.suggest_repair("{([(<{}[<>[]}>{[]{[(<()>");
+        assert_eq!(checker.check_line(&repaired), CheckResult::Complete);
+    }
+
+    #[test]
+    fn custom_brackets() {
+        let checker = SyntaxChecker::new(&[
+            BracketPair::new('«', '»', 10, 1),
+            BracketPair::new('/', '\\', 20, 2),
+        ]);
+
+        assert_eq!(checker.check_line("«/\\»"), CheckResult::Complete);
+        assert_eq!(
+            checker.check_line("«/»"),
+            CheckResult::Corrupted {
+                column: 3,
+                found: '»',
+                expected: Some('\\'),
+            }
+        );
+        assert_eq!(
+            checker.check_line("«/«"),
+            CheckResult::Incomplete {
+                completion: String::from("»\\»"),
+            }
+        );
+        assert_eq!(
+            checker.check_line("«()»"),
+            CheckResult::Corrupted {
+                column: 2,
+                found: '(',
+                expected: Some('»'),
+            }
+        );
+        assert_eq!(checker.error_score('\\'), 20);
+        assert_eq!(checker.error_score(')'), 0);
+        assert_eq!(checker.completion_score("»\\»"), (5 + 2) * 5 + 1);
     }
 }
@@ -1,41 +1,46 @@
-use hashbrown::HashSet;
 use std::convert::TryInto;
 use std::fmt;
 
-use itertools::Itertools;
-
 type Point = (usize, usize);
 
 struct OctopusGrid {
     data: Vec<u8>,
     rows: usize,
     columns: usize,
-    energy_to_location: [HashSet<Point>; 11],
+    // Buffers reused across steps, so stepping doesn't allocate
+    flashed: Vec<bool>,
+    flashed_cells: Vec<usize>,
+    pending_flashes: Vec<usize>,
 }
 
 impl OctopusGrid {
     pub fn new(input: &str) -> OctopusGrid {
         let grid_rows = input.split_whitespace();
         let mut flat_data = Vec::new();
-        let mut energy_to_location: [HashSet<Point>; 11] = [(); 11].map(|_| Default::default());
 
         let mut row_count = 0usize;
         let mut col_count = 0usize;
-        for (row, row_string) in grid_rows.enumerate() {
+        for row_string in grid_rows {
             row_count += 1;
             col_count = row_string.len();
-            for (col, risk) in row_string.chars().enumerate() {
+            for risk in row_string.chars() {
                 let risk_digit: u8 = risk.to_digit(10).unwrap().try_into().unwrap();
-                energy_to_location[usize::from(risk_digit)].insert((row, col));
                 flat_data.push(risk_digit);
             }
         }
+        assert_eq!(
+            flat_data.len(),
+            row_count * col_count,
+            "All the grid rows should have the same length!"
+        );
 
         OctopusGrid {
+            flashed: vec![false; flat_data.len()],
+            flashed_cells: Vec::with_capacity(flat_data.len()),
+            pending_flashes: Vec::with_capacity(flat_data.len()),
             data: flat_data,
             rows: row_count,
             columns: col_count,
-            energy_to_location,
         }
     }
 
@@ -51,94 +56,66 @@ impl OctopusGrid {
             .expect("Provided location is out of the grid bounds!")
     }
 
-    fn increase_energy(&mut self, location: &Point) {
-        let flat_idx = self.compute_flat_idx(location);
-        *self
-            .data
-            .get_mut(flat_idx)
-            .expect("Provided location is out of the grid bounds!") += 1u8;
-    }
-
-    fn reset_energy(&mut self, location: &Point) {
-        let flat_idx = self.compute_flat_idx(location);
-        *self
-            .data
-            .get_mut(flat_idx)
-            .expect("Provided location is out of the grid bounds!") = 0u8;
+    // Octopi that flashed during the last step
+    pub fn flashed_cells(&self) -> impl Iterator<Item = Point> + '_ {
+        self.flashed_cells
+            .iter()
+            .map(|idx| (idx / self.columns, idx % self.columns))
     }
 
-    fn get_neighbours(&self, location: &Point) -> HashSet<Point> {
-        let mut neighbours: HashSet<Point> = HashSet::new();
-
-        let up = if location.0 > 0 {
-            location.0 - 1
-        } else {
-            location.0
-        };
-
-        let down = if location.0 < self.rows - 1 {
-            location.0 + 1
-        } else {
-            location.0
-        };
-
-        let left = if location.1 > 0 {
-            location.1 - 1
-        } else {
-            location.1
-        };
-
-        let right = if location.1 < self.columns - 1 {
-            location.1 + 1
-        } else {
-            location.1
-        };
-
-        let neigh_cross_product = (up..=down).cartesian_product(left..=right);
-
-        neighbours.extend(neigh_cross_product);
-        neighbours.remove(location);
-
-        neighbours
+    // Raises the energy of an octopus, queueing it up if that makes it flash
+    fn energise(&mut self, flat_idx: usize) {
+        self.data[flat_idx] += 1;
+        if self.data[flat_idx] > 9 && !self.flashed[flat_idx] {
+            self.flashed[flat_idx] = true;
+            self.flashed_cells.push(flat_idx);
+            self.pending_flashes.push(flat_idx);
+        }
     }
 
     fn step(&mut self) -> u64 {
-        let mut flashing_octopi: HashSet<Point> = HashSet::new();
-
-        self.data.iter_mut().for_each(|octo| *octo += 1);
+        for &idx in self.flashed_cells.iter() {
+            self.flashed[idx] = false;
+        }
+        self.flashed_cells.clear();
 
-        // Increase energies by one
-        for energy in (0..=10).rev().skip(1) {
-            self.energy_to_location[energy + 1]
-                .extend(self.energy_to_location[energy].clone().iter());
-            self.energy_to_location[energy].clear();
+        for idx in 0..self.data.len() {
+            self.energise(idx);
         }
 
-        while !self.energy_to_location[10].is_empty() {
-            for flashing_octo in &self.energy_to_location[10].clone() {
-                let close_octos = self.get_neighbours(flashing_octo);
-                for neighbour in close_octos {
-                    let nei_energy: usize = self.get_energy(&neighbour).into();
-                    if nei_energy < 10 {
-                        self.increase_energy(&neighbour);
-                        self.energy_to_location[nei_energy + 1].insert(neighbour);
-                        self.energy_to_location[nei_energy].remove(&neighbour);
+        while let Some(idx) = self.pending_flashes.pop() {
+            let (row, col) = (idx / self.columns, idx % self.columns);
+            for neigh_row in row.saturating_sub(1)..=(row + 1).min(self.rows - 1) {
+                for neigh_col in col.saturating_sub(1)..=(col + 1).min(self.columns - 1) {
+                    if (neigh_row, neigh_col) != (row, col) {
+                        self.energise(neigh_row * self.columns + neigh_col);
                     }
                 }
-                flashing_octopi.insert(*flashing_octo);
-                self.energy_to_location[10].remove(flashing_octo);
             }
         }
 
-        for spent_octopus in flashing_octopi.iter() {
-            self.reset_energy(spent_octopus);
+        for &idx in self.flashed_cells.iter() {
+            self.data[idx] = 0;
         }
 
-        flashing_octopi.len().try_into().unwrap()
+        self.flashed_cells.len().try_into().unwrap()
+    }
+
+    // Calls `observer` with the step number, from 1, and the grid after each step
+    pub fn step_observed<F>(&mut self, t: usize, mut observer: F) -> u64
+    where
+        F: FnMut(usize, &OctopusGrid),
+    {
+        (1..=t).fold(0u64, |sum, step| {
+            let flashes = self.step();
+            observer(step, self);
+            sum + flashes
+        })
     }
 
+    #[cfg(test)]
     pub fn step_for(&mut self, t: usize) -> u64 {
-        (0..t).fold(0u64, |sum, _| sum + self.step())
+        self.step_observed(t, |_, _| {})
     }
 
     pub fn first_coordinated_flash(&mut self) -> u64 {
@@ -154,34 +131,31 @@ impl OctopusGrid {
 
 impl fmt::Display for OctopusGrid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Grid dimensions: {} x {}", self.rows, self.columns)?;
-
         for row in 0..self.rows {
             for col in 0..self.columns {
-                let risk_location = (row, col);
-                write!(f, "{} ", self.get_energy(&risk_location))?;
-            }
-            writeln!(f)?;
-        }
-
-        writeln!(f)?;
-        writeln!(f, "Reverse energies lookup: ")?;
-        for (energy, locations) in self.energy_to_location.iter().enumerate() {
-            write!(f, "{}: ", energy)?;
-            for loc in locations {
-                write!(f, "{}, {}; ", loc.0, loc.1)?;
+                write!(f, "{}", self.get_energy(&(row, col)))?;
             }
             writeln!(f)?;
         }
 
-        writeln!(f)
+        Ok(())
     }
 }
 
 pub fn part1(input: &str) {
     let mut octo_grid = OctopusGrid::new(input);
-    let final_flashes = octo_grid.step_for(100);
+    let mut busiest_step = (0usize, 0usize);
+    let final_flashes = octo_grid.step_observed(100, |step, grid| {
+        let flashes = grid.flashed_cells().count();
+        if flashes > busiest_step.1 {
+            busiest_step = (step, flashes);
+        }
+    });
     println!("Flashes after 100 steps: {}", final_flashes);
+    println!(
+        "Most flashes in a single step: {} (step {})",
+        busiest_step.1, busiest_step.0
+    );
 }
 
 pub fn part2(input: &str) {
@@ -248,4 +222,56 @@ mod tests {
 
         assert_eq!(octo_grid.first_coordinated_flash(), 195u64);
     }
+
+    #[test]
+    fn observed_steps() {
+        let input_string = "11111
+                            19991
+                            19191
+                            19991
+                            11111";
+
+        let mut octo_grid = OctopusGrid::new(input_string);
+        let mut frames: Vec<(usize, String, Vec<Point>)> = Vec::new();
+        octo_grid.step_observed(2, |step, grid| {
+            let mut flashed: Vec<Point> = grid.flashed_cells().collect();
+            flashed.sort_unstable();
+            frames.push((step, grid.to_string(), flashed));
+        });
+
+        let centre: Vec<Point> = (1..4).flat_map(|r| (1..4).map(move |c| (r, c))).collect();
+        assert_eq!(
+            frames,
+            vec![
+                (
+                    1,
+                    String::from("34543\n40004\n50005\n40004\n34543\n"),
+                    centre
+                ),
+                (
+                    2,
+                    String::from("45654\n51115\n61116\n51115\n45654\n"),
+                    vec![]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn any_grid_size() {
+        let mut single_row = OctopusGrid::new("909");
+        assert_eq!(single_row.step_for(1), 2);
+        assert_eq!(single_row.to_string(), "030\n");
+        assert_eq!(
+            single_row.flashed_cells().collect::<Vec<Point>>(),
+            vec![(0, 0), (0, 2)]
+        );
+
+        let mut tall = OctopusGrid::new("9 8 7 1");
+        assert_eq!(tall.step_for(1), 2);
+        assert_eq!(tall.to_string(), "0\n0\n9\n2\n");
+
+        assert_eq!(OctopusGrid::new("99").first_coordinated_flash(), 1);
+        assert_eq!(OctopusGrid::new("0").first_coordinated_flash(), 10);
+    }
 }
@@ -1,6 +1,7 @@
-use hashbrown::{HashMap, HashSet};
-use std::collections::VecDeque;
+use hashbrown::HashMap;
 use std::convert::TryFrom;
+use std::env::temp_dir;
+use std::fs;
 
 use nom::bytes::complete::tag;
 use nom::character::complete::alphanumeric1;
@@ -8,6 +9,12 @@ use nom::combinator::map;
 use nom::sequence::separated_pair;
 use nom::IResult;
 
+use crate::aoc_lib::params::{DayParams, Param};
+
+const EXPORT_DOT: Param<bool> = Param::new("export", false);
+
+pub const PARAMS: DayParams = &[&EXPORT_DOT];
+
 // Edge parser
 fn edge(input: &str) -> IResult<&str, (&str, &str)> {
     let parser = separated_pair(alphanumeric1, tag("-"), alphanumeric1);
@@ -17,167 +24,402 @@ fn edge(input: &str) -> IResult<&str, (&str, &str)> {
     })(input)
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum CaveType {
     Start,
     End,
-    Small(String),
-    Big(String),
+    Small,
+    Big,
+}
+
+impl CaveType {
+    fn from_name(name: &str) -> Self {
+        if name.eq("start") {
+            CaveType::Start
+        } else if name.eq("end") {
+            CaveType::End
+        } else if name.chars().all(|c| c.is_lowercase()) {
+            CaveType::Small
+        } else {
+            CaveType::Big
+        }
+    }
+}
+
+// Small cave visits are packed in a single integer, a nibble per cave
+const VISIT_BITS: u32 = 4;
+const MAX_SMALL_CAVES: usize = (u128::BITS / VISIT_BITS) as usize;
+// Visited waypoints are tracked with a bit each
+const MAX_WAYPOINTS: usize = u64::BITS as usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisitPolicy {
+    // Maximum amount of times a single small cave can be entered
+    pub max_small_visits: u32,
+    // Total amount of repeated visits to small caves allowed along a path
+    pub revisit_budget: u32,
+    // Caves that cannot be entered at all
+    pub forbidden: Vec<String>,
+    // Caves that have to be visited at least once before reaching the end
+    pub waypoints: Vec<String>,
+}
+
+impl VisitPolicy {
+    pub fn new(max_small_visits: u32, revisit_budget: u32) -> Self {
+        assert!(
+            max_small_visits > 0 && max_small_visits < (1 << VISIT_BITS),
+            "Unsupported amount of visits per small cave"
+        );
+
+        Self {
+            max_small_visits,
+            revisit_budget,
+            forbidden: Vec::new(),
+            waypoints: Vec::new(),
+        }
+    }
+
+    pub fn single_visit() -> Self {
+        Self::new(1, 0)
+    }
+
+    pub fn one_repeat() -> Self {
+        Self::new(2, 1)
+    }
+
+    // Policies can be put together field by field, this checks the ones the search can't handle
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_small_visits == 0 || self.max_small_visits >= (1 << VISIT_BITS) {
+            return Err(format!(
+                "Unsupported amount of visits per small cave: {}, it must be between 1 and {}",
+                self.max_small_visits,
+                (1 << VISIT_BITS) - 1
+            ));
+        }
+        if self.waypoints.len() > MAX_WAYPOINTS {
+            return Err(format!(
+                "Too many waypoints: {}, at most {} are supported",
+                self.waypoints.len(),
+                MAX_WAYPOINTS
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Visit policy translated in terms of cave ids
+struct CaveRules {
+    max_small_visits: u32,
+    forbidden: Vec<bool>,
+    waypoint_bits: Vec<u64>,
+    all_waypoints: u64,
+}
+
+impl CaveRules {
+    fn new(network: &CaveNetwork, policy: &VisitPolicy) -> Result<Self, String> {
+        policy.validate()?;
+
+        let mut forbidden = vec![false; network.names.len()];
+        let mut waypoint_bits = vec![0u64; network.names.len()];
+        let mut all_waypoints: u64 = 0;
+
+        for (cave, name) in network.names.iter().enumerate() {
+            forbidden[cave] = policy.forbidden.contains(name);
+        }
+
+        // Waypoints that are not in the network get a bit anyway, so that no path satisfies them
+        for (idx, waypoint) in policy.waypoints.iter().enumerate() {
+            let waypoint_bit = 1u64 << idx;
+            all_waypoints |= waypoint_bit;
+            if let Some(cave) = network.names.iter().position(|name| name == waypoint) {
+                waypoint_bits[cave] |= waypoint_bit;
+            }
+        }
+
+        Ok(Self {
+            max_small_visits: policy.max_small_visits,
+            forbidden,
+            waypoint_bits,
+            all_waypoints,
+        })
+    }
 }
 
 #[derive(Debug)]
 struct CaveNetwork {
-    edges: HashMap<String, Vec<CaveType>>,
-    small_caves: HashSet<String>,
+    names: Vec<String>,
+    types: Vec<CaveType>,
+    // Caves reachable from each cave. Nothing leads back to the start.
+    edges: Vec<Vec<usize>>,
+    // Tunnels as they appear in the input
+    tunnels: Vec<(usize, usize)>,
+    // Offset of the visit counter of each small cave in the packed visits
+    small_shifts: Vec<u32>,
+    start: usize,
+    end: usize,
 }
 
 impl CaveNetwork {
     pub fn new(input: &str) -> CaveNetwork {
-        let mut edge_map: HashMap<String, Vec<CaveType>> = HashMap::new();
-        let mut small_set: HashSet<String> = HashSet::new();
+        let mut cave_ids: HashMap<String, usize> = HashMap::new();
+        let mut network = CaveNetwork {
+            names: Vec::new(),
+            types: Vec::new(),
+            edges: Vec::new(),
+            tunnels: Vec::new(),
+            small_shifts: Vec::new(),
+            start: 0,
+            end: 0,
+        };
 
         for line in input.lines() {
             let (_, (start, end)) = edge(line.trim()).expect("Something went super wrong!");
-            let end_type = if end.eq("start") {
-                CaveType::Start
-            } else if end.eq("end") {
-                CaveType::End
-            } else if end.chars().all(|c| c.is_lowercase()) {
-                small_set.insert(end.to_string());
-                CaveType::Small(end.to_owned())
-            } else {
-                CaveType::Big(end.to_owned())
-            };
+            let start_id = network.intern(&mut cave_ids, start);
+            let end_id = network.intern(&mut cave_ids, end);
 
-            if end_type != CaveType::Start {
-                match edge_map.get_mut(start) {
-                    Some(map_entry) => {
-                        map_entry.push(end_type);
-                    }
-                    None => {
-                        edge_map.insert(start.to_owned(), vec![end_type]);
-                    }
-                }
+            network.tunnels.push((start_id, end_id));
+            if network.types[end_id] != CaveType::Start {
+                network.edges[start_id].push(end_id);
             }
-
-            let start_type = if start.eq("start") {
-                CaveType::Start
-            } else if start.eq("end") {
-                CaveType::End
-            } else if start.chars().all(|c| c.is_lowercase()) {
-                small_set.insert(start.to_string());
-                CaveType::Small(start.to_owned())
-            } else {
-                CaveType::Big(start.to_owned())
-            };
-
-            if start_type != CaveType::Start {
-                match edge_map.get_mut(end) {
-                    Some(map_entry) => {
-                        map_entry.push(start_type);
-                    }
-                    None => {
-                        edge_map.insert(end.to_owned(), vec![start_type]);
-                    }
-                }
+            if network.types[start_id] != CaveType::Start {
+                network.edges[end_id].push(start_id);
             }
         }
 
-        CaveNetwork {
-            edges: edge_map,
-            small_caves: small_set,
+        network.start = *cave_ids.get("start").expect("The network has no start");
+        network.end = *cave_ids.get("end").expect("The network has no end");
+
+        network
+    }
+
+    fn intern(&mut self, cave_ids: &mut HashMap<String, usize>, name: &str) -> usize {
+        if let Some(id) = cave_ids.get(name) {
+            return *id;
         }
+
+        let id = self.names.len();
+        let cave_type = CaveType::from_name(name);
+        let small_count = self.types.iter().filter(|t| **t == CaveType::Small).count();
+        assert!(
+            small_count < MAX_SMALL_CAVES,
+            "Too many small caves to track their visits"
+        );
+
+        self.names.push(name.to_owned());
+        self.types.push(cave_type);
+        self.edges.push(Vec::new());
+        self.small_shifts
+            .push(u32::try_from(small_count).unwrap() * VISIT_BITS);
+        cave_ids.insert(name.to_owned(), id);
+
+        id
     }
 
-    fn find_unique_paths(&self, repeatable_cave: &str) -> HashSet<String> {
-        let mut path_small_caves_visit: Vec<HashSet<String>> = Vec::new();
-        let mut path_repeated_cave: Vec<bool> = Vec::new();
-        let mut debug_path: Vec<Vec<String>> = Vec::new();
-        let mut visit_queue: VecDeque<(usize, CaveType)> = VecDeque::new();
-        visit_queue.push_back((0, CaveType::Start));
-
-        let mut found_paths: HashSet<String> = HashSet::new();
-
-        while let Some(current_cave) = visit_queue.pop_front() {
-            match current_cave {
-                (_, CaveType::Start) => {
-                    let first_caves = self.edges.get("start").unwrap();
-                    for (path, next_cave) in first_caves.iter().enumerate() {
-                        assert_eq!(path_small_caves_visit.len(), path);
-                        path_small_caves_visit.push(HashSet::new());
-                        path_repeated_cave.push(false);
-                        debug_path.push(vec!["start".to_string()]);
-                        visit_queue.push_back((path, next_cave.to_owned()));
-                    }
-                }
-                (path, CaveType::End) => {
-                    let completed_path = debug_path.get_mut(path).unwrap();
-                    completed_path.push("end".to_string());
-                    let mut path_string = String::new();
-                    for (idx, cave) in completed_path.iter().enumerate() {
-                        path_string.push_str(cave);
-                        if idx < completed_path.len() - 1 {
-                            path_string.push_str("=>");
-                        }
-                    }
-                    found_paths.insert(path_string);
-                }
-                (path, CaveType::Small(cave_name)) => {
-                    let current_visit_path = path_small_caves_visit.get_mut(path).unwrap();
-                    if !current_visit_path.contains(&cave_name) {
-                        if !cave_name.eq(repeatable_cave) || *path_repeated_cave.get(path).unwrap()
-                        {
-                            current_visit_path.insert(cave_name.to_owned());
-                        } else {
-                            *path_repeated_cave.get_mut(path).unwrap() = true;
-                        }
+    // Amount of paths from start to end allowed by the given policy
+    pub fn count_paths(&self, policy: &VisitPolicy) -> Result<u64, String> {
+        let rules = CaveRules::new(self, policy)?;
+        let mut results_lut: HashMap<(usize, u128, u32, u64), u64> = HashMap::new();
+
+        // The start can be a waypoint too, and it's always visited
+        let start_waypoints = rules.waypoint_bits[self.start];
+        Ok(self.rec_count_paths(
+            &rules,
+            &mut results_lut,
+            (self.start, 0, policy.revisit_budget, start_waypoints),
+        ))
+    }
 
-                        let next_caves = self.edges.get(&cave_name).unwrap();
-                        visit_queue.push_back((path, next_caves.first().unwrap().clone()));
-                        debug_path.get_mut(path).unwrap().push(cave_name);
-
-                        for next_cave in next_caves.iter().skip(1) {
-                            let new_path_id = path_small_caves_visit.len();
-                            visit_queue.push_back((new_path_id, next_cave.to_owned()));
-                            path_small_caves_visit
-                                .push(path_small_caves_visit.get(path).unwrap().clone());
-                            debug_path.push(debug_path.get(path).unwrap().clone());
-                            path_repeated_cave.push(*path_repeated_cave.get(path).unwrap());
-                        }
+    // The state is the current cave, the packed small cave visits, the remaining revisit budget
+    // and the waypoints visited so far
+    fn rec_count_paths(
+        &self,
+        rules: &CaveRules,
+        results_lut: &mut HashMap<(usize, u128, u32, u64), u64>,
+        state: (usize, u128, u32, u64),
+    ) -> u64 {
+        if let Some(paths) = results_lut.get(&state) {
+            return *paths;
+        }
+
+        let (cave, visits, revisit_budget, waypoints) = state;
+        let mut paths: u64 = 0;
+
+        for next_cave in self.edges[cave].iter().cloned() {
+            if rules.forbidden[next_cave] {
+                continue;
+            }
+
+            let next_waypoints = waypoints | rules.waypoint_bits[next_cave];
+            paths += match self.types[next_cave] {
+                CaveType::End => u64::from(next_waypoints == rules.all_waypoints),
+                CaveType::Start => 0,
+                CaveType::Big => self.rec_count_paths(
+                    rules,
+                    results_lut,
+                    (next_cave, visits, revisit_budget, next_waypoints),
+                ),
+                CaveType::Small => {
+                    let shift = self.small_shifts[next_cave];
+                    let cave_visits = ((visits >> shift) & ((1 << VISIT_BITS) - 1)) as u32;
+                    let next_visits = visits + (1 << shift);
+
+                    if cave_visits == 0 {
+                        self.rec_count_paths(
+                            rules,
+                            results_lut,
+                            (next_cave, next_visits, revisit_budget, next_waypoints),
+                        )
+                    } else if cave_visits < rules.max_small_visits && revisit_budget > 0 {
+                        self.rec_count_paths(
+                            rules,
+                            results_lut,
+                            (next_cave, next_visits, revisit_budget - 1, next_waypoints),
+                        )
                     } else {
-                        debug_path.get_mut(path).unwrap().push("fail".to_string());
-                    }
-                }
-                (path, CaveType::Big(cave_name)) => {
-                    let next_caves = self.edges.get(&cave_name).unwrap();
-                    visit_queue.push_back((path, next_caves.first().unwrap().clone()));
-                    debug_path.get_mut(path).unwrap().push(cave_name);
-
-                    for next_cave in next_caves.iter().skip(1) {
-                        let new_path_id = path_small_caves_visit.len();
-                        visit_queue.push_back((new_path_id, next_cave.to_owned()));
-                        path_small_caves_visit
-                            .push(path_small_caves_visit.get(path).unwrap().clone());
-                        debug_path.push(debug_path.get(path).unwrap().clone());
-                        path_repeated_cave.push(*path_repeated_cave.get(path).unwrap());
+                        0
                     }
                 }
             };
         }
 
-        found_paths
+        results_lut.insert(state, paths);
+
+        paths
+    }
+
+    pub fn paths(&self, policy: &VisitPolicy) -> Result<CavePaths<'_>, String> {
+        let mut visits = vec![0; self.names.len()];
+        visits[self.start] = 1;
+
+        Ok(CavePaths {
+            network: self,
+            rules: CaveRules::new(self, policy)?,
+            path: vec![(self.start, 0)],
+            visits,
+            revisit_budget: policy.revisit_budget,
+        })
     }
 
     pub fn find_paths(&self, allow_repetition: bool) -> u64 {
-        let mut total_paths: HashSet<String> = HashSet::new();
-        if allow_repetition {
-            for cave in self.small_caves.iter() {
-                total_paths.extend(self.find_unique_paths(cave).iter().cloned());
-            }
+        let policy = if allow_repetition {
+            VisitPolicy::one_repeat()
         } else {
-            total_paths = self.find_unique_paths("");
+            VisitPolicy::single_visit()
+        };
+
+        self.count_paths(&policy)
+            .expect("The built-in policies are always valid")
+    }
+
+    // Graphviz description of the network: start and end are double circles, big caves boxes
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph caves {\n");
+
+        for (name, cave_type) in self.names.iter().zip(self.types.iter()) {
+            let shape = match cave_type {
+                CaveType::Start | CaveType::End => "doublecircle",
+                CaveType::Small => "circle",
+                CaveType::Big => "box",
+            };
+            dot.push_str(&format!("    \"{}\" [shape={}];\n", name, shape));
+        }
+
+        for (left, right) in self.tunnels.iter() {
+            dot.push_str(&format!(
+                "    \"{}\" -- \"{}\";\n",
+                self.names[*left], self.names[*right]
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// Lazy depth-first enumeration of the paths counted by `CaveNetwork::count_paths`
+struct CavePaths<'a> {
+    network: &'a CaveNetwork,
+    rules: CaveRules,
+    // Caves of the current path, each with the index of the next neighbour to explore
+    path: Vec<(usize, usize)>,
+    visits: Vec<u32>,
+    revisit_budget: u32,
+}
+
+impl CavePaths<'_> {
+    fn can_visit(&self, cave: usize) -> bool {
+        if self.rules.forbidden[cave] {
+            return false;
+        }
+
+        match self.network.types[cave] {
+            CaveType::Start => false,
+            CaveType::Small => {
+                self.visits[cave] == 0
+                    || (self.visits[cave] < self.rules.max_small_visits && self.revisit_budget > 0)
+            }
+            CaveType::Big | CaveType::End => true,
+        }
+    }
+
+    fn waypoints_visited(&self) -> bool {
+        let visited = self.path.iter().fold(0u64, |seen, (cave, _)| {
+            seen | self.rules.waypoint_bits[*cave]
+        });
+        visited | self.rules.waypoint_bits[self.network.end] == self.rules.all_waypoints
+    }
+
+    fn enter(&mut self, cave: usize) {
+        if self.network.types[cave] == CaveType::Small && self.visits[cave] > 0 {
+            self.revisit_budget -= 1;
+        }
+        self.visits[cave] += 1;
+        self.path.push((cave, 0));
+    }
+
+    fn leave(&mut self) {
+        if let Some((cave, _)) = self.path.pop() {
+            self.visits[cave] -= 1;
+            if self.network.types[cave] == CaveType::Small && self.visits[cave] > 0 {
+                self.revisit_budget += 1;
+            }
         }
-        u64::try_from(total_paths.len()).unwrap()
+    }
+}
+
+impl<'a> Iterator for CavePaths<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((cave, next_edge)) = self.path.last_mut() {
+            match self.network.edges[*cave].get(*next_edge).cloned() {
+                Some(next_cave) => {
+                    *next_edge += 1;
+                    if !self.can_visit(next_cave) {
+                        continue;
+                    }
+
+                    if next_cave == self.network.end {
+                        if self.waypoints_visited() {
+                            let network = self.network;
+                            return Some(
+                                self.path
+                                    .iter()
+                                    .map(|(cave, _)| network.names[*cave].as_str())
+                                    .chain([network.names[next_cave].as_str()])
+                                    .collect(),
+                            );
+                        }
+                    } else {
+                        self.enter(next_cave);
+                    }
+                }
+                None => self.leave(),
+            }
+        }
+
+        None
     }
 }
 
@@ -185,6 +427,24 @@ pub fn part1(input: &str) {
     let cave_net = CaveNetwork::new(input);
     let path_count = cave_net.find_paths(false);
     println!("Amount of unique paths to the exit: {}", path_count);
+
+    if let Some(shortest) = cave_net
+        .paths(&VisitPolicy::single_visit())
+        .expect("The built-in policies are always valid")
+        .min_by_key(|path| path.len())
+    {
+        println!("One of the shortest paths: {}", shortest.join(" => "));
+    }
+
+    if !EXPORT_DOT.get() {
+        return;
+    }
+
+    let dot_path = temp_dir().join("day12_caves.dot");
+    match fs::write(&dot_path, cave_net.to_dot()) {
+        Ok(()) => println!("Cave network exported to {}", dot_path.display()),
+        Err(e) => println!("Failed to export the cave network: {}", e),
+    }
 }
 
 pub fn part2(input: &str) {
@@ -198,6 +458,8 @@ pub fn part2(input: &str) {
 
 #[cfg(test)]
 mod tests {
+    use hashbrown::HashSet;
+
     use super::*;
 
     #[test]
@@ -323,4 +585,239 @@ mod tests {
 
         assert_eq!(path_count, 3509u64);
     }
+
+    #[test]
+    fn lazy_paths() {
+        let input_string = "start-A
+                        start-b
+                        A-c
+                        A-b
+                        b-d
+                        A-end
+                        b-end";
+
+        let cave_net = CaveNetwork::new(input_string);
+
+        let mut paths: Vec<String> = cave_net
+            .paths(&VisitPolicy::single_visit())
+            .unwrap()
+            .map(|p| p.join(","))
+            .collect();
+        paths.sort();
+
+        let ref_paths = vec![
+            "start,A,b,A,c,A,end",
+            "start,A,b,A,end",
+            "start,A,b,end",
+            "start,A,c,A,b,A,end",
+            "start,A,c,A,b,end",
+            "start,A,c,A,end",
+            "start,A,end",
+            "start,b,A,c,A,end",
+            "start,b,A,end",
+            "start,b,end",
+        ];
+
+        assert_eq!(paths, ref_paths);
+    }
+
+    #[test]
+    fn larger_revisit_budget() {
+        let input_string = "dc-end
+                        HN-start
+                        start-kj
+                        dc-start
+                        dc-HN
+                        LN-dc
+                        HN-end
+                        kj-sa
+                        kj-HN
+                        kj-dc";
+
+        let cave_net = CaveNetwork::new(input_string);
+
+        for budget in 0..4 {
+            let policy = VisitPolicy::new(budget + 1, budget);
+            let lazy_paths: Vec<Vec<&str>> = cave_net.paths(&policy).unwrap().collect();
+            let unique_paths: HashSet<&Vec<&str>> = lazy_paths.iter().collect();
+
+            assert_eq!(lazy_paths.len(), unique_paths.len());
+            assert_eq!(cave_net.count_paths(&policy), Ok(lazy_paths.len() as u64));
+        }
+
+        assert_eq!(cave_net.count_paths(&VisitPolicy::new(2, 1)), Ok(103));
+        assert!(cave_net.count_paths(&VisitPolicy::new(3, 2)).unwrap() > 103);
+    }
+
+    #[test]
+    fn custom_policies() {
+        let input_string = "start-A
+                        start-b
+                        A-c
+                        A-b
+                        b-d
+                        A-end
+                        b-end";
+
+        let cave_net = CaveNetwork::new(input_string);
+
+        let check_policy = |policy: VisitPolicy| {
+            let lazy_paths: Vec<Vec<&str>> = cave_net.paths(&policy).unwrap().collect();
+            assert_eq!(cave_net.count_paths(&policy), Ok(lazy_paths.len() as u64));
+            lazy_paths
+        };
+
+        // Without "c" only the paths that never go through it are left
+        let no_c = check_policy(VisitPolicy {
+            forbidden: vec![String::from("c")],
+            ..VisitPolicy::single_visit()
+        });
+        assert_eq!(no_c.len(), 5);
+        assert!(no_c.iter().all(|path| !path.contains(&"c")));
+
+        // Forbidding the exit means that there's no way out
+        assert!(check_policy(VisitPolicy {
+            forbidden: vec![String::from("end")],
+            ..VisitPolicy::one_repeat()
+        })
+        .is_empty());
+
+        let through_c = check_policy(VisitPolicy {
+            waypoints: vec![String::from("c")],
+            ..VisitPolicy::single_visit()
+        });
+        assert_eq!(through_c.len(), 5);
+        assert!(through_c.iter().all(|path| path.contains(&"c")));
+
+        let through_c_and_b = check_policy(VisitPolicy {
+            waypoints: vec![String::from("c"), String::from("b")],
+            ..VisitPolicy::single_visit()
+        });
+        assert_eq!(through_c_and_b.len(), 4);
+
+        assert!(check_policy(VisitPolicy {
+            waypoints: vec![String::from("d")],
+            ..VisitPolicy::single_visit()
+        })
+        .is_empty());
+        assert!(check_policy(VisitPolicy {
+            waypoints: vec![String::from("nowhere")],
+            ..VisitPolicy::single_visit()
+        })
+        .is_empty());
+
+        // "b" visited up to three times, but only one repetition per path overall
+        let budget_limited = check_policy(VisitPolicy::new(3, 1));
+        assert_eq!(budget_limited.len(), 36);
+
+        // Two repetitions but each small cave can be entered only twice
+        let visit_limited = check_policy(VisitPolicy::new(2, 2));
+        assert!(visit_limited.iter().all(|path| {
+            ["b", "c", "d"]
+                .iter()
+                .all(|cave| path.iter().filter(|c| *c == cave).count() <= 2)
+        }));
+        assert!(visit_limited.len() > 36);
+        assert!(visit_limited.len() < check_policy(VisitPolicy::new(3, 2)).len());
+    }
+
+    #[test]
+    fn waypoint_limit() {
+        let cave_net = CaveNetwork::new("start-A\nA-b\nb-end");
+
+        let through = |count: usize| VisitPolicy {
+            waypoints: std::iter::once(String::from("b"))
+                .chain((1..count).map(|idx| format!("w{}", idx)))
+                .collect(),
+            ..VisitPolicy::single_visit()
+        };
+
+        assert_eq!(through(1).validate(), Ok(()));
+        assert_eq!(cave_net.count_paths(&through(1)), Ok(1));
+        assert_eq!(through(64).validate(), Ok(()));
+        assert_eq!(cave_net.count_paths(&through(64)), Ok(0));
+
+        assert_eq!(
+            through(65).validate(),
+            Err(String::from(
+                "Too many waypoints: 65, at most 64 are supported"
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_policies() {
+        let cave_net = CaveNetwork::new("start-A\nA-b\nb-end");
+
+        let many_waypoints = VisitPolicy {
+            waypoints: (0..65).map(|idx| format!("w{}", idx)).collect(),
+            ..VisitPolicy::single_visit()
+        };
+        assert!(cave_net.count_paths(&many_waypoints).is_err());
+        assert!(cave_net.paths(&many_waypoints).is_err());
+
+        // Sixteen visits would overflow into the counter of the next small cave
+        let many_visits = VisitPolicy {
+            max_small_visits: 16,
+            ..VisitPolicy::one_repeat()
+        };
+        assert_eq!(
+            cave_net.count_paths(&many_visits),
+            Err(String::from(
+                "Unsupported amount of visits per small cave: 16, it must be between 1 and 15"
+            ))
+        );
+        assert!(cave_net.paths(&many_visits).is_err());
+
+        let no_visits = VisitPolicy {
+            max_small_visits: 0,
+            ..VisitPolicy::single_visit()
+        };
+        assert!(no_visits.validate().is_err());
+    }
+
+    #[test]
+    fn start_and_end_waypoints() {
+        let cave_net = CaveNetwork::new(
+            "start-A
+            start-b
+            A-c
+            A-b
+            b-d
+            A-end
+            b-end",
+        );
+
+        for waypoints in [vec!["start"], vec!["end"], vec!["start", "c", "end"]] {
+            let policy = VisitPolicy {
+                waypoints: waypoints.into_iter().map(String::from).collect(),
+                ..VisitPolicy::one_repeat()
+            };
+            let lazy_count = cave_net.paths(&policy).unwrap().count() as u64;
+            assert!(lazy_count > 0);
+            assert_eq!(cave_net.count_paths(&policy), Ok(lazy_count));
+        }
+    }
+
+    #[test]
+    fn dot_export() {
+        let input_string = "start-A
+                        A-b
+                        b-end";
+
+        let cave_net = CaveNetwork::new(input_string);
+
+        let ref_dot = "graph caves {
+    \"start\" [shape=doublecircle];
+    \"A\" [shape=box];
+    \"b\" [shape=circle];
+    \"end\" [shape=doublecircle];
+    \"start\" -- \"A\";
+    \"A\" -- \"b\";
+    \"b\" -- \"end\";
+}
+";
+
+        assert_eq!(cave_net.to_dot(), ref_dot);
+    }
 }
@@ -1,6 +1,7 @@
-use hashbrown::{HashMap, HashSet};
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
+use std::env::temp_dir;
+use std::fs;
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
@@ -8,6 +9,13 @@ use nom::character::complete::{digit1, one_of, space0};
 use nom::sequence::{preceded, separated_pair};
 use nom::{IResult, ParseTo};
 
+use crate::aoc_lib::ocr;
+use crate::aoc_lib::params::{DayParams, Param};
+
+const EXPORT_PUZZLE: Param<bool> = Param::new("export", false);
+
+pub const PARAMS: DayParams = &[&EXPORT_PUZZLE];
+
 // Activation instructions parsers
 fn point_location(input: &str) -> IResult<&str, ActivationInstruction> {
     let (rem_input, (x, y)) = separated_pair(digit1, tag(","), digit1)(input)?;
@@ -43,6 +51,15 @@ enum OrigamiFold {
     Horizontal(usize),
 }
 
+impl OrigamiFold {
+    pub fn to_instruction(&self) -> String {
+        match self {
+            OrigamiFold::Vertical(col) => format!("fold along x={}", col),
+            OrigamiFold::Horizontal(row) => format!("fold along y={}", row),
+        }
+    }
+}
+
 // Folding instructions
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum ActivationInstruction {
@@ -50,182 +67,332 @@ enum ActivationInstruction {
     Fold(OrigamiFold),
 }
 
-#[derive(Debug)]
-struct ActivationData {
-    rows: BTreeMap<usize, HashSet<usize>>,
-    cols: BTreeMap<usize, HashSet<usize>>,
-    folding_queue: VecDeque<OrigamiFold>,
+// Where a dot ends up when unfolding the sheet: kept in place, mirrored over the fold line or both
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnfoldSide {
+    Near,
+    Far,
+    Both,
 }
 
-impl ActivationData {
-    pub fn new(input: &str) -> ActivationData {
-        let mut rows_init: BTreeMap<usize, HashSet<usize>> = BTreeMap::new();
-        let mut cols_init: BTreeMap<usize, HashSet<usize>> = BTreeMap::new();
-        let mut fold_q: VecDeque<OrigamiFold> = VecDeque::new();
-        for line in input.lines().filter(|l| !l.is_empty()) {
-            let (_, parsed_line) = activation_instruction(line).unwrap();
-            match parsed_line {
-                ActivationInstruction::Point((x, y)) => {
-                    if let Some(row_entry) = rows_init.get_mut(&y) {
-                        row_entry.insert(x);
-                    } else {
-                        rows_init.insert(y, HashSet::from([x]));
-                    }
-
-                    if let Some(col_entry) = cols_init.get_mut(&x) {
-                        col_entry.insert(y);
-                    } else {
-                        cols_init.insert(x, HashSet::from([y]));
-                    }
-                }
-                ActivationInstruction::Fold(f) => {
-                    fold_q.push_back(f);
-                }
-            }
-        }
+// Folding `coord` over `line` on an axis of `size` elements. The folded axis is as long as the
+// longest of both halves, so the shorter half gets shifted when the fold is not at the midpoint
+fn fold_coordinate(coord: usize, line: usize, size: usize) -> Option<usize> {
+    let folded_size = line.max(size.saturating_sub(line + 1));
+    let shift = folded_size - line;
 
-        ActivationData {
-            rows: rows_init,
-            cols: cols_init,
-            folding_queue: fold_q,
-        }
+    match coord.cmp(&line) {
+        std::cmp::Ordering::Less => Some(coord + shift),
+        std::cmp::Ordering::Equal => None,
+        std::cmp::Ordering::Greater => Some(line + folded_size - coord),
     }
+}
+
+// Inverse of `fold_coordinate` for a fold whose far half covers the whole folded axis. Returns the
+// near position, if there's one, and the far one
+fn unfold_coordinate(coord: usize, line: usize, size: usize) -> (Option<usize>, usize) {
+    (coord.checked_sub(size - line), line + size - coord)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct OrigamiSheet {
+    dots: BTreeSet<(usize, usize)>,
+    width: usize,
+    height: usize,
+}
+
+impl OrigamiSheet {
+    pub fn new(dots: BTreeSet<(usize, usize)>) -> OrigamiSheet {
+        let width = dots.iter().map(|(x, _)| x + 1).max().unwrap_or_default();
+        let height = dots.iter().map(|(_, y)| y + 1).max().unwrap_or_default();
 
-    pub fn fold_once(&mut self) {
-        match self.folding_queue.pop_front().unwrap() {
-            OrigamiFold::Horizontal(row) => self.horizontal_fold(row),
-            OrigamiFold::Vertical(col) => self.vertical_fold(col),
+        OrigamiSheet {
+            dots,
+            width,
+            height,
         }
     }
 
-    pub fn fold_all(&mut self) {
-        while !self.folding_queue.is_empty() {
-            self.fold_once();
+    // Reads a sheet drawn with '#' for dots and any other character for empty spots
+    #[cfg(test)]
+    pub fn from_image(image: &str) -> OrigamiSheet {
+        let rows: Vec<&str> = image.lines().map(|l| l.trim()).collect();
+        let dots = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect();
+
+        OrigamiSheet {
+            dots,
+            width: rows
+                .iter()
+                .map(|r| r.chars().count())
+                .max()
+                .unwrap_or_default(),
+            height: rows.len(),
         }
     }
 
-    fn vertical_fold(&mut self, col: usize) {
-        let mut removed_cols: Vec<usize> = Vec::new();
-        let mut moved_data_cols: HashMap<usize, Vec<usize>> = HashMap::new();
-        let mut moved_data_rows: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
-        let (&max_col, _) = self.cols.iter().next_back().unwrap();
-
-        for (col_idx, points) in self.cols.range_mut(col..=max_col) {
-            let target_col_idx = col * 2 - col_idx;
-            moved_data_cols.insert(target_col_idx, points.iter().cloned().collect());
-            removed_cols.push(*col_idx);
-
-            for p in points.iter() {
-                let target_row = moved_data_rows.entry(*p).or_insert_with(Vec::new);
-                target_row.push((*col_idx, target_col_idx));
+    pub fn fold(&mut self, fold: &OrigamiFold) -> Result<(), &'static str> {
+        let folded: Option<BTreeSet<(usize, usize)>> = match *fold {
+            OrigamiFold::Vertical(col) => self
+                .dots
+                .iter()
+                .map(|&(x, y)| fold_coordinate(x, col, self.width).map(|new_x| (new_x, y)))
+                .collect(),
+            OrigamiFold::Horizontal(row) => self
+                .dots
+                .iter()
+                .map(|&(x, y)| fold_coordinate(y, row, self.height).map(|new_y| (x, new_y)))
+                .collect(),
+        };
+
+        self.dots = folded.ok_or("There's a dot on the fold line")?;
+        match *fold {
+            OrigamiFold::Vertical(col) => {
+                self.width = col.max(self.width.saturating_sub(col + 1));
+            }
+            OrigamiFold::Horizontal(row) => {
+                self.height = row.max(self.height.saturating_sub(row + 1));
             }
         }
 
-        for col_idx in removed_cols {
-            self.cols.remove(&col_idx);
-        }
+        Ok(())
+    }
 
-        for (new_col_idx, points) in moved_data_cols {
-            let target_col = self.cols.entry(new_col_idx).or_insert_with(HashSet::new);
-            target_col.extend(points.iter());
+    // Builds a sheet that gives back this one once folded. The fold line can't be further than
+    // the sheet size along the folded axis, as the far half has to cover the whole sheet
+    pub fn unfold<F>(&self, fold: &OrigamiFold, mut choose: F) -> Result<OrigamiSheet, &'static str>
+    where
+        F: FnMut((usize, usize)) -> UnfoldSide,
+    {
+        let (line, size) = match *fold {
+            OrigamiFold::Vertical(col) => (col, self.width),
+            OrigamiFold::Horizontal(row) => (row, self.height),
+        };
+        if line > size {
+            return Err("The fold line is out of the folded sheet");
         }
 
-        for (row_idx, moved_points) in moved_data_rows {
-            let row = self.rows.get_mut(&row_idx).unwrap();
-            for (src, dst) in moved_points {
-                row.remove(&src);
-                row.insert(dst);
-            }
+        let mut dots: BTreeSet<(usize, usize)> = BTreeSet::new();
+        for &(x, y) in self.dots.iter() {
+            let coord = match *fold {
+                OrigamiFold::Vertical(_) => x,
+                OrigamiFold::Horizontal(_) => y,
+            };
+            let (near, far) = unfold_coordinate(coord, line, size);
+            let targets = match (near, choose((x, y))) {
+                (Some(near), UnfoldSide::Near) => vec![near],
+                (Some(near), UnfoldSide::Both) => vec![near, far],
+                _ => vec![far],
+            };
+
+            dots.extend(targets.into_iter().map(|target| match *fold {
+                OrigamiFold::Vertical(_) => (target, y),
+                OrigamiFold::Horizontal(_) => (x, target),
+            }));
         }
+
+        Ok(match *fold {
+            OrigamiFold::Vertical(_) => OrigamiSheet {
+                dots,
+                width: line + 1 + size,
+                height: self.height,
+            },
+            OrigamiFold::Horizontal(_) => OrigamiSheet {
+                dots,
+                width: self.width,
+                height: line + 1 + size,
+            },
+        })
     }
 
-    fn horizontal_fold(&mut self, row: usize) {
-        let mut removed_rows: Vec<usize> = Vec::new();
-        let mut moved_data_rows: HashMap<usize, Vec<usize>> = HashMap::new();
-        let mut moved_data_cols: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
-        let (&max_row, _) = self.rows.iter().next_back().unwrap();
+    pub fn get_unique_points(&self) -> u64 {
+        u64::try_from(self.dots.len()).unwrap()
+    }
 
-        for (row_idx, points) in self.rows.range_mut(row..=max_row) {
-            let target_row_idx = row * 2 - row_idx;
-            moved_data_rows.insert(target_row_idx, points.iter().cloned().collect());
-            removed_rows.push(*row_idx);
+    pub fn activation_grid(&self) -> Vec<Vec<bool>> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| self.dots.contains(&(x, y)))
+                    .collect()
+            })
+            .collect()
+    }
 
-            for p in points.iter() {
-                let target_col = moved_data_cols.entry(*p).or_insert_with(Vec::new);
-                target_col.push((*row_idx, target_row_idx));
-            }
-        }
+    pub fn render(&self) -> String {
+        self.activation_grid()
+            .iter()
+            .map(|row| row.iter().map(|&dot| if dot { '#' } else { '.' }).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
 
-        for row_idx in removed_rows {
-            self.rows.remove(&row_idx);
-        }
+    // Writes the sheet as puzzle input, with the folds that should be applied to it
+    pub fn to_puzzle(&self, folds: &[OrigamiFold]) -> String {
+        let dots = self.dots.iter().map(|(x, y)| format!("{},{}", x, y));
+        let instructions = folds.iter().map(|f| f.to_instruction());
+
+        let mut puzzle: Vec<String> = dots.collect();
+        puzzle.push(String::new());
+        puzzle.extend(instructions);
+        puzzle.join("\n")
+    }
+}
 
-        for (new_row_idx, points) in moved_data_rows {
-            let target_row = self.rows.entry(new_row_idx).or_insert_with(HashSet::new);
-            target_row.extend(points.iter());
+// Creates a new puzzle input that folds into `target`, undoing `folds` in reverse. Dots are spread
+// over both halves of each fold with a xorshift generator, so every seed gives a different input
+fn generate_puzzle(
+    target: &OrigamiSheet,
+    folds: &[OrigamiFold],
+    seed: u64,
+) -> Result<String, &'static str> {
+    let mut state = seed.max(1);
+    let mut choose = |_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        match state % 3 {
+            0 => UnfoldSide::Near,
+            1 => UnfoldSide::Far,
+            _ => UnfoldSide::Both,
         }
+    };
+
+    let mut sheet = target.clone();
+    for fold in folds.iter().rev() {
+        sheet = sheet.unfold(fold, &mut choose)?;
+    }
+
+    Ok(sheet.to_puzzle(folds))
+}
 
-        for (col_idx, moved_points) in moved_data_cols {
-            let col = self.cols.get_mut(&col_idx).unwrap();
-            for (src, dst) in moved_points {
-                col.remove(&src);
-                col.insert(dst);
+// Iterator over the sheet after each fold. It stops after the first fold that fails
+struct FoldSteps<'a> {
+    sheet: OrigamiSheet,
+    folds: std::slice::Iter<'a, OrigamiFold>,
+}
+
+impl Iterator for FoldSteps<'_> {
+    type Item = Result<OrigamiSheet, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let fold = self.folds.next()?;
+
+        match self.sheet.fold(fold) {
+            Ok(()) => Some(Ok(self.sheet.clone())),
+            Err(e) => {
+                self.folds = [].iter();
+                Some(Err(e))
             }
         }
     }
+}
 
-    pub fn get_unique_points(self) -> u64 {
-        self.rows.iter().fold(0u64, |sum, (_, points)| {
-            sum + u64::try_from(points.len()).unwrap()
-        })
-    }
+#[derive(Debug)]
+struct ActivationData {
+    sheet: OrigamiSheet,
+    folds: Vec<OrigamiFold>,
+}
 
-    pub fn print_activation(self) {
-        let (&max_row, _) = self.rows.iter().next_back().unwrap();
-        let (&max_col, _) = self.cols.iter().next_back().unwrap();
-
-        for row_idx in 0..=max_row {
-            if let Some(row) = self.rows.get(&row_idx) {
-                for col in 0..=max_col {
-                    if row.get(&col).is_some() {
-                        print!("#");
-                    } else {
-                        print!(" ");
-                    }
+impl ActivationData {
+    pub fn new(input: &str) -> ActivationData {
+        let mut dots: BTreeSet<(usize, usize)> = BTreeSet::new();
+        let mut folds: Vec<OrigamiFold> = Vec::new();
+        for line in input.lines().filter(|l| !l.trim().is_empty()) {
+            let (_, parsed_line) = activation_instruction(line).unwrap();
+            match parsed_line {
+                ActivationInstruction::Point(p) => {
+                    dots.insert(p);
                 }
-            } else {
-                for _ in 0..max_col {
-                    print!(" ");
+                ActivationInstruction::Fold(f) => {
+                    folds.push(f);
                 }
             }
-            println!();
+        }
+
+        ActivationData {
+            sheet: OrigamiSheet::new(dots),
+            folds,
         }
     }
+
+    pub fn fold_steps(&self) -> FoldSteps<'_> {
+        FoldSteps {
+            sheet: self.sheet.clone(),
+            folds: self.folds.iter(),
+        }
+    }
+
+    pub fn fold_all(&self) -> Result<OrigamiSheet, &'static str> {
+        self.fold_steps()
+            .last()
+            .unwrap_or_else(|| Ok(self.sheet.clone()))
+    }
 }
 
 pub fn part1(input: &str) {
-    let mut data = ActivationData::new(input);
-    data.fold_once();
-    let point_count = data.get_unique_points();
-    println!("Amount of unique points after one fold: {}", point_count);
+    let data = ActivationData::new(input);
+
+    match data.fold_steps().next() {
+        Some(Ok(sheet)) => println!(
+            "Amount of unique points after one fold: {}",
+            sheet.get_unique_points()
+        ),
+        Some(Err(e)) => println!("Couldn't fold the paper: {}", e),
+        None => println!("There are no folding instructions"),
+    }
 }
 
 pub fn part2(input: &str) {
-    let mut data = ActivationData::new(input);
-    data.fold_all();
-    println!("Activation paper after folding:");
-    println!();
-    data.print_activation();
-    println!();
+    let data = ActivationData::new(input);
+    let sheet = match data.fold_all() {
+        Ok(sheet) => sheet,
+        Err(e) => {
+            println!("Couldn't fold the paper: {}", e);
+            return;
+        }
+    };
+
+    match ocr::decode(&sheet.activation_grid()) {
+        Ok(code) => println!("Activation code: {}", code),
+        Err(e) => {
+            println!(
+                "Couldn't read the activation code ({}), here's the paper:",
+                e
+            );
+            println!();
+            println!("{}", sheet.render());
+            println!();
+        }
+    }
+
+    if !EXPORT_PUZZLE.get() {
+        return;
+    }
+
+    let puzzle_path = temp_dir().join("day13_puzzle.txt");
+    let puzzle = generate_puzzle(&sheet, &data.folds, input.len() as u64)
+        .map_err(|e| e.to_string())
+        .and_then(|puzzle| fs::write(&puzzle_path, puzzle).map_err(|e| e.to_string()));
+    match puzzle {
+        Ok(()) => println!("New puzzle with the same code at {}", puzzle_path.display()),
+        Err(e) => println!("Failed to generate a new puzzle: {}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn data_parsing() {
-        let input_string = "6,10
+    const EXAMPLE_INSTRUCTIONS: &str = "6,10
         0,14
         9,10
         0,3
@@ -247,68 +414,180 @@ mod tests {
         fold along y=7
         fold along x=5";
 
-        let data = ActivationData::new(input_string);
-
-        assert_eq!(data.get_unique_points(), 18u64);
+    #[test]
+    fn data_parsing() {
+        let data = ActivationData::new(EXAMPLE_INSTRUCTIONS);
+
+        assert_eq!(data.sheet.get_unique_points(), 18u64);
+        assert_eq!((data.sheet.width, data.sheet.height), (11, 15));
+        assert_eq!(
+            data.folds,
+            vec![OrigamiFold::Horizontal(7), OrigamiFold::Vertical(5)]
+        );
     }
 
     #[test]
     fn one_fold() {
-        let input_string = "6,10
-        0,14
-        9,10
-        0,3
-        10,4
-        4,11
-        6,0
-        6,12
-        4,1
-        0,13
-        10,12
-        3,4
-        3,0
-        8,4
-        1,10
-        2,14
-        8,10
-        9,0
+        let data = ActivationData::new(EXAMPLE_INSTRUCTIONS);
+        let sheet = data.fold_steps().next().unwrap().unwrap();
 
-        fold along y=7
-        fold along x=5";
+        assert_eq!(sheet.get_unique_points(), 17u64);
+    }
 
-        let mut data = ActivationData::new(input_string);
-        data.fold_once();
+    #[test]
+    fn fold_all() {
+        let data = ActivationData::new(EXAMPLE_INSTRUCTIONS);
+        let sheet = data.fold_all().unwrap();
 
-        assert_eq!(data.get_unique_points(), 17u64);
+        assert_eq!(sheet.get_unique_points(), 16u64);
     }
 
     #[test]
-    fn fold_all() {
-        let input_string = "6,10
-        0,14
-        9,10
-        0,3
-        10,4
-        4,11
-        6,0
-        6,12
-        4,1
-        0,13
-        10,12
-        3,4
+    fn fold_animation() {
+        let data = ActivationData::new(EXAMPLE_INSTRUCTIONS);
+        let frames: Vec<String> = data
+            .fold_steps()
+            .map(|step| step.unwrap().render())
+            .collect();
+
+        assert_eq!(
+            frames,
+            vec![
+                "#.##..#..#.\n\
+                 #...#......\n\
+                 ......#...#\n\
+                 #...#......\n\
+                 .#.#..#.###\n\
+                 ...........\n\
+                 ...........",
+                "#####\n\
+                 #...#\n\
+                 #...#\n\
+                 #...#\n\
+                 #####\n\
+                 .....\n\
+                 .....",
+            ]
+        );
+    }
+
+    #[test]
+    fn folded_grid() {
+        let data = ActivationData::new(EXAMPLE_INSTRUCTIONS);
+        let sheet = data.fold_all().unwrap();
+
+        assert_eq!(
+            sheet.activation_grid(),
+            ocr::grid_from_str(
+                "#####
+                #...#
+                #...#
+                #...#
+                #####
+                .....
+                ....."
+            )
+        );
+        assert!(ocr::decode(&sheet.activation_grid()).is_err());
+    }
+
+    #[test]
+    fn activation_code() {
+        let input_string = "0,0
+        1,0
+        2,0
         3,0
-        8,4
-        1,10
-        2,14
-        8,10
-        9,0
+        0,1
+        0,2
+        1,2
+        2,2
+        0,3
+        0,4
+        0,5
+        0,7
+        0,8
+        0,9
+        0,10
 
-        fold along y=7
-        fold along x=5";
+        fold along y=6";
+
+        let data = ActivationData::new(input_string);
+        let sheet = data.fold_all().unwrap();
+
+        assert_eq!(ocr::decode(&sheet.activation_grid()), Ok(String::from("F")));
+    }
 
-        let mut data = ActivationData::new(input_string);
-        data.fold_all();
+    #[test]
+    fn off_midpoint_folds() {
+        let mut sheet = OrigamiSheet::from_image(
+            "#.
+            ..
+            .#
+            ..
+            #.",
+        );
+
+        sheet.fold(&OrigamiFold::Horizontal(1)).unwrap();
+        assert_eq!(sheet.render(), "#.\n..\n##");
+
+        // Folding past the end of the sheet only makes it larger
+        sheet.fold(&OrigamiFold::Vertical(3)).unwrap();
+        assert_eq!(sheet.render(), "#..\n...\n##.");
+
+        assert_eq!(
+            sheet.fold(&OrigamiFold::Horizontal(2)),
+            Err("There's a dot on the fold line")
+        );
+    }
 
-        assert_eq!(data.get_unique_points(), 16u64);
+    #[test]
+    fn unfold_sheet() {
+        let target = OrigamiSheet::from_image(
+            "#..#
+            .##.
+            #...",
+        );
+
+        for fold in [
+            OrigamiFold::Horizontal(3),
+            OrigamiFold::Horizontal(1),
+            OrigamiFold::Vertical(4),
+            OrigamiFold::Vertical(0),
+        ] {
+            for side in [UnfoldSide::Near, UnfoldSide::Far, UnfoldSide::Both] {
+                let mut unfolded = target.unfold(&fold, |_| side).unwrap();
+                unfolded.fold(&fold).unwrap();
+                assert_eq!(unfolded, target);
+            }
+        }
+
+        let mirrored = target
+            .unfold(&OrigamiFold::Vertical(4), |_| UnfoldSide::Far)
+            .unwrap();
+        assert_eq!(mirrored.render(), ".....#..#\n......##.\n........#");
+
+        assert!(target
+            .unfold(&OrigamiFold::Horizontal(4), |_| UnfoldSide::Near)
+            .is_err());
+    }
+
+    #[test]
+    fn puzzle_generation() {
+        let target = ActivationData::new(EXAMPLE_INSTRUCTIONS)
+            .fold_all()
+            .unwrap();
+        let folds = vec![
+            OrigamiFold::Horizontal(7),
+            OrigamiFold::Vertical(5),
+            OrigamiFold::Horizontal(5),
+        ];
+
+        for seed in 0..5 {
+            let puzzle = generate_puzzle(&target, &folds, seed).unwrap();
+            let data = ActivationData::new(&puzzle);
+
+            assert_eq!(data.folds, folds);
+            assert_eq!(data.fold_all().unwrap().render(), target.render());
+        }
     }
 }
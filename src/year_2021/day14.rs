@@ -1,4 +1,6 @@
-use hashbrown::HashMap;
+use std::collections::VecDeque;
+
+use hashbrown::{HashMap, HashSet};
 
 use itertools::{Itertools, MinMaxResult};
 
@@ -7,6 +9,17 @@ use nom::character::complete::{alpha1, space0};
 use nom::sequence::{preceded, separated_pair};
 use nom::IResult;
 
+use num::{BigUint, Zero};
+
+use crate::aoc_lib::big_matrix::{self, BigMatrix};
+use crate::aoc_lib::params::{DayParams, Param};
+
+const STEPS: Param<u64> = Param::new("steps", 10);
+const MANY_STEPS: Param<u64> = Param::new("steps2", 40);
+// When set, element counts are reported modulo this value, which works for any step count
+const MODULUS: Param<u64> = Param::new("modulus", 0);
+pub const PARAMS: DayParams = &[&STEPS, &MANY_STEPS, &MODULUS];
+
 // Polymer evolution parser
 fn insertion_rule(input: &str) -> IResult<&str, (&str, &str)> {
     preceded(space0, separated_pair(alpha1, tag(" -> "), alpha1))(input)
@@ -15,125 +28,222 @@ fn insertion_rule(input: &str) -> IResult<&str, (&str, &str)> {
 #[derive(Debug)]
 struct PolymerData {
     template_polymer: String,
-    insertion_rules: HashMap<String, char>,
-    pair_frequencies: HashMap<String, u64>,
+    pairs: Vec<(char, char)>,
+    pair_indices: HashMap<(char, char), usize>,
+    initial_counts: Vec<BigUint>,
+    // The element at (row, col) is how many `row` pairs a `col` pair becomes. Keep in mind counts
+    // roughly double every step, so they grow by about one bit per step
+    transition: BigMatrix,
 }
 
 impl PolymerData {
     pub fn new(input: &str) -> PolymerData {
         let mut polymer_lines = input.lines();
-        let polymer_template = polymer_lines.next().unwrap();
+        let polymer_template = polymer_lines.next().unwrap().trim();
 
-        let mut initial_frequencies: HashMap<String, u64> = HashMap::new();
-        for (first, second) in polymer_template.chars().tuple_windows() {
-            let pair: String = vec![first, second].iter().collect();
-            if let Some(freq) = initial_frequencies.get_mut(&pair) {
-                *freq += 1;
-            } else {
-                initial_frequencies.insert(pair, 1u64);
+        let mut rules: HashMap<(char, char), char> = HashMap::new();
+        for line in polymer_lines.filter(|l| !l.trim().is_empty()) {
+            let (_, (pair, result)) = insertion_rule(line).unwrap();
+            let (first, second) = pair.chars().next_tuple().unwrap();
+            rules.insert((first, second), result.chars().next().unwrap());
+        }
+
+        // Only pairs reachable from the template take part in the transition matrix
+        let template_pairs: Vec<(char, char)> = polymer_template.chars().tuple_windows().collect();
+        let mut pairs: Vec<(char, char)> = Vec::new();
+        let mut visited: HashSet<(char, char)> = HashSet::new();
+        let mut pending: VecDeque<(char, char)> = template_pairs.iter().cloned().collect();
+        while let Some(pair) = pending.pop_front() {
+            if !visited.insert(pair) {
+                continue;
+            }
+            pairs.push(pair);
+            if let Some(&evo) = rules.get(&pair) {
+                pending.push_back((pair.0, evo));
+                pending.push_back((evo, pair.1));
             }
         }
 
-        let mut rules: HashMap<String, char> = HashMap::new();
-        for line in polymer_lines.skip(1) {
-            let (_, (pair, result)) = insertion_rule(line).unwrap();
-            rules.insert(pair.to_string(), result.chars().next().unwrap());
+        let pair_indices: HashMap<(char, char), usize> =
+            pairs.iter().enumerate().map(|(idx, p)| (*p, idx)).collect();
+
+        let mut initial_counts = vec![BigUint::zero(); pairs.len()];
+        for pair in template_pairs.iter() {
+            initial_counts[pair_indices[pair]] += 1u32;
+        }
+
+        // Pairs without an insertion rule stay the same
+        let mut transition = vec![vec![BigUint::zero(); pairs.len()]; pairs.len()];
+        for (col, pair) in pairs.iter().enumerate() {
+            if let Some(&evo) = rules.get(pair) {
+                transition[pair_indices[&(pair.0, evo)]][col] += 1u32;
+                transition[pair_indices[&(evo, pair.1)]][col] += 1u32;
+            } else {
+                transition[col][col] += 1u32;
+            }
         }
 
         PolymerData {
             template_polymer: polymer_template.to_string(),
-            insertion_rules: rules,
-            pair_frequencies: initial_frequencies,
+            pairs,
+            pair_indices,
+            initial_counts,
+            transition,
         }
     }
 
-    fn step(&mut self) {
-        let mut step_result: HashMap<String, u64> = HashMap::new();
-
-        for (pair, freq) in self.pair_frequencies.iter() {
-            if let Some(evo) = self.insertion_rules.get(pair) {
-                let (first_char, second_char) = pair.chars().next_tuple().unwrap();
-                let first_pair: String = vec![first_char, *evo].iter().collect();
-                let second_pair: String = vec![*evo, second_char].iter().collect();
-                if let Some(first_freq) = step_result.get_mut(&first_pair) {
-                    *first_freq += freq;
-                } else {
-                    step_result.insert(first_pair, *freq);
-                }
-                if let Some(second_freq) = step_result.get_mut(&second_pair) {
-                    *second_freq += freq;
-                } else {
-                    step_result.insert(second_pair, *freq);
-                }
-            }
+    // Exact counts, so the cost grows with the amount of steps: use `count_mod` for billions
+    pub fn evolve(&self, steps: u64) -> PolymerStep<'_> {
+        let pair_counts = big_matrix::apply(
+            &big_matrix::power(&self.transition, steps),
+            &self.initial_counts,
+        );
+
+        PolymerStep {
+            data: self,
+            pair_counts,
         }
-        self.pair_frequencies = step_result;
     }
 
-    pub fn evolve_polymer(&mut self, steps: u64) {
-        for _ in 0..steps {
-            self.step()
+    // Element counts after some steps modulo `modulus`, they stay small whatever the step count
+    pub fn count_mod(&self, steps: u64, modulus: u64) -> HashMap<char, u64> {
+        let transition = big_matrix::power_mod(&self.transition, steps, modulus);
+        let pair_counts: Vec<BigUint> = transition
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(self.initial_counts.iter())
+                    .map(|(&factor, count)| factor * count)
+                    .sum::<BigUint>()
+                    % modulus
+            })
+            .collect();
+
+        PolymerStep {
+            data: self,
+            pair_counts,
         }
+        .element_counts()
+        .into_iter()
+        .map(|(element, count)| (element, (count % modulus).try_into().unwrap()))
+        .collect()
     }
+}
 
-    pub fn compute_elements_delta(self) -> (u64, HashMap<char, u64>) {
-        let mut frequencies: HashMap<char, u64> = HashMap::new();
+// Pair counts of the polymer after some steps
+#[derive(Debug)]
+struct PolymerStep<'a> {
+    data: &'a PolymerData,
+    pair_counts: Vec<BigUint>,
+}
 
-        for (pair, freq) in self.pair_frequencies.iter() {
-            let (element, _) = pair.chars().next_tuple().unwrap();
-            if let Some(elem_freq) = frequencies.get_mut(&element) {
-                *elem_freq += *freq;
-            } else {
-                frequencies.insert(element, *freq);
+impl PolymerStep<'_> {
+    pub fn pair_count(&self, pair: &str) -> BigUint {
+        pair.chars()
+            .next_tuple()
+            .and_then(|p: (char, char)| self.data.pair_indices.get(&p))
+            .map_or_else(BigUint::zero, |&idx| self.pair_counts[idx].clone())
+    }
+
+    pub fn pair_counts(&self) -> HashMap<String, BigUint> {
+        self.data
+            .pairs
+            .iter()
+            .zip(self.pair_counts.iter())
+            .filter(|(_, count)| !count.is_zero())
+            .map(|((first, second), count)| (format!("{}{}", first, second), count.clone()))
+            .collect()
+    }
+
+    // Every element is the first one of a pair, except for the last one, which never changes
+    pub fn element_counts(&self) -> HashMap<char, BigUint> {
+        let mut frequencies: HashMap<char, BigUint> = HashMap::new();
+
+        for ((element, _), count) in self.data.pairs.iter().zip(self.pair_counts.iter()) {
+            if !count.is_zero() {
+                *frequencies.entry(*element).or_default() += count;
             }
         }
 
-        let last_element = self.template_polymer.chars().last().unwrap();
-        if let Some(last_freq) = frequencies.get_mut(&last_element) {
-            *last_freq += 1u64;
-        } else {
-            frequencies.insert(last_element, 1u64);
+        if let Some(last_element) = self.data.template_polymer.chars().last() {
+            *frequencies.entry(last_element).or_default() += 1u32;
         }
 
-        let delta: u64 = if let MinMaxResult::MinMax(min, max) = frequencies.values().minmax() {
-            max - min
-        } else {
-            panic!("For some reason we didn't find a min and max in the values!");
-        };
+        frequencies
+    }
 
-        (delta, frequencies)
+    pub fn element_count(&self, element: char) -> BigUint {
+        self.element_counts()
+            .remove(&element)
+            .unwrap_or_else(BigUint::zero)
+    }
+
+    pub fn elements_delta(&self) -> BigUint {
+        match self.element_counts().values().minmax() {
+            MinMaxResult::MinMax(min, max) => max - min,
+            _ => BigUint::zero(),
+        }
     }
 }
 
 pub fn part1(input: &str) {
-    let mut data = PolymerData::new(input);
-    data.evolve_polymer(10);
-    let (elements_delta, _) = data.compute_elements_delta();
+    let steps = STEPS.get();
+    let data = PolymerData::new(input);
     println!(
-        "Delta between highest and lowest frequency elements after 10 steps: {}",
-        elements_delta
+        "Delta between highest and lowest frequency elements after {} steps: {}",
+        steps,
+        data.evolve(steps).elements_delta()
     );
 }
 
 pub fn part2(input: &str) {
-    let mut data = PolymerData::new(input);
-    data.evolve_polymer(40);
-    let (elements_delta, _) = data.compute_elements_delta();
+    let steps = MANY_STEPS.get();
+    let data = PolymerData::new(input);
+    let modulus = MODULUS.get();
+    if modulus != 0 {
+        // Exact counts would take forever with a huge amount of steps
+        let counts = data.count_mod(steps, modulus);
+        println!(
+            "Element counts modulo {}: {}",
+            modulus,
+            counts
+                .iter()
+                .sorted()
+                .map(|(element, count)| format!("{}: {}", element, count))
+                .join(", ")
+        );
+        return;
+    }
+
+    let step = data.evolve(steps);
     println!(
-        "Delta between highest and lowest frequency elements after 40 steps: {}",
-        elements_delta
+        "Delta between highest and lowest frequency elements after {} steps: {}",
+        steps,
+        step.elements_delta()
     );
+
+    if let Some((pair, count)) = step.pair_counts().into_iter().max_by(|a, b| a.1.cmp(&b.1)) {
+        println!("Most common pair: {} ({} times)", pair, count);
+    }
+    if let Some(first_pair) = data.template_polymer.get(..2) {
+        let first_element = first_pair.chars().next().unwrap();
+        println!(
+            "The template starts with {} ({} times) and {} ({} times)",
+            first_pair,
+            step.pair_count(first_pair),
+            first_element,
+            step.element_count(first_element)
+        );
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::iter::FromIterator;
+    use num::One;
 
     use super::*;
 
-    #[test]
-    fn one_polymer_step() {
-        let input_string = "NNCB
+    const EXAMPLE_POLYMER: &str = "NNCB
 
         CH -> B
         HH -> N
@@ -152,8 +262,17 @@ mod tests {
         CC -> N
         CN -> C";
 
-        let mut data = PolymerData::new(input_string);
-        data.evolve_polymer(1);
+    fn counts<K: std::hash::Hash + Eq>(values: Vec<(K, u64)>) -> HashMap<K, BigUint> {
+        values
+            .into_iter()
+            .map(|(key, count)| (key, BigUint::from(count)))
+            .collect()
+    }
+
+    #[test]
+    fn one_polymer_step() {
+        let data = PolymerData::new(EXAMPLE_POLYMER);
+        let step = data.evolve(1);
 
         // Resulting polymer: NCNBCHB
         // Frequencies:
@@ -161,36 +280,28 @@ mod tests {
         //   - C: 2
         //   - B: 2
         //   - H: 1
-        let (delta, freqs) = data.compute_elements_delta();
-        let validation_freqs: HashMap<char, u64> =
-            HashMap::from_iter(vec![('N', 2), ('C', 2), ('B', 2), ('H', 1)]);
-        assert_eq!(freqs, validation_freqs);
-        assert_eq!(delta, 1u64);
+        assert_eq!(
+            step.element_counts(),
+            counts(vec![('N', 2), ('C', 2), ('B', 2), ('H', 1)])
+        );
+        assert_eq!(step.elements_delta(), BigUint::from(1u32));
+        assert_eq!(
+            step.pair_counts(),
+            counts(vec![
+                (String::from("NC"), 1),
+                (String::from("CN"), 1),
+                (String::from("NB"), 1),
+                (String::from("BC"), 1),
+                (String::from("CH"), 1),
+                (String::from("HB"), 1),
+            ])
+        );
     }
 
     #[test]
     fn four_polymer_steps() {
-        let input_string = "NNCB
-
-        CH -> B
-        HH -> N
-        CB -> H
-        NH -> C
-        HB -> C
-        HC -> B
-        HN -> C
-        NN -> C
-        BH -> H
-        NC -> B
-        NB -> B
-        BN -> B
-        BB -> N
-        BC -> B
-        CC -> N
-        CN -> C";
-
-        let mut data = PolymerData::new(input_string);
-        data.evolve_polymer(4);
+        let data = PolymerData::new(EXAMPLE_POLYMER);
+        let step = data.evolve(4);
 
         // Resulting polymer: NBBNBNBBCCNBCNCCNBBNBBNBBBNBBNBBCBHCBHHNHCBBCBHCB
         // Frequencies:
@@ -198,36 +309,20 @@ mod tests {
         //   - N: 11
         //   - C: 10
         //   - H: 5
-        let (delta, freqs) = data.compute_elements_delta();
-        let validation_freqs: HashMap<char, u64> =
-            HashMap::from_iter(vec![('B', 23), ('N', 11), ('C', 10), ('H', 5)]);
-        assert_eq!(freqs, validation_freqs);
-        assert_eq!(delta, 18u64);
+        assert_eq!(
+            step.element_counts(),
+            counts(vec![('B', 23), ('N', 11), ('C', 10), ('H', 5)])
+        );
+        assert_eq!(step.elements_delta(), BigUint::from(18u32));
+        assert_eq!(step.pair_count("BB"), BigUint::from(9u32));
+        assert_eq!(step.pair_count("HH"), BigUint::from(1u32));
+        assert_eq!(step.pair_count("XY"), BigUint::zero());
     }
 
     #[test]
     fn ten_polymer_steps_delta() {
-        let input_string = "NNCB
-
-        CH -> B
-        HH -> N
-        CB -> H
-        NH -> C
-        HB -> C
-        HC -> B
-        HN -> C
-        NN -> C
-        BH -> H
-        NC -> B
-        NB -> B
-        BN -> B
-        BB -> N
-        BC -> B
-        CC -> N
-        CN -> C";
-
-        let mut data = PolymerData::new(input_string);
-        data.evolve_polymer(10);
+        let data = PolymerData::new(EXAMPLE_POLYMER);
+        let step = data.evolve(10);
 
         // Resulting polymer: ??? (Too long)
         // Frequencies: B occurs 1749 times, C occurs 298 times, H occurs 161 times, and N occurs 865 times
@@ -235,10 +330,69 @@ mod tests {
         //   - N: 865
         //   - C: 298
         //   - H: 161
-        let (delta, freqs) = data.compute_elements_delta();
-        let validation_freqs: HashMap<char, u64> =
-            HashMap::from_iter(vec![('B', 1749), ('N', 865), ('C', 298), ('H', 161)]);
-        assert_eq!(freqs, validation_freqs);
-        assert_eq!(delta, 1588u64);
+        assert_eq!(
+            step.element_counts(),
+            counts(vec![('B', 1749), ('N', 865), ('C', 298), ('H', 161)])
+        );
+        assert_eq!(step.elements_delta(), BigUint::from(1588u32));
+    }
+
+    #[test]
+    fn long_evolutions() {
+        let data = PolymerData::new(EXAMPLE_POLYMER);
+
+        let step = data.evolve(40);
+        assert_eq!(step.element_count('B'), BigUint::from(2192039569602u64));
+        assert_eq!(step.element_count('H'), BigUint::from(3849876073u64));
+        assert_eq!(step.elements_delta(), BigUint::from(2188189693529u64));
+
+        // The polymer length doubles minus one every step
+        let steps = 5000u64;
+        let total: BigUint = data.evolve(steps).element_counts().values().sum();
+        assert_eq!(
+            total,
+            BigUint::from(3u32) * (BigUint::one() << steps) + 1u32
+        );
+
+        assert_eq!(data.evolve(0).element_count('N'), BigUint::from(2u32));
+    }
+
+    #[test]
+    fn pairs_without_rules() {
+        let data = PolymerData::new(
+            "ABA
+
+            AB -> A",
+        );
+
+        // ABA -> AABA -> AAABA
+        let step = data.evolve(2);
+        assert_eq!(step.pair_count("AA"), BigUint::from(2u32));
+        assert_eq!(step.pair_count("BA"), BigUint::from(1u32));
+        assert_eq!(step.element_count('A'), BigUint::from(4u32));
+    }
+
+    #[test]
+    fn modular_counts() {
+        let data = PolymerData::new(EXAMPLE_POLYMER);
+        let modulus = 1_000_000_007u64;
+
+        let exact = data.evolve(40).element_counts();
+        let reduced = data.count_mod(40, modulus);
+        for (element, count) in exact {
+            assert_eq!(BigUint::from(reduced[&element]), count % modulus);
+        }
+
+        // The polymer length is 3 * 2^steps + 1, even a billion steps in
+        let steps = 1_000_000_000u64;
+        let total: u64 = data
+            .count_mod(steps, modulus)
+            .values()
+            .fold(0, |total, count| (total + count) % modulus);
+        let length = (BigUint::from(3u32)
+            * BigUint::from(2u32).modpow(&BigUint::from(steps), &BigUint::from(modulus))
+            + 1u32)
+            % modulus;
+        assert_eq!(BigUint::from(total), length);
     }
 }
@@ -1,13 +1,18 @@
 use hashbrown::HashSet;
 use std::cmp::{max, min};
-use std::iter::FromIterator;
+use std::env::temp_dir;
+use std::fs;
 
-use itertools::Itertools;
 use nom::bytes::complete::tag;
 use nom::sequence::{preceded, separated_pair};
 use nom::IResult;
 
 use crate::aoc_lib::jazz_parser;
+use crate::aoc_lib::params::{DayParams, Param};
+
+const EXPORT_PLOT: Param<bool> = Param::new("export", false);
+
+pub const PARAMS: DayParams = &[&EXPORT_PLOT];
 
 // This problem was simple in my mind, but then my tendency of screwing up loop-based algorithms with
 // "by one" errors is legendary
@@ -15,6 +20,52 @@ use crate::aoc_lib::jazz_parser;
 // Point type
 type Point = (i32, i32);
 
+// Steps at which a probe is within the target columns. Horizontal speed drops to 0, so once a probe
+// stops within the target it stays there forever
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct StepRange {
+    first: u32,
+    last: Option<u32>,
+}
+
+impl StepRange {
+    fn contains(&self, step: u32) -> bool {
+        step >= self.first && self.last.is_none_or(|last| step <= last)
+    }
+}
+
+// Positions of a probe after every step, with drag pulling the horizontal speed towards 0
+struct Trajectory {
+    position: Point,
+    velocity: Point,
+}
+
+impl Iterator for Trajectory {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.position.0 += self.velocity.0;
+        self.position.1 += self.velocity.1;
+        self.velocity.0 -= self.velocity.0.signum();
+        self.velocity.1 -= 1;
+
+        Some(self.position)
+    }
+}
+
+fn trajectory(initial_velocity: &Point) -> Trajectory {
+    Trajectory {
+        position: (0, 0),
+        velocity: *initial_velocity,
+    }
+}
+
+// Highest point a probe reaches, the launch point included
+fn max_height(initial_velocity: &Point) -> i32 {
+    let vel_y = max(initial_velocity.1, 0);
+    vel_y * (vel_y + 1) / 2
+}
+
 struct Trench {
     top_left: Point,
     bottom_right: Point,
@@ -31,126 +82,163 @@ impl Trench {
         }
     }
 
-    // This function uses the formula to compute the sum of the first n natural numbers to find the first
-    // x velocity that will get to 0 once in range of the trench. This works because x velocities reach 0
-    // and cannot go lower, so the final distance = sum(ini_x, ini_x - 1, .., 1, 0)
-    // Using this value we can go crazy with the y velocities, as we won't overshoot horizontally the
-    // trench no matter how high we go
-    fn best_x(&self) -> i32 {
-        let mut curr_x_speed = 0i32;
-        while curr_x_speed * (curr_x_speed + 1i32) / 2i32 < self.top_left.0 {
-            curr_x_speed += 1i32;
-        }
-
-        curr_x_speed
+    fn contains_x(&self, x: i32) -> bool {
+        x >= self.top_left.0 && x <= self.bottom_right.0
     }
 
-    // After running a ton of computations I stopped and thought about how the ship moves. To end in the
-    // trench we just need to be at `min_y + 1` y speed when we are at `y = 0` (No matter the time step
-    // thanks to the zero x computation), as then the next step below 0 will be `min_y`
-    // Since the speed follows a parabolic trajectory we just need to set the starting velocity to the
-    // negative of the target speed at `y = 0`
-    pub fn coolest_speed(&self) -> Point {
-        (self.best_x(), -(self.bottom_right.1 + 1i32))
+    fn contains_y(&self, y: i32) -> bool {
+        y <= self.top_left.1 && y >= self.bottom_right.1
     }
 
-    // This function computes ALL the initial vertical velocities and how many steps will take the ship to reach the
-    // trench height.
-    // The function first computes all the speeds that will get to the trench directly and in how many steps and use
-    // that value to compute the time required for the opposite speed (i.e. how much to follow the parabolic trajectory)
-    fn get_potential_y_velocities(&self) -> Vec<(i32, i32)> {
-        let max_y = -(self.bottom_right.1 + 1i32);
-        let mut available_velocities: Vec<(i32, i32)> = vec![(self.bottom_right.1, 1i32)];
-        for start_y in 0i32..=max_y {
-            let mut y_check = 0i32;
-            let mut step = 0i32;
-            while y_check > self.top_left.1 {
-                y_check -= start_y + step;
-                step += 1i32;
-            }
-
-            while y_check >= self.bottom_right.1 {
-                available_velocities.push((-start_y, step));
-                available_velocities.push((start_y, start_y * 2 + step));
-                y_check -= start_y + step;
-                step += 1i32;
-            }
-        }
-
-        available_velocities
+    pub fn is_point_inside(&self, point: &Point) -> bool {
+        self.contains_x(point.0) && self.contains_y(point.1)
     }
 
-    // This is the same as "best_x" but computes all the possible initial horizontal velocities
-    // that will make the ship hit the trench with 0 horizontal speed
-    fn get_all_zero_x(&self) -> Vec<i32> {
-        let mut curr_x_speed = self.best_x();
-        let mut zero_x_velocities: Vec<i32> = Vec::new();
-        while curr_x_speed * (curr_x_speed + 1i32) / 2i32 <= self.bottom_right.0 {
-            zero_x_velocities.push(curr_x_speed);
-            curr_x_speed += 1i32;
-        }
+    // The probe only moves away from the target from now on
+    fn out_of_reach(&self, position: &Point, velocity: &Point) -> bool {
+        let fallen_below = velocity.1 < 0 && position.1 < self.bottom_right.1;
+        let past_target = match velocity.0.signum() {
+            1 => position.0 > self.bottom_right.0,
+            -1 => position.0 < self.top_left.0,
+            _ => !self.contains_x(position.0),
+        };
 
-        zero_x_velocities
+        fallen_below || past_target
     }
 
-    // This function computes all the initial horizontal velocities that will make the ship
-    // hit the trench in `steps` time
-    fn get_x_velocities(&self, steps: i32) -> Vec<i32> {
-        let mut x_vels: Vec<i32> = Vec::new();
-
-        for pot_vel in steps..=self.bottom_right.0 {
-            let final_x_vel = pot_vel - steps;
-            let final_x =
-                ((pot_vel + 1i32) * pot_vel) / 2i32 - ((final_x_vel + 1i32) * final_x_vel) / 2i32;
+    // Positions until the probe is in the target, or until it can't get there anymore
+    pub fn compute_trajectory(&self, initial_velocity: &Point) -> Vec<Point> {
+        let mut probe = trajectory(initial_velocity);
+        let mut trajectory_steps: Vec<Point> = Vec::new();
 
-            if final_x >= self.top_left.0 && final_x <= self.bottom_right.0 {
-                x_vels.push(pot_vel);
+        while let Some(location) = probe.next() {
+            trajectory_steps.push(location);
+            if self.is_point_inside(&location) || self.out_of_reach(&location, &probe.velocity) {
+                break;
             }
         }
 
-        x_vels
+        trajectory_steps
     }
 
-    // Since we can shoot the ship directly at the trench we can use all the coordinates of the target
-    // trench as initial velocities
-    fn one_step_velocities(&self) -> Vec<Point> {
-        let platform_locations = (self.top_left.0..=self.bottom_right.0)
-            .cartesian_product(self.bottom_right.1..=self.top_left.1);
+    fn x_hit_steps(&self, vel_x: i32) -> Option<StepRange> {
+        let mut hit_steps: Option<StepRange> = None;
+        let (mut x, mut velocity) = (0i32, vel_x);
+
+        for step in 1u32.. {
+            x += velocity;
+            velocity -= velocity.signum();
+
+            if self.contains_x(x) {
+                let range = hit_steps.get_or_insert(StepRange {
+                    first: step,
+                    last: None,
+                });
+                if velocity == 0 {
+                    range.last = None;
+                    break;
+                }
+                range.last = Some(step);
+            } else if velocity == 0 || self.out_of_reach(&(x, 0), &(velocity, 0)) {
+                break;
+            }
+        }
 
-        platform_locations.collect_vec()
+        hit_steps
     }
 
-    // This is pretty straightforward:
-    //   - Get all the kown velocities
-    //   - Get all the working y velocities and how much they take to reach the trench
-    //      - For each of these velocities find the x velocities that reach the trench in that amount of steps
-    //      - Add the velocity vector to the set
-    //   - Get all the initial horizontal velocities that make the ship reach the trench with `vel_x = 0`
-    //      - Combine each of these velocities with all the y velocities that take `initial_x` steps or more
-    //        to reach the trench
-    //      - Add these vectors to the set
-    pub fn compute_initial_velocities(&self) -> HashSet<Point> {
-        let mut velocities: HashSet<Point> =
-            HashSet::from_iter(self.one_step_velocities().into_iter());
+    fn y_hit_steps(&self, vel_y: i32) -> Vec<u32> {
+        let mut hit_steps: Vec<u32> = Vec::new();
+        let (mut y, mut velocity) = (0i32, vel_y);
 
-        let all_initial_y_vels = self.get_potential_y_velocities();
+        for step in 1u32.. {
+            y += velocity;
+            velocity -= 1;
 
-        for (vel, steps) in all_initial_y_vels.iter() {
-            let x_vels = self.get_x_velocities(*steps);
-            let vel_combos = x_vels.into_iter().map(|x| (x, *vel));
-            velocities.extend(vel_combos);
+            if self.contains_y(y) {
+                hit_steps.push(step);
+            } else if velocity < 0 && y < self.bottom_right.1 {
+                break;
+            }
         }
 
-        let zero_x_vels = self.get_all_zero_x();
+        hit_steps
+    }
 
-        for x in zero_x_vels {
-            for (y, _) in all_initial_y_vels.iter().filter(|(_, steps)| *steps >= x) {
-                velocities.insert((x, *y));
+    // Both axes are independent, so each horizontal velocity is paired with the vertical ones
+    // that are within the target at any of the same steps. Faster probes overshoot the target on
+    // their first step, and vertically they can't go faster than the speed they'd have when
+    // coming back down to the launch height. A target at the launch height where probes can stop
+    // would be hit by any upward velocity, so only the ones in this range are returned
+    pub fn compute_initial_velocities(&self) -> HashSet<Point> {
+        let x_velocities = min(self.top_left.0, 0)..=max(self.bottom_right.0, 0);
+        let y_velocities =
+            min(self.bottom_right.1, 0)..=max(self.top_left.1, max(-self.bottom_right.1 - 1, 0));
+
+        let y_hits: Vec<(i32, Vec<u32>)> = y_velocities
+            .map(|vel_y| (vel_y, self.y_hit_steps(vel_y)))
+            .filter(|(_, steps)| !steps.is_empty())
+            .collect();
+
+        let mut velocities: HashSet<Point> = HashSet::new();
+        for vel_x in x_velocities {
+            if let Some(x_range) = self.x_hit_steps(vel_x) {
+                for (vel_y, steps) in y_hits.iter() {
+                    if steps.iter().any(|step| x_range.contains(*step)) {
+                        velocities.insert((vel_x, *vel_y));
+                    }
+                }
             }
         }
 
         velocities
     }
+
+    // The velocity reaching the highest point among the ones hitting the target
+    pub fn coolest_speed(&self) -> Option<Point> {
+        self.compute_initial_velocities()
+            .into_iter()
+            .max_by_key(|velocity| (max_height(velocity), velocity.1, -velocity.0.abs()))
+    }
+
+    // Trajectory like the ones in the puzzle, with the launch point as 'S', the probe positions as
+    // '#' and the target area as 'T'
+    pub fn plot_trajectory(&self, initial_velocity: &Point) -> String {
+        let steps = self.compute_trajectory(initial_velocity);
+        let steps_set: HashSet<&Point> = steps.iter().collect();
+
+        let xs = steps
+            .iter()
+            .map(|p| p.0)
+            .chain([0, self.top_left.0, self.bottom_right.0]);
+        let ys = steps
+            .iter()
+            .map(|p| p.1)
+            .chain([0, self.top_left.1, self.bottom_right.1]);
+        let (min_x, max_x) = (xs.clone().min().unwrap(), xs.max().unwrap());
+        let (min_y, max_y) = (ys.clone().min().unwrap(), ys.max().unwrap());
+
+        (min_y..=max_y)
+            .rev()
+            .map(|row| {
+                (min_x..=max_x)
+                    .map(|col| {
+                        let curr_coords: Point = (col, row);
+                        if curr_coords == (0, 0) {
+                            'S'
+                        } else if steps_set.contains(&curr_coords) {
+                            '#'
+                        } else if self.is_point_inside(&curr_coords) {
+                            'T'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
 }
 
 // Edge parser
@@ -175,9 +263,27 @@ fn target(input: &str) -> IResult<&str, Trench> {
 
 pub fn part1(input: &str) {
     let (_, target_trench) = target(input).unwrap();
-    let start_v = target_trench.coolest_speed();
-    let max_height: i32 = start_v.1 * (start_v.1 + 1i32) / 2i32;
-    println!("Maximum height for provided trench: {}", max_height);
+    let start_v = match target_trench.coolest_speed() {
+        Some(velocity) => velocity,
+        None => {
+            println!("There's no way to hit the provided trench");
+            return;
+        }
+    };
+    println!(
+        "Maximum height for provided trench: {}",
+        max_height(&start_v)
+    );
+
+    if !EXPORT_PLOT.get() {
+        return;
+    }
+
+    let plot_path = temp_dir().join("day17_trajectory.txt");
+    match fs::write(&plot_path, target_trench.plot_trajectory(&start_v)) {
+        Ok(()) => println!("Trajectory plot exported to {}", plot_path.display()),
+        Err(e) => println!("Failed to export the trajectory plot: {}", e),
+    }
 }
 
 pub fn part2(input: &str) {
@@ -194,9 +300,10 @@ mod tests {
     use nom::multi::separated_list1;
 
     impl Trench {
-        pub fn is_point_inside(&self, point: &Point) -> bool {
-            (point.0 >= self.top_left.0 && point.0 <= self.bottom_right.0)
-                && (point.1 <= self.top_left.1 && point.1 >= self.bottom_right.1)
+        pub fn hits(&self, initial_velocity: &Point) -> bool {
+            self.compute_trajectory(initial_velocity)
+                .last()
+                .is_some_and(|location| self.is_point_inside(location))
         }
     }
 
@@ -210,51 +317,12 @@ mod tests {
         separated_list1(multispace1, vel)(input)
     }
 
-    fn compute_trajectory(initial_velocities: &Point, target: &Trench) -> Vec<Point> {
-        let mut trajectory_steps: Vec<Point> = Vec::new();
-        let mut current_location: Point = (0i32, 0i32);
-        let mut simulation_step: u16 = 0u16;
-
-        while (current_location.0 < target.bottom_right.0
-            && current_location.1 > target.bottom_right.1)
-            && !target.is_point_inside(&current_location)
-        {
-            let x_step = max(initial_velocities.0 - i32::from(simulation_step), 0i32);
-            let y_step = initial_velocities.1 - i32::from(simulation_step);
-            current_location.0 += x_step;
-            current_location.1 += y_step;
-
-            simulation_step += 1u16;
-            trajectory_steps.push(current_location);
-        }
-
-        trajectory_steps
-    }
-
-    fn _print_trajectory(steps: &[Point], target_trench: &Trench) {
-        let mut curr_y = *steps.iter().map(|(_, y)| y).max().unwrap();
-        let min_y = min(steps.last().unwrap().1, target_trench.bottom_right.1);
-        let max_x = max(steps.last().unwrap().0, target_trench.bottom_right.0);
-
-        let steps_set: HashSet<&Point> = HashSet::from_iter(steps.iter());
-
-        while curr_y >= min_y {
-            for col in 0i32..=max_x {
-                let curr_coords: Point = (col, curr_y);
-                if col == 0i32 && curr_y == 0i32 {
-                    print!("S");
-                } else if steps_set.contains(&curr_coords) {
-                    print!("#");
-                } else if target_trench.is_point_inside(&curr_coords) {
-                    print!("T");
-                } else {
-                    print!(".");
-                }
-            }
-
-            println!();
-            curr_y -= 1i32;
-        }
+    // Tries every velocity in a generous range, simulating each of them
+    fn brute_force_velocities(target: &Trench, limit: i32) -> HashSet<Point> {
+        (-limit..=limit)
+            .flat_map(|x| (-limit..=limit).map(move |y| (x, y)))
+            .filter(|velocity| target.hits(velocity))
+            .collect()
     }
 
     #[test]
@@ -273,7 +341,7 @@ mod tests {
             (28i32, -7i32),
         ];
 
-        let traj = compute_trajectory(&start_v, &target_trench);
+        let traj = target_trench.compute_trajectory(&start_v);
 
         assert_eq!(traj, reference_trajectory);
 
@@ -290,7 +358,7 @@ mod tests {
             (21i32, -9i32),
         ];
 
-        let traj = compute_trajectory(&start_v, &target_trench);
+        let traj = target_trench.compute_trajectory(&start_v);
 
         assert_eq!(traj, reference_trajectory);
 
@@ -298,27 +366,27 @@ mod tests {
         let reference_trajectory: Vec<Point> =
             vec![(9i32, 0i32), (17i32, -1i32), (24i32, -3i32), (30i32, -6i32)];
 
-        let traj = compute_trajectory(&start_v, &target_trench);
+        let traj = target_trench.compute_trajectory(&start_v);
 
         assert_eq!(traj, reference_trajectory);
 
         let start_v: Point = (17i32, -4i32);
         let reference_trajectory: Vec<Point> = vec![(17i32, -4i32), (33i32, -9i32)];
 
-        let traj = compute_trajectory(&start_v, &target_trench);
+        let traj = target_trench.compute_trajectory(&start_v);
 
         assert_eq!(traj, reference_trajectory);
+        assert!(!target_trench.hits(&start_v));
     }
 
     #[test]
     fn compute_max_height() {
         let input_string = "target area: x=20..30, y=-10..-5";
         let (_, target_trench) = target(input_string).unwrap();
-        let start_v: Point = target_trench.coolest_speed();
-
-        let max_height: i32 = start_v.1 * (start_v.1 + 1i32) / 2i32;
+        let start_v: Point = target_trench.coolest_speed().unwrap();
 
-        assert_eq!(max_height, 45i32);
+        assert_eq!(start_v.1, 9);
+        assert_eq!(max_height(&start_v), 45i32);
     }
 
     #[test]
@@ -330,14 +398,27 @@ mod tests {
             -8, 4, -5, -3, -9, -7, 6, 9, -4, 5, 7, -10, 0, 2, 8, -2, -1, 1, -6, 3,
         ]);
 
-        let computed_y_vels: HashSet<i32> = HashSet::from_iter(
-            target_trench
-                .get_potential_y_velocities()
-                .into_iter()
-                .map(|(y, _)| y),
-        );
+        let computed_y_vels: HashSet<i32> = (-20..=20)
+            .filter(|vel_y| !target_trench.y_hit_steps(*vel_y).is_empty())
+            .collect();
 
         assert_eq!(computed_y_vels, ref_y_velocities);
+        assert_eq!(target_trench.y_hit_steps(2), vec![7]);
+        assert_eq!(
+            target_trench.x_hit_steps(6),
+            Some(StepRange {
+                first: 5,
+                last: None
+            })
+        );
+        assert_eq!(
+            target_trench.x_hit_steps(20),
+            Some(StepRange {
+                first: 1,
+                last: Some(1)
+            })
+        );
+        assert_eq!(target_trench.x_hit_steps(31), None);
     }
 
     #[test]
@@ -361,10 +442,70 @@ mod tests {
 
         let (_, reference_velocities) = velocities(input_result).unwrap();
 
-        let reference_set: HashSet<Point> = HashSet::from_iter(reference_velocities.into_iter());
+        let reference_set: HashSet<Point> = HashSet::from_iter(reference_velocities);
 
         let initial_velocities: HashSet<Point> = target_trench.compute_initial_velocities();
 
         assert_eq!(initial_velocities, reference_set);
     }
+
+    #[test]
+    fn other_target_sides() {
+        for input_string in [
+            "target area: x=-30..-20, y=-10..-5",
+            "target area: x=20..30, y=5..10",
+            "target area: x=-30..-20, y=5..10",
+            "target area: x=-3..4, y=-6..-2",
+        ] {
+            let (_, target_trench) = target(input_string).unwrap();
+
+            assert_eq!(
+                target_trench.compute_initial_velocities(),
+                brute_force_velocities(&target_trench, 40),
+                "{}",
+                input_string
+            );
+        }
+
+        // Mirroring the example target doesn't change anything but the horizontal direction
+        let (_, mirrored) = target("target area: x=-30..-20, y=-10..-5").unwrap();
+        assert_eq!(mirrored.compute_initial_velocities().len(), 112);
+        assert_eq!(mirrored.coolest_speed().map(|v| max_height(&v)), Some(45));
+
+        let (_, above) = target("target area: x=20..30, y=5..10").unwrap();
+        let highest = above.coolest_speed().unwrap();
+        assert_eq!(highest.1, 10);
+        assert!(above.hits(&highest));
+    }
+
+    #[test]
+    fn trajectory_plot() {
+        let (_, target_trench) = target("target area: x=20..30, y=-10..-5").unwrap();
+
+        assert_eq!(
+            target_trench.plot_trajectory(&(7, 2)),
+            ".............#....#............\n\
+             .......#..............#........\n\
+             ...............................\n\
+             S........................#.....\n\
+             ...............................\n\
+             ...............................\n\
+             ...........................#...\n\
+             ...............................\n\
+             ....................TTTTTTTTTTT\n\
+             ....................TTTTTTTTTTT\n\
+             ....................TTTTTTTT#TT\n\
+             ....................TTTTTTTTTTT\n\
+             ....................TTTTTTTTTTT\n\
+             ....................TTTTTTTTTTT"
+        );
+
+        let (_, behind) = target("target area: x=-4..-2, y=1..2").unwrap();
+        assert_eq!(
+            behind.plot_trajectory(&(-2, 2)),
+            "TT#..\n\
+             TTT..\n\
+             ....S"
+        );
+    }
 }
@@ -4,6 +4,10 @@ use nom::character::complete::{space0, u8};
 use nom::sequence::{delimited, preceded, separated_pair};
 use nom::IResult;
 
+use rayon::prelude::*;
+
+use std::cmp::Reverse;
+
 type SailfishArena = Arena<Option<u8>>;
 
 #[derive(Clone)]
@@ -162,7 +166,7 @@ fn explode(arena: &mut SailfishArena, deep_node: NodeId) {
 
 fn split(arena: &mut SailfishArena, big_node: NodeId) {
     let curr_val = arena.get_mut(big_node).unwrap().get_mut().take().unwrap();
-    let (new_left, new_right) = if curr_val % 2 == 0 {
+    let (new_left, new_right) = if curr_val.is_multiple_of(2) {
         (curr_val / 2, curr_val / 2)
     } else {
         (curr_val / 2, curr_val / 2 + 1)
@@ -211,14 +215,23 @@ fn sailfish_tree(input: &str) -> IResult<&str, TempNode> {
     Ok((remain_str, TempNode { left, right }))
 }
 
-fn parse_numbers(input: &str, arena: &mut SailfishArena) -> Vec<NodeId> {
-    let mut roots: Vec<NodeId> = Vec::new();
-    for line in input.lines() {
-        let (_, new_number) = sailfish_tree(line).unwrap();
-        roots.push(convert_tree(new_number, arena));
-    }
+fn homework(input: &str) -> Vec<TempNode> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (_, number) = sailfish_tree(line).unwrap();
+            number
+        })
+        .collect()
+}
 
-    roots
+#[cfg(test)]
+fn parse_numbers(input: &str, arena: &mut SailfishArena) -> Vec<NodeId> {
+    homework(input)
+        .into_iter()
+        .map(|number| convert_tree(number, arena))
+        .collect()
 }
 
 fn convert_tree(from: TempNode, arena: &mut SailfishArena) -> NodeId {
@@ -254,39 +267,92 @@ fn convert_tree(from: TempNode, arena: &mut SailfishArena) -> NodeId {
     new_root
 }
 
-pub fn part1(input: &str) {
-    let mut arena: SailfishArena = Arena::new();
-    let numbers = parse_numbers(input, &mut arena);
+// Reads a number back out of the arena, so it can outlive it
+fn extract_tree(arena: &SailfishArena, node_idx: NodeId) -> TempNode {
+    let current_node = arena.get(node_idx).unwrap();
 
-    let mut total_idx = sum(&mut arena, numbers[0], numbers[1]);
-    for next_root in numbers.into_iter().skip(2) {
-        total_idx = sum(&mut arena, total_idx, next_root);
+    TempNode {
+        left: extract_component(arena, current_node.first_child().unwrap()),
+        right: extract_component(arena, current_node.last_child().unwrap()),
     }
+}
 
-    println!(
-        "Final magnitude of the sum: {}",
-        compute_magnitude(&arena, total_idx)
-    );
+fn extract_component(arena: &SailfishArena, node_idx: NodeId) -> TempRef {
+    match arena.get(node_idx).unwrap().get() {
+        Some(val) => TempRef::Value(*val),
+        None => TempRef::Node(Box::new(extract_tree(arena, node_idx))),
+    }
 }
 
-pub fn part2(input: &str) {
+// Every addition gets its own arena, so the nodes dropped while reducing don't pile up over a
+// long homework list
+fn add_numbers(lhs: &TempNode, rhs: &TempNode) -> (SailfishArena, NodeId) {
     let mut arena: SailfishArena = Arena::new();
-    let mut max_magnitude: u64 = 0;
-
-    for (start_idx, first) in input.lines().enumerate() {
-        for second in input.lines().skip(start_idx + 1) {
-            let numbers = parse_numbers(format!("{}\n{}", first, second).as_str(), &mut arena);
-            let f_s_root = sum(&mut arena, numbers[0], numbers[1]);
-            max_magnitude = max_magnitude.max(compute_magnitude(&arena, f_s_root));
-            f_s_root.remove_subtree(&mut arena);
-
-            let numbers = parse_numbers(format!("{}\n{}", first, second).as_str(), &mut arena);
-            let s_f_root = sum(&mut arena, numbers[1], numbers[0]);
-            max_magnitude = max_magnitude.max(compute_magnitude(&arena, s_f_root));
-        }
+    let lhs_root = convert_tree(lhs.clone(), &mut arena);
+    let rhs_root = convert_tree(rhs.clone(), &mut arena);
+    let root = sum(&mut arena, lhs_root, rhs_root);
+
+    (arena, root)
+}
+
+fn homework_sum(numbers: &[TempNode]) -> Option<u64> {
+    let (first, others) = numbers.split_first()?;
+
+    let mut total = first.clone();
+    for number in others {
+        let (arena, root) = add_numbers(&total, number);
+        total = extract_tree(&arena, root);
     }
 
-    println!("Maximum magnitude of all the pairs: {}", max_magnitude);
+    let mut arena: SailfishArena = Arena::new();
+    let root = convert_tree(total, &mut arena);
+    Some(compute_magnitude(&arena, root))
+}
+
+// Indices of the homework numbers added together, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PairMagnitude {
+    first: usize,
+    second: usize,
+    magnitude: u64,
+}
+
+// Addition isn't commutative, so both orders of every pair are tried. Ties go to the earliest pair
+fn largest_pair_sum(numbers: &[TempNode]) -> Option<PairMagnitude> {
+    (0..numbers.len())
+        .into_par_iter()
+        .flat_map_iter(|first| {
+            (0..numbers.len())
+                .filter(move |&second| second != first)
+                .map(move |second| {
+                    let (arena, root) = add_numbers(&numbers[first], &numbers[second]);
+                    PairMagnitude {
+                        first,
+                        second,
+                        magnitude: compute_magnitude(&arena, root),
+                    }
+                })
+        })
+        .max_by_key(|pair| (pair.magnitude, Reverse(pair.first), Reverse(pair.second)))
+}
+
+pub fn part1(input: &str) {
+    match homework_sum(&homework(input)) {
+        Some(magnitude) => println!("Final magnitude of the sum: {}", magnitude),
+        None => println!("There's no homework to sum"),
+    }
+}
+
+pub fn part2(input: &str) {
+    match largest_pair_sum(&homework(input)) {
+        Some(pair) => println!(
+            "Maximum magnitude of all the pairs: {} (number {} plus number {})",
+            pair.magnitude,
+            pair.first + 1,
+            pair.second + 1
+        ),
+        None => println!("There are no pairs of numbers to add"),
+    }
 }
 
 #[cfg(test)]
@@ -556,4 +622,50 @@ mod tests {
 
         assert_eq!(max_magnitude, 3993);
     }
+
+    #[test]
+    fn parallel_max_pair() {
+        let input_string = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+        [[[5,[2,8]],4],[5,[[9,9],0]]]
+        [6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
+        [[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
+        [[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]
+
+        [[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]
+        [[[[5,4],[7,7]],8],[[8,3],8]]
+        [[9,3],[[9,9],[6,[4,9]]]]
+        [[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
+        [[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
+
+        let numbers = homework(input_string);
+
+        assert_eq!(numbers.len(), 10);
+        assert_eq!(homework_sum(&numbers), Some(4140));
+        assert_eq!(
+            largest_pair_sum(&numbers),
+            Some(PairMagnitude {
+                first: 8,
+                second: 0,
+                magnitude: 3993
+            })
+        );
+
+        assert_eq!(homework_sum(&[]), None);
+        assert_eq!(largest_pair_sum(&numbers[..1]), None);
+    }
+
+    #[test]
+    fn extracted_sums() {
+        let numbers = homework("[[[[4,3],4],4],[7,[[8,4],9]]]\n[1,1]");
+
+        let (arena, root) = add_numbers(&numbers[0], &numbers[1]);
+        let extracted = extract_tree(&arena, root);
+
+        let mut other_arena: SailfishArena = Arena::new();
+        let other_root = convert_tree(extracted, &mut other_arena);
+        assert_eq!(
+            print_number(&other_arena, other_root),
+            "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]"
+        );
+    }
 }
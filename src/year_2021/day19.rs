@@ -3,9 +3,10 @@ use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::env::temp_dir;
 use std::fs::File;
-use std::io::{Error, Write};
+use std::io::{BufWriter, Error, Write};
+use std::path::Path;
 
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
 use nom::bytes::complete::tag;
 use nom::character::complete::{digit1, i64, newline, space0};
@@ -15,6 +16,18 @@ use nom::sequence::{delimited, terminated, tuple};
 use nom::IResult;
 
 use nalgebra::{Matrix3, Point3, Vector3};
+use rayon::prelude::*;
+
+use crate::aoc_lib::params::{DayParams, Param};
+
+const EXPORT_REPORT: Param<bool> = Param::new("export", false);
+pub const PARAMS: DayParams = &[&EXPORT_REPORT];
+
+// Two scanners are considered overlapping if they share at least this many beacons
+const OVERLAPS_NEEDED: usize = 12;
+
+// Neighbouring scanner with the rotation and translation that map its beacons into our frame
+type ScannerLink = (usize, Matrix3<i64>, Vector3<i64>);
 
 const ROTATION_MATRICES: [[[i64; 3]; 3]; 24] = [
     [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
@@ -57,28 +70,105 @@ fn compare_points(left: &Point3<i64>, right: &Point3<i64>) -> Ordering {
     left.z.cmp(&right.z)
 }
 
-fn _write_beacons(filename: &str, rotated_data: &[Vec<Point3<i64>>]) -> Result<(), Error> {
-    let mut file_path = temp_dir();
-    file_path.push(filename);
+fn rotation_matrix(rot_idx: usize) -> Matrix3<i64> {
+    Matrix3::from_iterator(ROTATION_MATRICES[rot_idx].iter().flatten().cloned())
+}
+
+/// Absolute placement of a scanner, expressed in the reference frame of scanner 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannerPlacement {
+    pub scanner_id: usize,
+    pub position: Point3<i64>,
+    pub rotation: Matrix3<i64>,
+}
+
+/// Outcome of the full reconstruction. Scanners are stored in the order in which they have been
+/// merged into the map, starting from scanner 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignmentReport {
+    pub scanners: Vec<ScannerPlacement>,
+    pub beacons: Vec<Point3<i64>>,
+}
+
+impl AlignmentReport {
+    pub fn merge_order(&self) -> Vec<usize> {
+        self.scanners.iter().map(|s| s.scanner_id).collect()
+    }
+
+    pub fn origins(&self) -> Vec<Point3<i64>> {
+        self.scanners.iter().map(|s| s.position).collect()
+    }
 
-    let mut output = File::create(file_path)?;
+    pub fn write_beacons_csv<W: Write>(&self, output: &mut W) -> Result<(), Error> {
+        writeln!(output, "x,y,z")?;
+        for beacon in self.beacons.iter() {
+            writeln!(output, "{},{},{}", beacon.x, beacon.y, beacon.z)?;
+        }
 
-    for translation_block in rotated_data.iter() {
-        writeln!(output, "==== Shifted block start ====")?;
-        writeln!(output)?;
+        Ok(())
+    }
 
-        for beacon_location in translation_block {
+    pub fn write_scanners_csv<W: Write>(&self, output: &mut W) -> Result<(), Error> {
+        writeln!(
+            output,
+            "scanner,merge_step,x,y,z,r11,r12,r13,r21,r22,r23,r31,r32,r33"
+        )?;
+        for (step, scanner) in self.scanners.iter().enumerate() {
+            let rotation = scanner.rotation.transpose().iter().join(",");
             writeln!(
                 output,
-                "{}, {}, {}",
-                beacon_location.x, beacon_location.y, beacon_location.z
+                "{},{},{},{},{},{}",
+                scanner.scanner_id,
+                step,
+                scanner.position.x,
+                scanner.position.y,
+                scanner.position.z,
+                rotation
             )?;
         }
 
-        writeln!(output)?;
-        writeln!(output, "==== Shifted block end ====")?;
-        writeln!(output)?;
+        Ok(())
+    }
+
+    /// Writes an ASCII PLY point cloud: beacons are white, scanners are red.
+    pub fn write_ply<W: Write>(&self, output: &mut W) -> Result<(), Error> {
+        writeln!(output, "ply")?;
+        writeln!(output, "format ascii 1.0")?;
+        writeln!(output, "comment AoC 2021 day 19 beacon map")?;
+        writeln!(
+            output,
+            "element vertex {}",
+            self.beacons.len() + self.scanners.len()
+        )?;
+        for coord in ["x", "y", "z"] {
+            writeln!(output, "property int {}", coord)?;
+        }
+        for channel in ["red", "green", "blue"] {
+            writeln!(output, "property uchar {}", channel)?;
+        }
+        writeln!(output, "end_header")?;
+
+        for beacon in self.beacons.iter() {
+            writeln!(output, "{} {} {} 255 255 255", beacon.x, beacon.y, beacon.z)?;
+        }
+        for scanner in self.scanners.iter() {
+            let pos = scanner.position;
+            writeln!(output, "{} {} {} 255 0 0", pos.x, pos.y, pos.z)?;
+        }
+
+        Ok(())
     }
+}
+
+fn export_report(report: &AlignmentReport, folder: &Path) -> Result<(), Error> {
+    let mut beacons_csv = BufWriter::new(File::create(folder.join("day19_beacons.csv"))?);
+    report.write_beacons_csv(&mut beacons_csv)?;
+
+    let mut scanners_csv = BufWriter::new(File::create(folder.join("day19_scanners.csv"))?);
+    report.write_scanners_csv(&mut scanners_csv)?;
+
+    let mut point_cloud = BufWriter::new(File::create(folder.join("day19_map.ply"))?);
+    report.write_ply(&mut point_cloud)?;
 
     Ok(())
 }
@@ -102,9 +192,8 @@ impl SensorData {
     }
 
     pub fn compute_rotations(&mut self) {
-        for (rot_idx, rotation_matrix) in ROTATION_MATRICES
-            .iter()
-            .map(|mat| Matrix3::from_iterator(mat.iter().flatten().cloned()))
+        for (rot_idx, rotation_matrix) in (0..ROTATION_MATRICES.len())
+            .map(rotation_matrix)
             .enumerate()
         {
             self.rotated_data[rot_idx] = self
@@ -126,11 +215,12 @@ impl SensorData {
         let mut translation: Vector3<i64> = Vector3::zeros();
 
         // There have to be at least 12 shared beacons to have two overlapped sensor spaces
-        let last_start_beacon = beacons.len() - overlaps_needed;
+        let last_start_beacon = (beacons.len() + 1).saturating_sub(overlaps_needed);
         for (rot_idx, rotated_beacons) in self.rotated_data.iter().enumerate() {
+            let last_start_origin = (rotated_beacons.len() + 1).saturating_sub(overlaps_needed);
             for (shift_idx, shift_target) in beacons.iter().take(last_start_beacon).enumerate() {
                 for (origin_id, curr_origin) in
-                    rotated_beacons.iter().take(last_start_beacon).enumerate()
+                    rotated_beacons.iter().take(last_start_origin).enumerate()
                 {
                     let mut current_overlaps: usize = 1;
                     let block_translation: Vector3<i64> = shift_target - curr_origin;
@@ -200,49 +290,132 @@ fn full_data(input: &str) -> IResult<&str, Vec<SensorData>> {
     ))(input)
 }
 
-fn merge_sensors(
-    dst: Vec<Point3<i64>>,
-    beacons: &[Point3<i64>],
-    origin_translation: &Vector3<i64>,
-) -> Vec<Point3<i64>> {
-    let mut beacons_set: HashSet<Point3<i64>> = dst.into_iter().collect();
+/// Squared distances between every pair of beacons seen by a scanner, sorted. They do not depend
+/// on the orientation or position of the scanner, so two scanners sharing 12 beacons have to share
+/// at least 66 of these distances.
+fn distance_fingerprint(beacons: &[Point3<i64>]) -> Vec<i64> {
+    beacons
+        .iter()
+        .tuple_combinations()
+        .map(|(left, right)| {
+            let distance = right - left;
+            distance.dot(&distance)
+        })
+        .sorted_unstable()
+        .collect()
+}
+
+fn shared_distances(left: &[i64], right: &[i64]) -> usize {
+    let mut left_iter = left.iter().peekable();
+    let mut right_iter = right.iter().peekable();
+    let mut shared: usize = 0;
+
+    while let (Some(left_dist), Some(right_dist)) = (left_iter.peek(), right_iter.peek()) {
+        match left_dist.cmp(right_dist) {
+            Ordering::Less => {
+                left_iter.next();
+            }
+            Ordering::Greater => {
+                right_iter.next();
+            }
+            Ordering::Equal => {
+                shared += 1;
+                left_iter.next();
+                right_iter.next();
+            }
+        }
+    }
 
-    beacons_set.extend(beacons.iter().map(|b| b + origin_translation));
-    beacons_set.into_iter().sorted_by(compare_points).collect()
+    shared
 }
 
-fn reconstruct_beacon_map(
-    base_data: &[Point3<i64>],
-    sensors: &[SensorData],
-) -> (Vec<Point3<i64>>, Vec<Point3<i64>>) {
-    let mut full_map: Vec<Point3<i64>> = base_data.to_vec();
-    let mut sensors_to_parse: HashSet<usize> = (0..sensors.len()).collect();
-    let mut origins: Vec<Point3<i64>> = vec![Point3::new(0, 0, 0)];
+fn find_candidate_pairs(fingerprints: &[Vec<i64>]) -> Vec<(usize, usize)> {
+    let distances_needed = OVERLAPS_NEEDED * (OVERLAPS_NEEDED - 1) / 2;
 
-    while !sensors_to_parse.is_empty() {
-        let remaining_sensors: Vec<_> = sensors
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| sensors_to_parse.contains(idx))
+    (0..fingerprints.len())
+        .tuple_combinations()
+        .filter(|&(left, right)| {
+            shared_distances(&fingerprints[left], &fingerprints[right]) >= distances_needed
+        })
+        .collect()
+}
+
+fn reconstruct_beacon_map(sensors: &[SensorData]) -> Result<AlignmentReport, &'static str> {
+    let fingerprints: Vec<Vec<i64>> = sensors
+        .par_iter()
+        .map(|sensor| distance_fingerprint(&sensor.beacons))
+        .collect();
+
+    // Each link maps the beacons of the second scanner in the reference frame of the first one
+    let relative_placements: Vec<(usize, usize, Matrix3<i64>, Vector3<i64>)> =
+        find_candidate_pairs(&fingerprints)
+            .into_par_iter()
+            .filter_map(|(base, other)| {
+                sensors[other]
+                    .find_overlap(&sensors[base].beacons, OVERLAPS_NEEDED)
+                    .map(|(rot, translation)| {
+                        (base, other, rotation_matrix(usize::from(rot)), translation)
+                    })
+            })
             .collect();
-        let remaining_idx: Vec<_> = remaining_sensors.iter().map(|(id, _)| id).collect();
-        println!("Remaining sensors to parse: {:?}", remaining_idx);
-        for (sensor_idx, sensor_data) in remaining_sensors.into_iter() {
-            if let Some((rot, translation)) = sensor_data.find_overlap(&full_map, 12) {
-                full_map = merge_sensors(
-                    full_map,
-                    &sensor_data.rotated_data[usize::from(rot)],
-                    &translation,
-                );
-
-                origins.push(Point3::from(translation));
-
-                sensors_to_parse.remove(&sensor_idx);
+
+    let mut links: HashMap<usize, Vec<ScannerLink>> = HashMap::new();
+    for (base, other, rotation, translation) in relative_placements.into_iter() {
+        let inverse_rotation = rotation.transpose();
+        links
+            .entry(base)
+            .or_default()
+            .push((other, rotation, translation));
+        links.entry(other).or_default().push((
+            base,
+            inverse_rotation,
+            -(inverse_rotation * translation),
+        ));
+    }
+
+    let mut scanners: Vec<ScannerPlacement> = vec![ScannerPlacement {
+        scanner_id: 0,
+        position: Point3::origin(),
+        rotation: Matrix3::identity(),
+    }];
+    let mut placed: HashSet<usize> = HashSet::from([0]);
+
+    // Breadth-first visit, the placements vector doubles as the queue
+    let mut next_to_visit: usize = 0;
+    while let Some(current) = scanners.get(next_to_visit).cloned() {
+        next_to_visit += 1;
+
+        for (neighbour, rotation, translation) in
+            links.get(&current.scanner_id).into_iter().flatten()
+        {
+            if placed.insert(*neighbour) {
+                scanners.push(ScannerPlacement {
+                    scanner_id: *neighbour,
+                    position: current.position + current.rotation * translation,
+                    rotation: current.rotation * rotation,
+                });
             }
         }
     }
 
-    (full_map, origins)
+    if scanners.len() < sensors.len() {
+        return Err("Some scanners do not overlap with the reconstructed map");
+    }
+
+    let beacons: Vec<Point3<i64>> = scanners
+        .iter()
+        .flat_map(|scanner| {
+            sensors[scanner.scanner_id]
+                .beacons
+                .iter()
+                .map(move |beacon| scanner.rotation * beacon + scanner.position.coords)
+        })
+        .collect::<HashSet<Point3<i64>>>()
+        .into_iter()
+        .sorted_by(compare_points)
+        .collect();
+
+    Ok(AlignmentReport { scanners, beacons })
 }
 
 fn find_farthest_pair(origins: &[Point3<i64>]) -> (usize, (Point3<i64>, Point3<i64>)) {
@@ -276,11 +449,34 @@ pub fn both_parts(input: &str) {
         sensor.compute_rotations();
     }
 
-    let (beacon_volume, origins) = reconstruct_beacon_map(&sensors[0].beacons, &sensors[1..]);
-    let (manhattan_distance, _) = find_farthest_pair(&origins);
+    let report = match reconstruct_beacon_map(&sensors) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("Failed to reconstruct the beacon map: {}", e);
+            return;
+        }
+    };
+    let (manhattan_distance, _) = find_farthest_pair(&report.origins());
 
-    println!("Total beacons count: {}", beacon_volume.len());
+    println!("Total beacons count: {}", report.beacons.len());
     println!("Distance between farthest beacons: {}", manhattan_distance);
+
+    println!("Merge order: {:?}", report.merge_order());
+    for scanner in report.scanners.iter() {
+        println!(
+            "Scanner {:>2} at {:>6}, {:>6}, {:>6}",
+            scanner.scanner_id, scanner.position.x, scanner.position.y, scanner.position.z
+        );
+    }
+
+    if !EXPORT_REPORT.get() {
+        return;
+    }
+    let export_folder = temp_dir();
+    match export_report(&report, &export_folder) {
+        Ok(()) => println!("Reconstruction exported to {}", export_folder.display()),
+        Err(e) => println!("Failed to export the reconstruction: {}", e),
+    }
 }
 
 pub fn _part2(_input: &str) {}
@@ -289,6 +485,143 @@ pub fn _part2(_input: &str) {}
 mod tests {
     use super::*;
 
+    const EXAMPLE_SCANNERS: &str = "--- scanner 0 ---
+        404,-588,-901
+        528,-643,409
+        -838,591,734
+        390,-675,-793
+        -537,-823,-458
+        -485,-357,347
+        -345,-311,381
+        -661,-816,-575
+        -876,649,763
+        -618,-824,-621
+        553,345,-567
+        474,580,667
+        -447,-329,318
+        -584,868,-557
+        544,-627,-890
+        564,392,-477
+        455,729,728
+        -892,524,684
+        -689,845,-530
+        423,-701,434
+        7,-33,-71
+        630,319,-379
+        443,580,662
+        -789,900,-551
+        459,-707,401
+
+        --- scanner 1 ---
+        686,422,578
+        605,423,415
+        515,917,-361
+        -336,658,858
+        95,138,22
+        -476,619,847
+        -340,-569,-846
+        567,-361,727
+        -460,603,-452
+        669,-402,600
+        729,430,532
+        -500,-761,534
+        -322,571,750
+        -466,-666,-811
+        -429,-592,574
+        -355,545,-477
+        703,-491,-529
+        -328,-685,520
+        413,935,-424
+        -391,539,-444
+        586,-435,557
+        -364,-763,-893
+        807,-499,-711
+        755,-354,-619
+        553,889,-390
+
+        --- scanner 2 ---
+        649,640,665
+        682,-795,504
+        -784,533,-524
+        -644,584,-595
+        -588,-843,648
+        -30,6,44
+        -674,560,763
+        500,723,-460
+        609,671,-379
+        -555,-800,653
+        -675,-892,-343
+        697,-426,-610
+        578,704,681
+        493,664,-388
+        -671,-858,530
+        -667,343,800
+        571,-461,-707
+        -138,-166,112
+        -889,563,-600
+        646,-828,498
+        640,759,510
+        -630,509,768
+        -681,-892,-333
+        673,-379,-804
+        -742,-814,-386
+        577,-820,562
+
+        --- scanner 3 ---
+        -589,542,597
+        605,-692,669
+        -500,565,-823
+        -660,373,557
+        -458,-679,-417
+        -488,449,543
+        -626,468,-788
+        338,-750,-386
+        528,-832,-391
+        562,-778,733
+        -938,-730,414
+        543,643,-506
+        -524,371,-870
+        407,773,750
+        -104,29,83
+        378,-903,-323
+        -778,-728,485
+        426,699,580
+        -438,-605,-362
+        -469,-447,-387
+        509,732,623
+        647,635,-688
+        -868,-804,481
+        614,-800,639
+        595,780,-596
+
+        --- scanner 4 ---
+        727,592,562
+        -293,-554,779
+        441,611,-461
+        -714,465,-776
+        -743,427,-804
+        -660,-479,-426
+        832,-632,460
+        927,-485,-438
+        408,393,-506
+        466,436,-512
+        110,16,151
+        -258,-428,682
+        -393,719,612
+        -211,-452,876
+        808,-476,-593
+        -575,615,604
+        -485,667,467
+        -680,325,-822
+        -627,-443,-432
+        872,-547,-609
+        833,512,582
+        807,604,487
+        839,-516,451
+        891,-625,532
+        -652,-548,-490
+        30,-46,-14";
+
     // Utility function to generate all the rotation matrices I used to create the const data
     fn _generate_rot_matrices() -> Vec<Matrix3<i8>> {
         let roll_mat: Matrix3<i8> = Matrix3::new(1, 0, 0, 0, 0, 1, 0, -1, 0);
@@ -522,183 +855,46 @@ mod tests {
 
     #[test]
     fn beacon_volume_reconstruction() {
-        let input_string = "--- scanner 0 ---
-        404,-588,-901
-        528,-643,409
+        let (_, mut sensors) = full_data(EXAMPLE_SCANNERS).unwrap();
+
+        for sensor in sensors.iter_mut().skip(1) {
+            sensor.compute_rotations();
+        }
+
+        let beacon_volume = reconstruct_beacon_map(&sensors).unwrap().beacons;
+
+        let ref_volume_str = "-892,524,684
+        -876,649,763
         -838,591,734
-        390,-675,-793
-        -537,-823,-458
-        -485,-357,347
-        -345,-311,381
+        -789,900,-551
+        -739,-1745,668
+        -706,-3180,-659
+        -697,-3072,-689
+        -689,845,-530
+        -687,-1600,576
         -661,-816,-575
-        -876,649,763
+        -654,-3158,-753
+        -635,-1737,486
+        -631,-672,1502
+        -624,-1620,1868
+        -620,-3212,371
         -618,-824,-621
-        553,345,-567
-        474,580,667
-        -447,-329,318
+        -612,-1695,1788
+        -601,-1648,-643
         -584,868,-557
-        544,-627,-890
-        564,392,-477
-        455,729,728
-        -892,524,684
-        -689,845,-530
-        423,-701,434
-        7,-33,-71
-        630,319,-379
-        443,580,662
-        -789,900,-551
-        459,-707,401
-
-        --- scanner 1 ---
-        686,422,578
-        605,423,415
-        515,917,-361
-        -336,658,858
-        95,138,22
-        -476,619,847
-        -340,-569,-846
-        567,-361,727
-        -460,603,-452
-        669,-402,600
-        729,430,532
-        -500,-761,534
-        -322,571,750
-        -466,-666,-811
-        -429,-592,574
-        -355,545,-477
-        703,-491,-529
-        -328,-685,520
-        413,935,-424
-        -391,539,-444
-        586,-435,557
-        -364,-763,-893
-        807,-499,-711
-        755,-354,-619
-        553,889,-390
-
-        --- scanner 2 ---
-        649,640,665
-        682,-795,504
-        -784,533,-524
-        -644,584,-595
-        -588,-843,648
-        -30,6,44
-        -674,560,763
-        500,723,-460
-        609,671,-379
-        -555,-800,653
-        -675,-892,-343
-        697,-426,-610
-        578,704,681
-        493,664,-388
-        -671,-858,530
-        -667,343,800
-        571,-461,-707
-        -138,-166,112
-        -889,563,-600
-        646,-828,498
-        640,759,510
-        -630,509,768
-        -681,-892,-333
-        673,-379,-804
-        -742,-814,-386
-        577,-820,562
-
-        --- scanner 3 ---
-        -589,542,597
-        605,-692,669
-        -500,565,-823
-        -660,373,557
-        -458,-679,-417
-        -488,449,543
-        -626,468,-788
-        338,-750,-386
-        528,-832,-391
-        562,-778,733
-        -938,-730,414
-        543,643,-506
-        -524,371,-870
-        407,773,750
-        -104,29,83
-        378,-903,-323
-        -778,-728,485
-        426,699,580
-        -438,-605,-362
-        -469,-447,-387
-        509,732,623
-        647,635,-688
-        -868,-804,481
-        614,-800,639
-        595,780,-596
-
-        --- scanner 4 ---
-        727,592,562
-        -293,-554,779
-        441,611,-461
-        -714,465,-776
-        -743,427,-804
-        -660,-479,-426
-        832,-632,460
-        927,-485,-438
-        408,393,-506
-        466,436,-512
-        110,16,151
-        -258,-428,682
-        -393,719,612
-        -211,-452,876
-        808,-476,-593
-        -575,615,604
-        -485,667,467
-        -680,325,-822
-        -627,-443,-432
-        872,-547,-609
-        833,512,582
-        807,604,487
-        839,-516,451
-        891,-625,532
-        -652,-548,-490
-        30,-46,-14";
-
-        let (_, mut sensors) = full_data(input_string).unwrap();
-
-        for sensor in sensors.iter_mut().skip(1) {
-            sensor.compute_rotations();
-        }
-
-        let (beacon_volume, _) = reconstruct_beacon_map(&sensors[0].beacons, &sensors[1..]);
-
-        let ref_volume_str = "-892,524,684
-        -876,649,763
-        -838,591,734
-        -789,900,-551
-        -739,-1745,668
-        -706,-3180,-659
-        -697,-3072,-689
-        -689,845,-530
-        -687,-1600,576
-        -661,-816,-575
-        -654,-3158,-753
-        -635,-1737,486
-        -631,-672,1502
-        -624,-1620,1868
-        -620,-3212,371
-        -618,-824,-621
-        -612,-1695,1788
-        -601,-1648,-643
-        -584,868,-557
-        -537,-823,-458
-        -532,-1715,1894
-        -518,-1681,-600
-        -499,-1607,-770
-        -485,-357,347
-        -470,-3283,303
-        -456,-621,1527
-        -447,-329,318
-        -430,-3130,366
-        -413,-627,1469
-        -345,-311,381
-        -36,-1284,1171
-        -27,-1108,-65
+        -537,-823,-458
+        -532,-1715,1894
+        -518,-1681,-600
+        -499,-1607,-770
+        -485,-357,347
+        -470,-3283,303
+        -456,-621,1527
+        -447,-329,318
+        -430,-3130,366
+        -413,-627,1469
+        -345,-311,381
+        -36,-1284,1171
+        -27,-1108,-65
         7,-33,-71
         12,-2351,-103
         26,-1119,1091
@@ -764,150 +960,13 @@ mod tests {
 
     #[test]
     fn largest_beacon_distance() {
-        let input_string = "--- scanner 0 ---
-        404,-588,-901
-        528,-643,409
-        -838,591,734
-        390,-675,-793
-        -537,-823,-458
-        -485,-357,347
-        -345,-311,381
-        -661,-816,-575
-        -876,649,763
-        -618,-824,-621
-        553,345,-567
-        474,580,667
-        -447,-329,318
-        -584,868,-557
-        544,-627,-890
-        564,392,-477
-        455,729,728
-        -892,524,684
-        -689,845,-530
-        423,-701,434
-        7,-33,-71
-        630,319,-379
-        443,580,662
-        -789,900,-551
-        459,-707,401
-
-        --- scanner 1 ---
-        686,422,578
-        605,423,415
-        515,917,-361
-        -336,658,858
-        95,138,22
-        -476,619,847
-        -340,-569,-846
-        567,-361,727
-        -460,603,-452
-        669,-402,600
-        729,430,532
-        -500,-761,534
-        -322,571,750
-        -466,-666,-811
-        -429,-592,574
-        -355,545,-477
-        703,-491,-529
-        -328,-685,520
-        413,935,-424
-        -391,539,-444
-        586,-435,557
-        -364,-763,-893
-        807,-499,-711
-        755,-354,-619
-        553,889,-390
-
-        --- scanner 2 ---
-        649,640,665
-        682,-795,504
-        -784,533,-524
-        -644,584,-595
-        -588,-843,648
-        -30,6,44
-        -674,560,763
-        500,723,-460
-        609,671,-379
-        -555,-800,653
-        -675,-892,-343
-        697,-426,-610
-        578,704,681
-        493,664,-388
-        -671,-858,530
-        -667,343,800
-        571,-461,-707
-        -138,-166,112
-        -889,563,-600
-        646,-828,498
-        640,759,510
-        -630,509,768
-        -681,-892,-333
-        673,-379,-804
-        -742,-814,-386
-        577,-820,562
-
-        --- scanner 3 ---
-        -589,542,597
-        605,-692,669
-        -500,565,-823
-        -660,373,557
-        -458,-679,-417
-        -488,449,543
-        -626,468,-788
-        338,-750,-386
-        528,-832,-391
-        562,-778,733
-        -938,-730,414
-        543,643,-506
-        -524,371,-870
-        407,773,750
-        -104,29,83
-        378,-903,-323
-        -778,-728,485
-        426,699,580
-        -438,-605,-362
-        -469,-447,-387
-        509,732,623
-        647,635,-688
-        -868,-804,481
-        614,-800,639
-        595,780,-596
-
-        --- scanner 4 ---
-        727,592,562
-        -293,-554,779
-        441,611,-461
-        -714,465,-776
-        -743,427,-804
-        -660,-479,-426
-        832,-632,460
-        927,-485,-438
-        408,393,-506
-        466,436,-512
-        110,16,151
-        -258,-428,682
-        -393,719,612
-        -211,-452,876
-        808,-476,-593
-        -575,615,604
-        -485,667,467
-        -680,325,-822
-        -627,-443,-432
-        872,-547,-609
-        833,512,582
-        807,604,487
-        839,-516,451
-        891,-625,532
-        -652,-548,-490
-        30,-46,-14";
-
-        let (_, mut sensors) = full_data(input_string).unwrap();
+        let (_, mut sensors) = full_data(EXAMPLE_SCANNERS).unwrap();
 
         for sensor in sensors.iter_mut().skip(1) {
             sensor.compute_rotations();
         }
 
-        let (_, sensor_origins) = reconstruct_beacon_map(&sensors[0].beacons, &sensors[1..]);
+        let sensor_origins = reconstruct_beacon_map(&sensors).unwrap().origins();
 
         let (distance, (left_origin, right_origin)) = find_farthest_pair(&sensor_origins);
 
@@ -925,4 +984,148 @@ mod tests {
         assert!(origins_overlap);
         assert_eq!(distance, 3621);
     }
+
+    #[test]
+    fn alignment_report() {
+        let (_, mut sensors) = full_data(EXAMPLE_SCANNERS).unwrap();
+
+        for sensor in sensors.iter_mut().skip(1) {
+            sensor.compute_rotations();
+        }
+
+        let report = reconstruct_beacon_map(&sensors).unwrap();
+
+        assert_eq!(report.beacons.len(), 79);
+        assert_eq!(report.merge_order().len(), 5);
+        assert_eq!(report.merge_order()[0], 0);
+        assert!(report.merge_order().iter().all_unique());
+
+        let ref_positions = [
+            (0, Point3::new(0, 0, 0)),
+            (1, Point3::new(68, -1246, -43)),
+            (2, Point3::new(1105, -1205, 1229)),
+            (3, Point3::new(-92, -2380, -20)),
+            (4, Point3::new(-20, -1133, 1061)),
+        ];
+
+        for (scanner_id, position) in ref_positions {
+            let placement = report
+                .scanners
+                .iter()
+                .find(|s| s.scanner_id == scanner_id)
+                .unwrap();
+            assert_eq!(placement.position, position);
+
+            // Every beacon seen by the scanner has to end up in the map once rotated and shifted
+            for beacon in sensors[scanner_id].beacons.iter() {
+                let absolute = placement.rotation * beacon + placement.position.coords;
+                assert!(report.beacons.contains(&absolute));
+            }
+        }
+    }
+
+    #[test]
+    fn report_export() {
+        let report = AlignmentReport {
+            scanners: vec![
+                ScannerPlacement {
+                    scanner_id: 0,
+                    position: Point3::origin(),
+                    rotation: Matrix3::identity(),
+                },
+                ScannerPlacement {
+                    scanner_id: 1,
+                    position: Point3::new(6, 3, 0),
+                    rotation: rotation_matrix(18),
+                },
+            ],
+            beacons: vec![Point3::new(0, 2, 0), Point3::new(4, 1, 0)],
+        };
+
+        let mut beacons_csv: Vec<u8> = Vec::new();
+        report.write_beacons_csv(&mut beacons_csv).unwrap();
+        assert_eq!(
+            String::from_utf8(beacons_csv).unwrap(),
+            "x,y,z\n0,2,0\n4,1,0\n"
+        );
+
+        let mut scanners_csv: Vec<u8> = Vec::new();
+        report.write_scanners_csv(&mut scanners_csv).unwrap();
+        let scanners_csv = String::from_utf8(scanners_csv).unwrap();
+        let scanner_lines: Vec<&str> = scanners_csv.lines().collect();
+        assert_eq!(scanner_lines.len(), 3);
+        assert_eq!(scanner_lines[1], "0,0,0,0,0,1,0,0,0,1,0,0,0,1");
+        assert_eq!(scanner_lines[2], "1,1,6,3,0,0,0,1,-1,0,0,0,-1,0");
+
+        let mut ply: Vec<u8> = Vec::new();
+        report.write_ply(&mut ply).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply.starts_with("ply\nformat ascii 1.0\n"));
+        assert!(ply.contains("element vertex 4\n"));
+        let (_, vertices) = ply.split_once("end_header\n").unwrap();
+        assert_eq!(
+            vertices.lines().collect::<Vec<_>>(),
+            vec![
+                "0 2 0 255 255 255",
+                "4 1 0 255 255 255",
+                "0 0 0 255 0 0",
+                "6 3 0 255 0 0"
+            ]
+        );
+    }
+
+    #[test]
+    fn fingerprint_invariance() {
+        let beacons = vec![
+            Point3::new(0, 2, 0),
+            Point3::new(4, 1, 0),
+            Point3::new(3, 3, 0),
+            Point3::new(-1, 5, 7),
+        ];
+        let fingerprint = distance_fingerprint(&beacons);
+        assert_eq!(fingerprint.len(), 6);
+
+        let rotation = rotation_matrix(13);
+        let translation = Vector3::new(-20, 1133, 61);
+        let moved: Vec<Point3<i64>> = beacons
+            .iter()
+            .map(|beacon| rotation * beacon + translation)
+            .collect();
+
+        assert_eq!(distance_fingerprint(&moved), fingerprint);
+        assert_eq!(
+            shared_distances(&fingerprint, &distance_fingerprint(&moved)),
+            6
+        );
+        assert_eq!(shared_distances(&[1, 1, 2, 5], &[1, 2, 2, 5, 7]), 3);
+    }
+
+    #[test]
+    fn candidate_pairs_filter() {
+        let (_, sensors) = full_data(EXAMPLE_SCANNERS).unwrap();
+
+        let fingerprints: Vec<Vec<i64>> = sensors
+            .iter()
+            .map(|sensor| distance_fingerprint(&sensor.beacons))
+            .collect();
+        let candidates = find_candidate_pairs(&fingerprints);
+
+        for pair in [(0, 1), (1, 3), (1, 4), (2, 4)] {
+            assert!(candidates.contains(&pair));
+        }
+        assert!(candidates.len() < 10);
+    }
+
+    #[test]
+    fn disconnected_scanner() {
+        let (_, mut sensors) = full_data(EXAMPLE_SCANNERS).unwrap();
+        let (_, unrelated) = sensor_data("1,2,3\n4,5,6\n7,8,10").unwrap();
+        sensors.push(unrelated);
+
+        for sensor in sensors.iter_mut().skip(1) {
+            sensor.compute_rotations();
+        }
+
+        assert!(reconstruct_beacon_map(&sensors).is_err());
+    }
 }
@@ -1,12 +1,23 @@
-use std::convert::TryFrom;
 use std::convert::TryInto;
+use std::env::temp_dir;
 use std::fmt;
-use std::vec;
+use std::fs::File;
+use std::io::{BufWriter, Error, Write};
+use std::path::Path;
+use std::str::FromStr;
 
 use itertools::Itertools;
 
 use rayon::prelude::*;
 
+use crate::aoc_lib::params::{DayParams, Param};
+
+// Step whose picture is saved to the temp directory, none by default
+const DUMP_STEP: Param<usize> = Param::new("dump", 0);
+const DUMP_FORMAT: Param<ImageFormat> = Param::new("format", ImageFormat::Pgm);
+
+pub const PARAMS: DayParams = &[&DUMP_STEP, &DUMP_FORMAT];
+
 struct ImageEnhancer {
     lut: [bool; 512],
 }
@@ -24,105 +35,61 @@ impl ImageEnhancer {
     }
 
     pub fn enhance_picture(&self, picture: &SensorImage) -> SensorImage {
-        let enhanced_rows = picture.rows + 2;
-        let enhanced_cols = picture.cols + 2;
-
-        let padding_data = if picture.padding_value {
-            self.lut.last().unwrap()
-        } else {
-            self.lut.first().unwrap()
-        };
-
-        let coords: Vec<(usize, usize)> = (0..enhanced_rows)
-            .cartesian_product(0..enhanced_cols)
-            .collect();
-
-        let data: Vec<bool> = coords
-            .par_iter()
-            .map(|(y, x)| {
-                let matrix_data = picture.get_matrix(*x, *y);
-                self.compute_pixel_value(&matrix_data)
+        // Pixels further than one step from the stored window only see the background, so the
+        // window has to grow by exactly one pixel per side at every step
+        let rows = picture.rows + 2;
+        let cols = picture.cols + 2;
+        let origin = (picture.origin.0 - 1, picture.origin.1 - 1);
+
+        let data: Vec<bool> = (0..rows)
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                (0..cols).map(move |x| {
+                    let matrix_data = picture.get_matrix(origin.0 + x as i64, origin.1 + y as i64);
+                    self.compute_pixel_value(&matrix_data)
+                })
             })
             .collect();
 
-        SensorImage::from_vec_dimensions(&data, enhanced_rows, enhanced_cols, *padding_data)
-    }
-
-    pub fn batch_enhance(&self, picture: &SensorImage, steps: usize) -> SensorImage {
-        let enhanced_rows = picture.rows + (steps + 1) * 2;
-        let enhanced_cols = picture.cols + (steps + 1) * 2;
-
-        let mut data_flip: Vec<bool> = vec![picture.padding_value; enhanced_rows * enhanced_cols];
+        let background = if picture.background {
+            *self.lut.last().unwrap()
+        } else {
+            *self.lut.first().unwrap()
+        };
 
-        for (row, chunk) in picture
-            .data
-            .iter()
-            .chunks(picture.cols + 4)
-            .into_iter()
-            .enumerate()
-        {
-            let target_row_offset = (steps + row - 1) * enhanced_cols + steps - 1;
-
-            for (col, bit) in chunk.enumerate() {
-                data_flip[target_row_offset + col] = *bit;
-            }
+        SensorImage {
+            rows,
+            cols,
+            origin,
+            data,
+            background,
         }
+    }
 
-        let mut data_flop: Vec<bool> = data_flip.clone();
-
-        let offset_matrix = SensorImage::compute_offsets(i32::try_from(enhanced_cols).unwrap());
-        let coords: Vec<(usize, usize)> = (0..enhanced_rows)
-            .cartesian_product(0..enhanced_cols)
-            .collect();
-
-        for iteration in 0..steps {
-            let (input, output) = if iteration % 2 == 0 {
-                (&data_flip, &mut data_flop)
-            } else {
-                (&data_flop, &mut data_flip)
-            };
-
-            *output = coords
-                .par_iter()
-                .map(|(y, x)| {
-                    let centre_id = (y * enhanced_cols) + x;
-                    if *y == 0 || *y >= enhanced_rows - 1 || *x == 0 || *x >= enhanced_cols - 1 {
-                        if input[centre_id] {
-                            *self.lut.last().unwrap()
-                        } else {
-                            *self.lut.first().unwrap()
-                        }
-                    } else {
-                        let matrix_data = ImageEnhancer::get_matrix(
-                            input,
-                            i32::try_from(centre_id).unwrap(),
-                            &offset_matrix,
-                        );
-                        self.compute_pixel_value(&matrix_data)
-                    }
-                })
-                .collect();
+    /// Endless sequence of the pictures obtained after each enhancement step
+    pub fn enhancement_steps<'a>(&'a self, picture: &SensorImage) -> EnhancementSteps<'a> {
+        EnhancementSteps {
+            enhancer: self,
+            current: picture.clone(),
         }
+    }
 
-        let data = if (steps - 1) % 2 == 0 {
-            &data_flop
-        } else {
-            &data_flip
-        };
-
-        let cropped_output: Vec<bool> = (1..enhanced_rows - 1)
-            .cartesian_product(1..enhanced_cols - 1)
-            .into_iter()
-            .map(|(y, x)| (y * enhanced_cols) + x)
-            .map(|id| data[id])
-            .collect();
+    pub fn batch_enhance(&self, picture: &SensorImage, steps: usize) -> SensorImage {
+        self.enhancement_steps(picture)
+            .take(steps)
+            .last()
+            .unwrap_or_else(|| picture.clone())
+    }
 
-        SensorImage::from_vec_dimensions(
-            &cropped_output,
-            enhanced_rows - 2,
-            enhanced_cols - 2,
-            data[0],
-        )
+    /// Saves the picture obtained after the given number of enhancement steps
+    pub fn dump_step(
+        &self,
+        picture: &SensorImage,
+        step: usize,
+        path: &Path,
+        format: ImageFormat,
+    ) -> Result<(), Error> {
+        self.batch_enhance(picture, step).save(path, format, 2)
     }
 
     fn compute_pixel_value(&self, pixels: &[bool; 9]) -> bool {
@@ -132,15 +99,6 @@ impl ImageEnhancer {
 
         self.lut[index]
     }
-
-    fn get_matrix(data: &[bool], centre_id: i32, offsets: &[i32; 9]) -> [bool; 9] {
-        offsets
-            .iter()
-            .map(|offset| data[usize::try_from(centre_id + *offset).unwrap()])
-            .collect::<Vec<bool>>()
-            .try_into()
-            .unwrap()
-    }
 }
 
 impl fmt::Display for ImageEnhancer {
@@ -161,13 +119,56 @@ impl fmt::Display for ImageEnhancer {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+struct EnhancementSteps<'a> {
+    enhancer: &'a ImageEnhancer,
+    current: SensorImage,
+}
+
+impl Iterator for EnhancementSteps<'_> {
+    type Item = SensorImage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.current = self.enhancer.enhance_picture(&self.current);
+        Some(self.current.clone())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Pbm,
+    Pgm,
+}
+
+impl ImageFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Pbm => "pbm",
+            ImageFormat::Pgm => "pgm",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pbm" => Ok(ImageFormat::Pbm),
+            "pgm" => Ok(ImageFormat::Pgm),
+            _ => Err(format!("unknown image format '{}', expected pbm or pgm", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct SensorImage {
     rows: usize,
     cols: usize,
+    // Canvas coordinates (x, y) of the top-left pixel of the stored window
+    origin: (i64, i64),
     data: Vec<bool>,
-    kernel_ids: [i32; 9],
-    padding_value: bool,
+    // Value of every pixel outside of the stored window, all the way to infinity
+    background: bool,
 }
 
 impl SensorImage {
@@ -175,104 +176,102 @@ impl SensorImage {
         let rows = raw_data.lines().count();
         let cols = raw_data.lines().next().unwrap().chars().count();
 
-        let row_padding = vec![false; (rows + 4) * 2];
-        let mut data: Vec<bool> = row_padding.clone();
-        for data_line in raw_data.lines() {
-            data.extend([false, false]);
-            data.extend(data_line.chars().map(|c| c == '#'));
-            data.extend([false, false]);
-        }
-        data.extend(row_padding.into_iter());
-
         Self {
             rows,
             cols,
-            data,
-            kernel_ids: Self::compute_offsets(i32::try_from(cols + 4).unwrap()),
-            padding_value: false,
+            origin: (0, 0),
+            data: raw_data
+                .lines()
+                .flat_map(|line| line.chars().map(|c| c == '#'))
+                .collect(),
+            background: false,
         }
     }
 
-    fn from_vec_dimensions(
-        raw_data: &[bool],
-        rows: usize,
-        cols: usize,
-        padding_value: bool,
-    ) -> Self {
-        let row_padding = vec![padding_value; (rows + 4) * 2];
-        let mut data: Vec<bool> = row_padding.clone();
-        for row in 0..rows {
-            data.extend([padding_value, padding_value]);
-            data.extend(raw_data.iter().skip(row * cols).take(cols));
-            data.extend([padding_value, padding_value]);
-        }
-        data.extend(row_padding.into_iter());
+    pub fn get_pixel(&self, x: i64, y: i64) -> bool {
+        let col = x - self.origin.0;
+        let row = y - self.origin.1;
 
-        Self {
-            rows,
-            cols,
-            data,
-            kernel_ids: Self::compute_offsets(i32::try_from(cols + 4).unwrap()),
-            padding_value,
+        if col < 0 || row < 0 || col >= self.cols as i64 || row >= self.rows as i64 {
+            self.background
+        } else {
+            self.data[row as usize * self.cols + col as usize]
         }
     }
 
-    fn compute_offsets(padding: i32) -> [i32; 9] {
-        (-1..=1)
-            .cartesian_product(-1..=1)
-            .map(|(y, x)| y * padding + x)
-            .collect::<Vec<i32>>()
-            .try_into()
-            .unwrap()
-    }
-
-    // This function gets a row-major pixel matrix centred on the given location
-    // The location is based on the "extended" picture as the first usable pixel
-    // of the picture influences a matrix centred in [-1, -1]
-    pub fn get_matrix(&self, x: usize, y: usize) -> [bool; 9] {
-        assert!(x <= self.cols + 1 && y <= self.rows + 1);
-
-        let centre_id = i32::try_from((y + 1) * (self.cols + 4) + x + 1).unwrap();
-
-        self.kernel_ids
-            .iter()
-            .map(|offset| self.data[usize::try_from(centre_id + *offset).unwrap()])
-            .collect::<Vec<bool>>()
-            .try_into()
-            .unwrap()
+    // Row-major pixel matrix centred on the given canvas location
+    pub fn get_matrix(&self, x: i64, y: i64) -> [bool; 9] {
+        std::array::from_fn(|idx| {
+            let x_offset = (idx % 3) as i64 - 1;
+            let y_offset = (idx / 3) as i64 - 1;
+            self.get_pixel(x + x_offset, y + y_offset)
+        })
     }
 
-    // If we have a "lit" padding, that means that we will have INFINITE lit pixels
+    // If we have a "lit" background, that means that we will have INFINITE lit pixels
     pub fn get_lit_pixels(&self) -> Option<usize> {
-        if !self.padding_value {
+        if !self.background {
             Some(self.data.iter().filter(|pixel| **pixel).count())
         } else {
             None
         }
     }
+
+    // Pixels of the stored window surrounded by `border` pixels of background on each side
+    fn bordered_rows(&self, border: usize) -> impl Iterator<Item = Vec<bool>> + '_ {
+        let border = border as i64;
+        let (left, top) = (self.origin.0 - border, self.origin.1 - border);
+        let width = self.cols as i64 + border * 2;
+        let height = self.rows as i64 + border * 2;
+
+        (top..top + height)
+            .map(move |y| (left..left + width).map(|x| self.get_pixel(x, y)).collect())
+    }
+
+    /// Writes the picture as a plain PBM (lit pixels are black) or PGM (lit pixels are white)
+    pub fn write_image<W: Write>(
+        &self,
+        output: &mut W,
+        format: ImageFormat,
+        border: usize,
+    ) -> Result<(), Error> {
+        let width = self.cols + border * 2;
+        let height = self.rows + border * 2;
+
+        match format {
+            ImageFormat::Pbm => writeln!(output, "P1\n{} {}", width, height)?,
+            ImageFormat::Pgm => writeln!(output, "P2\n{} {}\n255", width, height)?,
+        }
+
+        for row in self.bordered_rows(border) {
+            let line = row
+                .into_iter()
+                .map(|lit| match (format, lit) {
+                    (ImageFormat::Pbm, true) => "1",
+                    (ImageFormat::Pbm, false) => "0",
+                    (ImageFormat::Pgm, true) => "255",
+                    (ImageFormat::Pgm, false) => "0",
+                })
+                .join(" ");
+            writeln!(output, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path, format: ImageFormat, border: usize) -> Result<(), Error> {
+        let mut output = BufWriter::new(File::create(path)?);
+        self.write_image(&mut output, format, border)
+    }
 }
 
 impl fmt::Display for SensorImage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Image dimensions: [{}; {}]", self.rows, self.cols)?;
-        writeln!(
-            f,
-            "Padding data: {}",
-            if self.padding_value { '#' } else { '.' }
-        )?;
-        for pixels in self
-            .data
-            .iter()
-            .chunks(self.cols + 4)
-            .into_iter()
-            .skip(2)
-            .take(self.rows)
-        {
-            let row_data: String = pixels
-                .skip(2)
-                .take(self.cols)
-                .map(|p| if *p { '#' } else { '.' })
-                .collect();
+        writeln!(f, "Image origin: [{}; {}]", self.origin.0, self.origin.1)?;
+        writeln!(f, "Background: {}", if self.background { '#' } else { '.' })?;
+        for pixels in self.data.chunks(self.cols) {
+            let row_data: String = pixels.iter().map(|p| if *p { '#' } else { '.' }).collect();
             writeln!(f, "{}", row_data)?;
         }
         writeln!(f)
@@ -310,25 +309,38 @@ pub fn part1(input: &str) {
 pub fn part2(input: &str) {
     let (enhancer, picture) = parse_input(input);
 
-    /*
-    let mut final_image = picture;
-    for _ in 0..50 {
-        final_image = enhancer.enhance_picture(&final_image);
-    }
-    */
-
     let final_image = enhancer.batch_enhance(&picture, 50);
 
     println!(
         "The enhanced picture has {:?} lit pixels",
         final_image.get_lit_pixels()
     );
+
+    let step = DUMP_STEP.get();
+    if step == 0 {
+        return;
+    }
+
+    let format = DUMP_FORMAT.get();
+    let image_path = temp_dir().join(format!("day20_step{}.{}", step, format.extension()));
+    match enhancer.dump_step(&picture, step, &image_path, format) {
+        Ok(()) => println!("Picture at step {} saved to {}", step, image_path.display()),
+        Err(e) => println!("Failed to save the picture at step {}: {}", step, e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn pixels(raw_data: &str) -> Vec<bool> {
+        raw_data
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c == '#')
+            .collect()
+    }
+
     #[test]
     fn input_parsing() {
         let input_string =
@@ -349,19 +361,17 @@ mod tests {
         let (enhancer, picture) = parse_input(input_string);
 
         let ref_picture = SensorImage {
-            data: vec![
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, true, false, false, true,
-                false, false, false, false, false, true, false, false, false, false, false, false,
-                false, false, true, true, false, false, true, false, false, false, false, false,
-                false, true, false, false, false, false, false, false, false, false, true, true,
-                true, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false,
-            ],
             rows: 5,
             cols: 5,
-            kernel_ids: [-10, -9, -8, -1, 0, 1, 8, 9, 10],
-            padding_value: false,
+            origin: (0, 0),
+            data: pixels(
+                "#..#.
+                #....
+                ##..#
+                ..#..
+                ..###",
+            ),
+            background: false,
         };
 
         assert_eq!(enhancer.lut.len(), 512);
@@ -392,21 +402,17 @@ mod tests {
         let first_pass_ref = SensorImage {
             rows: 7,
             cols: 7,
-            data: vec![
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, true, true, false, true, true, false, false, false, false, false, true,
-                false, false, true, false, true, false, false, false, false, false, true, true,
-                false, true, false, false, true, false, false, false, false, true, true, true,
-                true, false, false, true, false, false, false, false, false, true, false, false,
-                true, true, false, false, false, false, false, false, false, true, true, false,
-                false, true, false, false, false, false, false, false, false, true, false, true,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false,
-            ],
-            kernel_ids: [-12, -11, -10, -1, 0, 1, 10, 11, 12],
-            padding_value: false,
+            origin: (-1, -1),
+            data: pixels(
+                ".##.##.
+                #..#.#.
+                ##.#..#
+                ####..#
+                .#..##.
+                ..##..#
+                ...#.#.",
+            ),
+            background: false,
         };
 
         assert_eq!(first_pass, first_pass_ref);
@@ -436,21 +442,17 @@ mod tests {
         let first_pass_ref = SensorImage {
             rows: 7,
             cols: 7,
-            data: vec![
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, true, true, false, true, true, false, false, false, false, false, true,
-                false, false, true, false, true, false, false, false, false, false, true, true,
-                false, true, false, false, true, false, false, false, false, true, true, true,
-                true, false, false, true, false, false, false, false, false, true, false, false,
-                true, true, false, false, false, false, false, false, false, true, true, false,
-                false, true, false, false, false, false, false, false, false, true, false, true,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false,
-            ],
-            kernel_ids: [-12, -11, -10, -1, 0, 1, 10, 11, 12],
-            padding_value: false,
+            origin: (-1, -1),
+            data: pixels(
+                ".##.##.
+                #..#.#.
+                ##.#..#
+                ####..#
+                .#..##.
+                ..##..#
+                ...#.#.",
+            ),
+            background: false,
         };
 
         assert_eq!(first_pass, first_pass_ref);
@@ -481,25 +483,19 @@ mod tests {
         let second_pass_ref = SensorImage {
             rows: 9,
             cols: 9,
-            data: vec![
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, true,
-                false, false, false, false, false, false, true, false, false, true, false, true,
-                false, false, false, false, false, false, true, false, true, false, false, false,
-                true, true, true, false, false, false, false, true, false, false, false, true,
-                true, false, true, false, false, false, false, false, true, false, false, false,
-                false, false, true, false, true, false, false, false, false, false, true, false,
-                true, true, true, true, true, false, false, false, false, false, false, false,
-                true, false, true, true, true, true, true, false, false, false, false, false,
-                false, false, true, true, false, true, true, false, false, false, false, false,
-                false, false, false, false, true, true, true, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false,
-            ],
-            kernel_ids: [-14, -13, -12, -1, 0, 1, 12, 13, 14],
-            padding_value: false,
+            origin: (-2, -2),
+            data: pixels(
+                ".......#.
+                .#..#.#..
+                #.#...###
+                #...##.#.
+                #.....#.#
+                .#.#####.
+                ..#.#####
+                ...##.##.
+                ....###..",
+            ),
+            background: false,
         };
 
         assert_eq!(second_pass, second_pass_ref);
@@ -530,25 +526,19 @@ mod tests {
         let second_pass_ref = SensorImage {
             rows: 9,
             cols: 9,
-            data: vec![
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, true,
-                false, false, false, false, false, false, true, false, false, true, false, true,
-                false, false, false, false, false, false, true, false, true, false, false, false,
-                true, true, true, false, false, false, false, true, false, false, false, true,
-                true, false, true, false, false, false, false, false, true, false, false, false,
-                false, false, true, false, true, false, false, false, false, false, true, false,
-                true, true, true, true, true, false, false, false, false, false, false, false,
-                true, false, true, true, true, true, true, false, false, false, false, false,
-                false, false, true, true, false, true, true, false, false, false, false, false,
-                false, false, false, false, true, true, true, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false,
-            ],
-            kernel_ids: [-14, -13, -12, -1, 0, 1, 12, 13, 14],
-            padding_value: false,
+            origin: (-2, -2),
+            data: pixels(
+                ".......#.
+                .#..#.#..
+                #.#...###
+                #...##.#.
+                #.....#.#
+                .#.#####.
+                ..#.#####
+                ...##.##.
+                ....###..",
+            ),
+            background: false,
         };
 
         assert_eq!(second_pass, second_pass_ref);
@@ -632,25 +622,19 @@ mod tests {
         let second_pass_ref = SensorImage {
             rows: 9,
             cols: 9,
-            data: vec![
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, true, false,
-                false, false, false, false, false, false, true, true, true, false, false, false,
-                false, false, false, false, false, false, false, false, true, false, false, true,
-                true, true, false, false, false, false, false, false, false, false, false, false,
-                false, false, true, false, false, false, false, false, false, false, true, false,
-                false, true, true, true, false, true, false, false, false, false, true, false,
-                false, true, true, true, true, true, false, false, false, false, false, false,
-                false, false, true, false, false, false, false, true, false, false, false, false,
-                false, false, true, false, false, false, false, false, true, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false,
-            ],
-            kernel_ids: [-14, -13, -12, -1, 0, 1, 12, 13, 14],
-            padding_value: false,
+            origin: (-2, -2),
+            data: pixels(
+                ".........
+                .....#...
+                ###......
+                .#..###..
+                ......#..
+                .#..###.#
+                #..#####.
+                ...#....#
+                ..#.....#",
+            ),
+            background: false,
         };
 
         assert_eq!(second_pass, second_pass_ref);
@@ -682,23 +666,19 @@ mod tests {
         let second_pass_ref = SensorImage {
             rows: 9,
             cols: 9,
-            data: vec![
-                true, true, true, true, true, true, true, true, true, true, true, true, true, true,
-                true, true, true, true, true, true, true, true, true, true, true, true, true, true,
-                false, false, false, false, false, false, true, true, true, true, true, true, true,
-                false, false, false, false, false, true, false, true, true, true, true, true, true,
-                true, true, true, false, false, false, false, false, true, true, true, true, true,
-                true, true, false, false, true, true, true, false, true, true, true, true, true,
-                false, false, false, false, false, false, true, false, false, true, true, true,
-                true, false, true, false, false, true, true, true, false, true, true, true, true,
-                true, true, false, false, true, true, true, true, true, false, true, true, true,
-                true, true, true, false, true, false, false, false, false, true, true, true, true,
-                true, true, true, true, false, false, false, false, false, true, true, true, true,
-                true, true, true, true, true, true, true, true, true, true, true, true, true, true,
-                true, true, true, true, true, true, true, true, true, true, true,
-            ],
-            kernel_ids: [-14, -13, -12, -1, 0, 1, 12, 13, 14],
-            padding_value: true,
+            origin: (-2, -2),
+            data: pixels(
+                "......###
+                .....#.##
+                ###.....#
+                ##..###.#
+                ......#..
+                .#..###.#
+                #..#####.
+                ##.#....#
+                ###.....#",
+            ),
+            background: true,
         };
 
         assert_eq!(second_pass, second_pass_ref);
@@ -729,25 +709,19 @@ mod tests {
         let second_pass_ref = SensorImage {
             rows: 9,
             cols: 9,
-            data: vec![
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, true, false,
-                false, false, false, false, false, false, true, true, true, false, false, false,
-                false, false, false, false, false, false, false, false, true, false, false, true,
-                true, true, false, false, false, false, false, false, false, false, false, false,
-                false, false, true, false, false, false, false, false, false, false, true, false,
-                false, true, true, true, false, true, false, false, false, false, true, false,
-                false, true, true, true, true, true, false, false, false, false, false, false,
-                false, false, true, false, false, false, false, true, false, false, false, false,
-                false, false, true, false, false, false, false, false, true, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false, false, false, false, false, false, false, false, false, false, false, false,
-                false,
-            ],
-            kernel_ids: [-14, -13, -12, -1, 0, 1, 12, 13, 14],
-            padding_value: false,
+            origin: (-2, -2),
+            data: pixels(
+                ".........
+                .....#...
+                ###......
+                .#..###..
+                ......#..
+                .#..###.#
+                #..#####.
+                ...#....#
+                ..#.....#",
+            ),
+            background: false,
         };
 
         assert_eq!(second_pass, second_pass_ref);
@@ -778,26 +752,108 @@ mod tests {
         let second_pass_ref = SensorImage {
             rows: 9,
             cols: 9,
-            data: vec![
-                true, true, true, true, true, true, true, true, true, true, true, true, true, true,
-                true, true, true, true, true, true, true, true, true, true, true, true, true, true,
-                false, false, false, false, false, false, true, true, true, true, true, true, true,
-                false, false, false, false, false, true, false, true, true, true, true, true, true,
-                true, true, true, false, false, false, false, false, true, true, true, true, true,
-                true, true, false, false, true, true, true, false, true, true, true, true, true,
-                false, false, false, false, false, false, true, false, false, true, true, true,
-                true, false, true, false, false, true, true, true, false, true, true, true, true,
-                true, true, false, false, true, true, true, true, true, false, true, true, true,
-                true, true, true, false, true, false, false, false, false, true, true, true, true,
-                true, true, true, true, false, false, false, false, false, true, true, true, true,
-                true, true, true, true, true, true, true, true, true, true, true, true, true, true,
-                true, true, true, true, true, true, true, true, true, true, true,
-            ],
-            kernel_ids: [-14, -13, -12, -1, 0, 1, 12, 13, 14],
-            padding_value: true,
+            origin: (-2, -2),
+            data: pixels(
+                "......###
+                .....#.##
+                ###.....#
+                ##..###.#
+                ......#..
+                .#..###.#
+                #..#####.
+                ##.#....#
+                ###.....#",
+            ),
+            background: true,
         };
 
         assert_eq!(second_pass, second_pass_ref);
         assert!(second_pass.get_lit_pixels().is_none());
     }
+
+    #[test]
+    fn arbitrary_step_count() {
+        let input_string =
+            "#.#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..##\
+            #..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###\
+            .######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#.\
+            .#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#.....\
+            .#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#..\
+            ...####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.....\
+            ..##..####..#...#.#.#...##..#.#..###..#####........#..####......#...
+
+            #..#.
+            #....
+            ##..#
+            ..#..
+            ..###";
+
+        let (enhancer, picture) = parse_input(input_string);
+
+        let steps: Vec<SensorImage> = enhancer.enhancement_steps(&picture).take(5).collect();
+
+        for (step, image) in steps.iter().enumerate() {
+            assert_eq!(image.rows, 5 + (step + 1) * 2);
+            assert_eq!(image.background, step % 2 == 0);
+            assert_eq!(image.get_lit_pixels().is_none(), step % 2 == 0);
+        }
+        assert_eq!(steps[4], enhancer.batch_enhance(&picture, 5));
+        assert_eq!(enhancer.batch_enhance(&picture, 0), picture);
+
+        // Far away pixels just follow the background
+        assert!(steps[0].get_pixel(-1000, 1000));
+        assert!(!steps[1].get_pixel(1000, -1000));
+    }
+
+    #[test]
+    fn image_export() {
+        let picture = SensorImage::new("#.\n.#\n");
+
+        let mut pbm: Vec<u8> = Vec::new();
+        picture.write_image(&mut pbm, ImageFormat::Pbm, 0).unwrap();
+        assert_eq!(String::from_utf8(pbm).unwrap(), "P1\n2 2\n1 0\n0 1\n");
+
+        let mut pgm: Vec<u8> = Vec::new();
+        picture.write_image(&mut pgm, ImageFormat::Pgm, 1).unwrap();
+        assert_eq!(
+            String::from_utf8(pgm).unwrap(),
+            "P2\n4 4\n255\n0 0 0 0\n0 255 0 0\n0 0 255 0\n0 0 0 0\n"
+        );
+    }
+
+    #[test]
+    fn step_dump() {
+        let (enhancer, picture) = parse_input(
+            "..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..##\
+            #..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###\
+            .######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#.\
+            .#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#.....\
+            .#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#..\
+            ...####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.....\
+            ..##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#
+
+            #..#.
+            #....
+            ##..#
+            ..#..
+            ..###",
+        );
+
+        let path = temp_dir().join("day20_dump_step_test.pbm");
+        enhancer
+            .dump_step(&picture, 2, &path, ImageFormat::Pbm)
+            .unwrap();
+        let dumped = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut expected: Vec<u8> = Vec::new();
+        enhancer
+            .batch_enhance(&picture, 2)
+            .write_image(&mut expected, ImageFormat::Pbm, 2)
+            .unwrap();
+        assert_eq!(dumped, String::from_utf8(expected).unwrap());
+
+        assert_eq!("pgm".parse(), Ok(ImageFormat::Pgm));
+        assert!("png".parse::<ImageFormat>().is_err());
+    }
 }
@@ -1,27 +1,114 @@
-use std::convert::{TryFrom, TryInto};
-
 use hashbrown::HashMap;
+use itertools::Itertools;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameRules {
+    pub board_size: u32,
+    pub die_sides: u32,
+    pub rolls_per_turn: u32,
+    pub win_score: u32,
+}
+
+impl GameRules {
+    pub fn new(
+        board_size: u32,
+        die_sides: u32,
+        rolls_per_turn: u32,
+        win_score: u32,
+    ) -> Result<Self, String> {
+        let rules = Self {
+            board_size,
+            die_sides,
+            rolls_per_turn,
+            win_score,
+        };
+        rules.validate()?;
+
+        Ok(rules)
+    }
 
-const FIRST_PLAYER_CYCLE: [u8; 5] = [6, 4, 2, 0, 8];
-const SECOND_PLAYER_CYCLE: [u8; 5] = [5, 3, 1, 9, 7];
+    pub fn deterministic() -> Self {
+        Self::new(10, 100, 3, 1000).unwrap()
+    }
 
-const ALL_ROLLS: [u8; 27] = [
-    3, 4, 5, 4, 5, 6, 5, 6, 7, 4, 5, 6, 5, 6, 7, 6, 7, 8, 5, 6, 7, 6, 7, 8, 7, 8, 9,
-];
+    pub fn dirac() -> Self {
+        Self::new(10, 3, 3, 21).unwrap()
+    }
+
+    // Rules can also be put together field by field, so games check them again before starting
+    pub fn validate(&self) -> Result<(), String> {
+        if self.board_size == 0 {
+            return Err(String::from("The board needs at least one square"));
+        }
+        if self.die_sides == 0 {
+            return Err(String::from("The die needs at least one side"));
+        }
+        if self.rolls_per_turn == 0 {
+            return Err(String::from("Players have to roll at least once per turn"));
+        }
+        if self.win_score == 0 {
+            return Err(String::from("The winning score has to be above zero"));
+        }
+        if self
+            .die_sides
+            .checked_mul(self.rolls_per_turn)
+            .and_then(|squares| squares.checked_add(self.board_size))
+            .and_then(|position| position.checked_add(self.win_score))
+            .is_none()
+        {
+            return Err(String::from("The scores could overflow with these rules"));
+        }
+
+        Ok(())
+    }
+
+    fn check_positions(&self, starting_positions: &[u32]) -> Result<(), String> {
+        if starting_positions.is_empty() {
+            return Err(String::from("A game without players cannot be played"));
+        }
+        match starting_positions
+            .iter()
+            .find(|&&position| position == 0 || position > self.board_size)
+        {
+            Some(position) => Err(format!(
+                "Starting position {} is not on a board of {} squares",
+                position, self.board_size
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // Number of universes in which each total is obtained with all the rolls of a turn
+    fn roll_frequencies(&self) -> Vec<(u32, u64)> {
+        let mut frequencies: HashMap<u32, u64> = HashMap::from([(0, 1)]);
+
+        for _ in 0..self.rolls_per_turn {
+            let mut next_frequencies: HashMap<u32, u64> = HashMap::new();
+            for (total, universes) in frequencies.iter() {
+                for side in 1..=self.die_sides {
+                    *next_frequencies.entry(total + side).or_default() += universes;
+                }
+            }
+            frequencies = next_frequencies;
+        }
+
+        frequencies.into_iter().sorted().collect()
+    }
+}
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 struct Player {
-    position: u8,
-    score: u8,
+    position: u32,
+    score: u32,
 }
 
 impl Player {
-    fn new(position: u8) -> Self {
+    fn new(position: u32) -> Self {
         Self { position, score: 0 }
     }
 
-    pub fn advance(&self, squares: u8) -> Self {
-        let new_position = (self.position + squares - 1) % 10 + 1;
+    pub fn advance(&self, squares: u32, board_size: u32) -> Self {
+        let new_position = (self.position + squares - 1) % board_size + 1;
         Self {
             position: new_position,
             score: self.score + new_position,
@@ -29,214 +116,187 @@ impl Player {
     }
 }
 
-fn get_positions(input: &str) -> (u8, u8) {
-    let starting_positions: Vec<u8> = input
-        .lines()
-        .map(|line| {
-            line.chars()
-                .last()
-                .unwrap()
-                .to_digit(10)
-                .unwrap()
-                .try_into()
-                .unwrap()
-        })
-        .collect();
-    (starting_positions[0], starting_positions[1])
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeterministicOutcome {
+    pub scores: Vec<u32>,
+    pub rolls: u32,
+    pub winner: usize,
 }
 
-fn generate_score_loop(start_locations: (u8, u8)) -> (Vec<u8>, Vec<u8>) {
-    let (mut first_location, mut second_location) = (
-        usize::from(start_locations.0 + FIRST_PLAYER_CYCLE[0]),
-        usize::from(start_locations.1 + SECOND_PLAYER_CYCLE[0]),
-    );
-
-    let mut scores: (Vec<u8>, Vec<u8>) = (
-        vec![u8::try_from((first_location - 1) % 10).unwrap() + 1],
-        vec![u8::try_from((second_location - 1) % 10).unwrap() + 1],
-    );
-
-    let mut step_idx: usize = 1;
-
-    while (u8::try_from(first_location % 10).unwrap() != start_locations.0) || step_idx != 0 {
-        first_location += usize::from(FIRST_PLAYER_CYCLE[step_idx]);
-        let score: u8 = (((first_location - 1) % 10) + 1).try_into().unwrap();
-        scores.0.push(score);
-        step_idx = (step_idx + 1) % 5;
+impl DeterministicOutcome {
+    // Lowest score among the players that didn't win times the number of rolls
+    pub fn loser_score(&self) -> u32 {
+        self.scores
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != self.winner)
+            .map(|(_, score)| *score)
+            .min()
+            .unwrap_or_default()
+            * self.rolls
     }
+}
 
-    step_idx = 1;
-
-    while (u8::try_from(second_location % 10).unwrap() != start_locations.1) || step_idx != 0 {
-        second_location += usize::from(SECOND_PLAYER_CYCLE[step_idx]);
-        let score: u8 = (((second_location - 1) % 10) + 1).try_into().unwrap();
-        scores.1.push(score);
-        step_idx = (step_idx + 1) % 5;
+fn play_deterministic(
+    rules: &GameRules,
+    starting_positions: &[u32],
+) -> Result<DeterministicOutcome, String> {
+    rules.validate()?;
+    rules.check_positions(starting_positions)?;
+
+    let mut players: Vec<Player> = starting_positions
+        .iter()
+        .cloned()
+        .map(Player::new)
+        .collect();
+    let mut die = (1..=rules.die_sides).cycle();
+    let mut rolls: u32 = 0;
+
+    for current in (0..players.len()).cycle() {
+        let squares: u32 = die.by_ref().take(rules.rolls_per_turn as usize).sum();
+        rolls += rules.rolls_per_turn;
+
+        players[current] = players[current].advance(squares, rules.board_size);
+        if players[current].score >= rules.win_score {
+            return Ok(DeterministicOutcome {
+                scores: players.iter().map(|p| p.score).collect(),
+                rolls,
+                winner: current,
+            });
+        }
     }
 
-    scores
+    unreachable!("A game without players cannot be played")
 }
 
-fn compute_final_scores(starting_positions: (u8, u8)) -> ((u16, u16), u32, u32) {
-    let (first_score_loop, second_score_loop) = generate_score_loop(starting_positions);
-
-    let first_loop_total: u16 = first_score_loop.iter().cloned().map(u16::from).sum();
-    let second_loop_total: u16 = second_score_loop.iter().cloned().map(u16::from).sum();
-
-    let loops_to_win_first = 1000 / first_loop_total;
+struct DiracGame {
+    rules: GameRules,
+    roll_frequencies: Vec<(u32, u64)>,
+    // Wins for each player, indexed starting from the one in turn
+    results_lut: HashMap<Vec<Player>, Vec<u64>>,
+}
 
-    let loops_to_win_second = 1000 / second_loop_total;
+impl DiracGame {
+    fn new(rules: GameRules) -> Result<Self, String> {
+        rules.validate()?;
 
-    let mut first_score = loops_to_win_first * first_loop_total;
-    let mut extra_rolls_first: u8 = 0;
-    while first_score < 1000 {
-        first_score += u16::from(
-            first_score_loop
-                [usize::from(extra_rolls_first % u8::try_from(first_score_loop.len()).unwrap())],
-        );
-        extra_rolls_first += 1;
+        Ok(Self {
+            rules,
+            roll_frequencies: rules.roll_frequencies(),
+            results_lut: HashMap::new(),
+        })
     }
 
-    let mut second_score = loops_to_win_second * second_loop_total;
-    let mut extra_rolls_second: u8 = 0;
-    while second_score < 1000 {
-        second_score += u16::from(
-            second_score_loop
-                [usize::from(extra_rolls_second % u8::try_from(second_score_loop.len()).unwrap())],
-        );
-        extra_rolls_second += 1;
-    }
+    pub fn count_wins(&mut self, starting_positions: &[u32]) -> Result<Vec<u64>, String> {
+        self.rules.check_positions(starting_positions)?;
 
-    let first_rolls = loops_to_win_first * u16::try_from(first_score_loop.len()).unwrap()
-        + u16::from(extra_rolls_first);
-    let second_rolls = loops_to_win_second * u16::try_from(second_score_loop.len()).unwrap()
-        + u16::from(extra_rolls_second);
-
-    let mut total_rolls: (u16, u16) = (first_rolls, second_rolls);
-
-    if first_rolls <= second_rolls {
-        let loops = first_rolls / u16::try_from(second_score_loop.len()).unwrap();
-        let missing_rolls =
-            first_rolls - (loops * u16::try_from(second_score_loop.len()).unwrap()) - 1;
-
-        let missing_score = (0..missing_rolls).into_iter().fold(0u16, |total, idx| {
-            total
-                + u16::from(
-                    second_score_loop
-                        [usize::from(idx % u16::try_from(second_score_loop.len()).unwrap())],
-                )
-        });
-        second_score = loops * second_loop_total + missing_score;
-        total_rolls.1 = (loops * u16::try_from(second_score_loop.len()).unwrap()) + missing_rolls;
-    } else {
-        let loops = second_rolls / u16::try_from(first_score_loop.len()).unwrap();
-        let missing_rolls = second_rolls - (loops * u16::try_from(first_score_loop.len()).unwrap());
-
-        let missing_score = (0..missing_rolls).into_iter().fold(0u16, |total, idx| {
-            total
-                + u16::from(
-                    first_score_loop
-                        [usize::from(idx % u16::try_from(first_score_loop.len()).unwrap())],
-                )
-        });
-        first_score = loops * first_loop_total + missing_score;
-        total_rolls.0 = (loops * u16::try_from(first_score_loop.len()).unwrap()) + missing_rolls;
+        let players: Vec<Player> = starting_positions
+            .iter()
+            .cloned()
+            .map(Player::new)
+            .collect();
+        Ok(self.rec_dirac_match(&players))
     }
 
-    let total_rolls = (u32::from(total_rolls.0) + u32::from(total_rolls.1)) * 3;
-    let loser_score = if first_score >= second_score {
-        u32::from(second_score) * total_rolls
-    } else {
-        u32::from(first_score) * total_rolls
-    };
+    fn rec_dirac_match(&mut self, players: &[Player]) -> Vec<u64> {
+        if let Some(results) = self.results_lut.get(players) {
+            return results.clone();
+        }
 
-    ((first_score, second_score), total_rolls, loser_score)
-}
+        let player_count = players.len();
+        let mut wins: Vec<u64> = vec![0; player_count];
 
-fn rec_dirac_match(
-    results_lut: &mut HashMap<(Player, Player), (u64, u64)>,
-    in_turn: &Player,
-    next_turn: &Player,
-    target_score: u8,
-) -> (u64, u64) {
-    if let Some(results) = results_lut.get(&(*in_turn, *next_turn)) {
-        return *results;
-    }
+        for (squares, universes) in self.roll_frequencies.clone() {
+            let in_turn_evolution = players[0].advance(squares, self.rules.board_size);
 
-    if in_turn.score >= target_score {
-        return (1, 0);
-    };
-    if next_turn.score >= target_score {
-        return (0, 1);
-    };
+            if in_turn_evolution.score >= self.rules.win_score {
+                wins[0] += universes;
+                continue;
+            }
 
-    let mut in_turn_score: u64 = 0;
-    let mut next_turn_score: u64 = 0;
+            // The player that just moved goes to the back of the queue
+            let next_turn: Vec<Player> = players[1..]
+                .iter()
+                .cloned()
+                .chain([in_turn_evolution])
+                .collect();
 
-    for roll in ALL_ROLLS {
-        let in_turn_evolution = in_turn.advance(roll);
+            for (idx, next_wins) in self.rec_dirac_match(&next_turn).into_iter().enumerate() {
+                wins[(idx + 1) % player_count] += next_wins * universes;
+            }
+        }
 
-        let (next_turn_wins, in_turn_wins) =
-            rec_dirac_match(results_lut, next_turn, &in_turn_evolution, target_score);
+        self.results_lut.insert(players.to_vec(), wins.clone());
 
-        in_turn_score += in_turn_wins;
-        next_turn_score += next_turn_wins;
+        wins
     }
+}
 
-    results_lut.insert((*in_turn, *next_turn), (in_turn_score, next_turn_score));
-
-    (in_turn_score, next_turn_score)
+fn get_positions(input: &str) -> Vec<u32> {
+    input
+        .lines()
+        .map(|line| {
+            line.rsplit(':')
+                .next()
+                .unwrap()
+                .trim()
+                .parse::<u32>()
+                .unwrap()
+        })
+        .collect()
 }
 
 pub fn part1(input: &str) {
     let starting_positions = get_positions(input);
 
-    let (_, _, loser_score) = compute_final_scores(starting_positions);
-
-    println!("The loser scored: {}", loser_score);
+    match play_deterministic(&GameRules::deterministic(), &starting_positions) {
+        Ok(outcome) => println!("The loser scored: {}", outcome.loser_score()),
+        Err(e) => println!("Couldn't play the game: {}", e),
+    }
 }
 
 pub fn part2(input: &str) {
     let starting_positions = get_positions(input);
 
-    let player_one = Player::new(starting_positions.0);
-    let player_two = Player::new(starting_positions.1);
-
-    let mut acceleration_structure: HashMap<(Player, Player), (u64, u64)> = HashMap::new();
+    let universes = DiracGame::new(GameRules::dirac())
+        .and_then(|mut game| game.count_wins(&starting_positions));
 
-    let (first_universes, second_universes) =
-        rec_dirac_match(&mut acceleration_structure, &player_one, &player_two, 21);
-
-    println!(
-        "The player that won in most universes won in {} universes",
-        first_universes.max(second_universes)
-    );
+    match universes {
+        Ok(universes) => println!(
+            "The player that won in most universes won in {} universes",
+            universes.iter().max().unwrap()
+        ),
+        Err(e) => println!("Couldn't play the game: {}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use itertools::Itertools;
-
     use super::*;
 
-    fn _generate_steps_triplets(dice_sides: u8) -> Vec<([u8; 3], [u8; 3])> {
-        let rolls = if (dice_sides % 6) == 0 {
-            dice_sides
-        } else {
-            (dice_sides / 6 + 1) * 6
-        };
-        (0..rolls)
-            .map(|roll| (roll % dice_sides) + 1)
-            .chunks(3)
-            .into_iter()
-            .tuples()
-            .map(|(first, second)| {
-                (
-                    first.collect::<Vec<u8>>().try_into().unwrap(),
-                    second.collect::<Vec<u8>>().try_into().unwrap(),
-                )
-            })
-            .collect()
+    // Plain exploration of every universe, only usable on tiny games
+    fn brute_force_wins(rules: &GameRules, players: &[Player], current: usize) -> Vec<u64> {
+        let mut wins: Vec<u64> = vec![0; players.len()];
+
+        for rolls in (0..rules.rolls_per_turn)
+            .map(|_| 1..=rules.die_sides)
+            .multi_cartesian_product()
+        {
+            let moved = players[current].advance(rolls.iter().sum(), rules.board_size);
+            if moved.score >= rules.win_score {
+                wins[current] += 1;
+            } else {
+                let mut next_players = players.to_vec();
+                next_players[current] = moved;
+                let next_wins =
+                    brute_force_wins(rules, &next_players, (current + 1) % players.len());
+                for (total, partial) in wins.iter_mut().zip(next_wins) {
+                    *total += partial;
+                }
+            }
+        }
+
+        wins
     }
 
     #[test]
@@ -246,7 +306,13 @@ mod tests {
 
         let starting_positions = get_positions(input_str);
 
-        assert_eq!(starting_positions, (4, 8))
+        assert_eq!(starting_positions, vec![4, 8]);
+
+        let input_str = "Player 1 starting position: 10
+        Player 2 starting position: 3
+        Player 3 starting position: 12";
+
+        assert_eq!(get_positions(input_str), vec![10, 3, 12]);
     }
 
     #[test]
@@ -256,12 +322,26 @@ mod tests {
 
         let starting_positions = get_positions(input_str);
 
-        let (results, total_rolls, loser_score) = compute_final_scores(starting_positions);
+        let outcome = play_deterministic(&GameRules::deterministic(), &starting_positions).unwrap();
 
-        assert_eq!(results.0, 1000);
-        assert_eq!(results.1, 745);
-        assert_eq!(total_rolls, 993);
-        assert_eq!(loser_score, 739785);
+        assert_eq!(outcome.scores, vec![1000, 745]);
+        assert_eq!(outcome.winner, 0);
+        assert_eq!(outcome.rolls, 993);
+        assert_eq!(outcome.loser_score(), 739785);
+    }
+
+    #[test]
+    fn roll_frequencies() {
+        assert_eq!(
+            GameRules::dirac().roll_frequencies(),
+            vec![(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)]
+        );
+
+        let rules = GameRules::new(10, 6, 2, 21).unwrap();
+        let frequencies = rules.roll_frequencies();
+        assert_eq!(frequencies.len(), 11);
+        assert_eq!(frequencies[5], (7, 6));
+        assert_eq!(frequencies.iter().map(|(_, u)| u).sum::<u64>(), 36);
     }
 
     #[test]
@@ -271,19 +351,93 @@ mod tests {
 
         let starting_positions = get_positions(input_str);
 
-        let first_player = Player::new(starting_positions.0);
-        let second_player = Player::new(starting_positions.1);
+        let mut game = DiracGame::new(GameRules::dirac()).unwrap();
+        let universes = game.count_wins(&starting_positions).unwrap();
+
+        assert_eq!(universes, vec![444356092776315u64, 341960390180808u64]);
+    }
+
+    #[test]
+    fn single_universe_matches_simulation() {
+        // With a one-sided die there's a single universe, which has to be the deterministic game
+        for (board_size, rolls_per_turn, win_score) in [(10, 3, 1000), (7, 2, 50), (13, 1, 100)] {
+            let rules = GameRules::new(board_size, 1, rolls_per_turn, win_score).unwrap();
+
+            for starting_positions in [vec![4, 6], vec![1, 2, 3], vec![5]] {
+                let outcome = play_deterministic(&rules, &starting_positions).unwrap();
+                let universes = DiracGame::new(rules)
+                    .unwrap()
+                    .count_wins(&starting_positions)
+                    .unwrap();
+
+                let mut expected = vec![0u64; starting_positions.len()];
+                expected[outcome.winner] = 1;
+                assert_eq!(universes, expected);
+            }
+        }
+    }
 
-        let mut acceleration_structure: HashMap<(Player, Player), (u64, u64)> = HashMap::new();
+    #[test]
+    fn game_variants() {
+        let rules = GameRules::new(7, 2, 2, 9).unwrap();
+
+        for starting_positions in [vec![1, 4], vec![2, 7, 5], vec![3, 3, 3, 3]] {
+            let players: Vec<Player> = starting_positions
+                .iter()
+                .cloned()
+                .map(Player::new)
+                .collect();
+
+            assert_eq!(
+                DiracGame::new(rules)
+                    .unwrap()
+                    .count_wins(&starting_positions)
+                    .unwrap(),
+                brute_force_wins(&rules, &players, 0)
+            );
+        }
+    }
 
-        let (first_universes, second_universes) = rec_dirac_match(
-            &mut acceleration_structure,
-            &first_player,
-            &second_player,
-            21,
+    #[test]
+    fn invalid_games() {
+        assert_eq!(
+            GameRules::new(0, 100, 3, 1000),
+            Err(String::from("The board needs at least one square"))
+        );
+        assert_eq!(
+            GameRules::new(10, 0, 3, 1000),
+            Err(String::from("The die needs at least one side"))
+        );
+        assert_eq!(
+            GameRules::new(10, 100, 0, 1000),
+            Err(String::from("Players have to roll at least once per turn"))
         );
+        assert_eq!(
+            GameRules::new(10, 100, 3, 0),
+            Err(String::from("The winning score has to be above zero"))
+        );
+        assert!(GameRules::new(10, u32::MAX, 2, 1000).is_err());
+        assert!(GameRules::new(10, 100, 3, u32::MAX).is_err());
+
+        // Rules built field by field are checked when the game starts
+        let spinning_die = GameRules {
+            die_sides: 0,
+            ..GameRules::deterministic()
+        };
+        assert!(play_deterministic(&spinning_die, &[4, 8]).is_err());
+        assert!(DiracGame::new(spinning_die).is_err());
 
-        assert_eq!(first_universes.max(second_universes), 444356092776315u64);
-        assert_eq!(first_universes.min(second_universes), 341960390180808u64);
+        let rules = GameRules::dirac();
+        assert_eq!(
+            play_deterministic(&rules, &[]),
+            Err(String::from("A game without players cannot be played"))
+        );
+        assert_eq!(
+            DiracGame::new(rules).unwrap().count_wins(&[4, 11]),
+            Err(String::from(
+                "Starting position 11 is not on a board of 10 squares"
+            ))
+        );
+        assert!(play_deterministic(&rules, &[0, 8]).is_err());
     }
 }
@@ -4,12 +4,23 @@ use nalgebra::Point3;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::{digit1, space0};
-use nom::combinator::opt;
-use nom::multi::separated_list1;
-use nom::sequence::{delimited, pair, preceded, separated_pair};
+use nom::combinator::{all_consuming, opt};
+use nom::sequence::{delimited, pair, preceded, separated_pair, tuple};
 use nom::IResult;
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::str::FromStr;
+
+use crate::aoc_lib::params::{DayParams, Param};
+
+const ENGINE: Param<RebootEngine> = Param::new("engine", RebootEngine::SignedVolumes);
+// Region whose on cubes are counted in the first part, x=-50..50,y=-50..50,z=-50..50 by default
+const REGION: Param<Cuboid> = Param::new(
+    "region",
+    Cuboid::new(Point3::new(-50, -50, -50), Point3::new(51, 51, 51)),
+);
+pub const PARAMS: DayParams = &[&ENGINE, &REGION];
 
 fn power(input: &str) -> IResult<&str, bool> {
     let (rem_str, power) = alt((tag("on"), tag("off")))(input)?;
@@ -36,29 +47,69 @@ fn axis_range(input: &str) -> IResult<&str, (i32, i32)> {
     ))
 }
 
+// Inclusive ranges like the ones in the reboot steps, e.g. "x=-50..50,y=-50..50,z=-50..50"
+pub fn region(input: &str) -> IResult<&str, Cuboid> {
+    let (rem_str, (x_axis, y_axis, z_axis)) = tuple((
+        axis_range,
+        preceded(tag(","), axis_range),
+        preceded(tag(","), axis_range),
+    ))(input)?;
+
+    let bottom_left = Point3::new(
+        x_axis.0.min(x_axis.1),
+        y_axis.0.min(y_axis.1),
+        z_axis.0.min(z_axis.1),
+    );
+    let top_right = Point3::new(
+        x_axis.0.max(x_axis.1) + 1,
+        y_axis.0.max(y_axis.1) + 1,
+        z_axis.0.max(z_axis.1) + 1,
+    );
+
+    Ok((rem_str, Cuboid::new(bottom_left, top_right)))
+}
+
 pub fn power_cube(input: &str) -> IResult<&str, PowerCuboid> {
     let (rem_str, power_state) = delimited(space0, power, space0)(input)?;
 
-    let (rem_str, axes) = separated_list1(tag(","), axis_range)(rem_str)?;
-
-    let x_range = (axes[0].0.min(axes[0].1), axes[0].0.max(axes[0].1) + 1);
-    let y_range = (axes[1].0.min(axes[1].1), axes[1].0.max(axes[1].1) + 1);
-    let z_range = (axes[2].0.min(axes[2].1), axes[2].0.max(axes[2].1) + 1);
+    let (rem_str, cuboid) = region(rem_str)?;
 
     Ok((
         rem_str,
-        PowerCuboid::new(power_state, x_range, y_range, z_range),
+        PowerCuboid {
+            cuboid,
+            power_state,
+        },
     ))
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+fn reboot_steps(input: &str) -> Result<Vec<PowerCuboid>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_idx, line)| {
+            all_consuming(power_cube)(line.trim_end())
+                .map(|(_, cube)| cube)
+                .map_err(|_| {
+                    format!(
+                        "Line {}: invalid reboot step '{}'",
+                        line_idx + 1,
+                        line.trim()
+                    )
+                })
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Cuboid {
     top_right: Point3<i32>,
     bottom_left: Point3<i32>,
 }
 
 impl Cuboid {
-    pub fn new(bottom_left: Point3<i32>, top_right: Point3<i32>) -> Self {
+    pub const fn new(bottom_left: Point3<i32>, top_right: Point3<i32>) -> Self {
         Self {
             top_right,
             bottom_left,
@@ -78,14 +129,15 @@ impl Cuboid {
             .product()
     }
 
+    // The top corner is exclusive, so cuboids that only touch don't intersect
     pub fn intersect(&self, other: &Self) -> Option<Self> {
-        if self.bottom_left.x > other.top_right.x || self.top_right.x < other.bottom_left.x {
+        if self.bottom_left.x >= other.top_right.x || self.top_right.x <= other.bottom_left.x {
             return None;
         }
-        if self.bottom_left.y > other.top_right.y || self.top_right.y < other.bottom_left.y {
+        if self.bottom_left.y >= other.top_right.y || self.top_right.y <= other.bottom_left.y {
             return None;
         }
-        if self.bottom_left.z > other.top_right.z || self.top_right.z < other.bottom_left.z {
+        if self.bottom_left.z >= other.top_right.z || self.top_right.z <= other.bottom_left.z {
             return None;
         }
 
@@ -116,24 +168,6 @@ pub struct PowerCuboid {
 }
 
 impl PowerCuboid {
-    fn new(
-        power_state: bool,
-        x_range: (i32, i32),
-        y_range: (i32, i32),
-        z_range: (i32, i32),
-    ) -> Self {
-        let bottom_left: Point3<i32> = Point3::new(x_range.0, y_range.0, z_range.0);
-        let top_right: Point3<i32> = Point3::new(x_range.1, y_range.1, z_range.1);
-
-        Self {
-            cuboid: Cuboid {
-                top_right,
-                bottom_left,
-            },
-            power_state,
-        }
-    }
-
     pub fn inside_volume(&self, volume: &Cuboid) -> bool {
         self.cuboid.inside_volume(volume)
     }
@@ -147,6 +181,14 @@ impl PowerCuboid {
         })
     }
 
+    // Same step, only affecting the cubes inside the region
+    pub fn clip(&self, region: &Cuboid) -> Option<PowerCuboid> {
+        Some(PowerCuboid {
+            cuboid: self.cuboid.intersect(region)?,
+            power_state: self.power_state,
+        })
+    }
+
     fn compute_on_volume(&self, other_cuboids: &[PowerCuboid]) -> u64 {
         let conflicts = other_cuboids
             .iter()
@@ -163,48 +205,122 @@ impl PowerCuboid {
     }
 }
 
-pub fn part1(input: &str) {
-    let target_volume = Cuboid::new(Point3::new(-50, -50, -50), Point3::new(51, 51, 51));
+// Ways of counting the cubes left on after a reboot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RebootEngine {
+    // Each on step counts its volume minus the later steps overlapping it, recursively
+    Recursive,
+    // Inclusion-exclusion over a list of signed cuboids, where each step cancels its overlap
+    // with the cuboids so far and on steps add themselves
+    SignedVolumes,
+}
 
-    let cubes = input
-        .lines()
-        .filter_map(|line| {
-            let (_, cube) = power_cube(line).unwrap();
-            if cube.inside_volume(&target_volume) {
-                Some(cube)
-            } else {
-                None
+impl FromStr for Cuboid {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        all_consuming(region)(raw)
+            .map(|(_, cuboid)| cuboid)
+            .map_err(|_| {
+                format!(
+                    "invalid region '{}', expected something like x=-50..50,y=-50..50,z=-50..50",
+                    raw
+                )
+            })
+    }
+}
+
+impl FromStr for RebootEngine {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "recursive" => Ok(Self::Recursive),
+            "signed" => Ok(Self::SignedVolumes),
+            _ => Err(format!(
+                "unknown engine '{}', expected 'recursive' or 'signed'",
+                name
+            )),
+        }
+    }
+}
+
+impl RebootEngine {
+    pub fn on_volume(&self, steps: &[PowerCuboid]) -> u64 {
+        match self {
+            Self::Recursive => steps
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.power_state)
+                .map(|(idx, c)| c.compute_on_volume(&steps[idx + 1..]))
+                .sum(),
+            Self::SignedVolumes => signed_on_volume(steps),
+        }
+    }
+
+    // Only the cubes inside the region are counted
+    pub fn on_volume_in(&self, steps: &[PowerCuboid], region: &Cuboid) -> u64 {
+        let clipped_steps = steps
+            .iter()
+            .filter_map(|step| step.clip(region))
+            .collect_vec();
+
+        self.on_volume(&clipped_steps)
+    }
+}
+
+// Identical cuboids are merged by adding up their signs, which keeps the list short when
+// many steps overlap the same areas
+fn signed_on_volume(steps: &[PowerCuboid]) -> u64 {
+    let mut signed_cuboids: HashMap<Cuboid, i64> = HashMap::new();
+
+    for step in steps {
+        let mut changes: HashMap<Cuboid, i64> = HashMap::new();
+        for (cuboid, sign) in signed_cuboids.iter() {
+            if let Some(overlap) = cuboid.intersect(&step.cuboid) {
+                *changes.entry(overlap).or_insert(0) -= sign;
             }
-        })
-        .collect_vec();
+        }
+        if step.power_state {
+            *changes.entry(step.cuboid.clone()).or_insert(0) += 1;
+        }
 
-    assert_eq!(cubes.len(), 20);
+        for (cuboid, sign) in changes {
+            *signed_cuboids.entry(cuboid).or_insert(0) += sign;
+        }
+        signed_cuboids.retain(|_, sign| *sign != 0);
+    }
 
-    let final_volume: u64 = cubes
+    let on_volume: i64 = signed_cuboids
         .iter()
-        .enumerate()
-        .filter(|(_, c)| c.power_state)
-        .map(|(idx, c)| c.compute_on_volume(&cubes[idx + 1..]))
+        .map(|(cuboid, sign)| i64::try_from(cuboid.volume()).unwrap() * sign)
         .sum();
 
+    u64::try_from(on_volume).unwrap()
+}
+
+pub fn part1(input: &str) {
+    let steps = reboot_steps(input).unwrap();
+    let init_region = REGION.get();
+
+    let inside_steps = steps
+        .iter()
+        .filter(|step| step.inside_volume(&init_region))
+        .count();
+    println!(
+        "{} of {} reboot steps are within the initialization region",
+        inside_steps,
+        steps.len()
+    );
+
+    let final_volume = ENGINE.get().on_volume_in(&steps, &init_region);
     println!("Number of on voxels: {}", final_volume);
 }
 
 pub fn part2(input: &str) {
-    let cubes = input
-        .lines()
-        .map(|line| {
-            let (_, cube) = power_cube(line).unwrap();
-            cube
-        })
-        .collect_vec();
+    let steps = reboot_steps(input).unwrap();
 
-    let final_volume: u64 = cubes
-        .iter()
-        .enumerate()
-        .filter(|(_, c)| c.power_state)
-        .map(|(idx, c)| c.compute_on_volume(&cubes[idx + 1..]))
-        .sum();
+    let final_volume = ENGINE.get().on_volume(&steps);
 
     println!("Number of on voxels: {}", final_volume);
 }
@@ -564,5 +680,103 @@ mod tests {
             .sum();
 
         assert_eq!(final_volume, 2758514936282235);
+        assert_eq!(RebootEngine::SignedVolumes.on_volume(&cubes), final_volume);
+    }
+
+    #[test]
+    fn touching_cuboids() {
+        let (_, left) = region("x=0..2,y=0..2,z=0..2").unwrap();
+        let (_, right) = region("x=3..5,y=0..2,z=0..2").unwrap();
+        let (_, overlapping) = region("x=2..5,y=2..2,z=-4..0").unwrap();
+
+        assert!(left.intersect(&right).is_none());
+        assert_eq!(
+            left.intersect(&overlapping),
+            Some(Cuboid::new(Point3::new(2, 2, 0), Point3::new(3, 3, 1)))
+        );
+    }
+
+    #[test]
+    fn engines_agree() {
+        let input_string = "on x=10..12,y=10..12,z=10..12
+        on x=11..13,y=11..13,z=11..13
+        off x=9..11,y=9..11,z=9..11
+        on x=10..10,y=10..10,z=10..10
+        on x=-54112..-39298,y=-85059..-49293,z=-27449..7877
+        off x=-54000..-39298,y=-85059..-49293,z=-27449..7877
+        on x=9..11,y=9..11,z=9..11";
+
+        let steps = reboot_steps(input_string).unwrap();
+
+        for engine in [RebootEngine::Recursive, RebootEngine::SignedVolumes] {
+            assert_eq!(engine.on_volume(&steps[..4]), 39);
+            assert_eq!(engine.on_volume(&steps), 65 + 112 * 35767 * 35327);
+        }
+    }
+
+    #[test]
+    fn region_queries() {
+        let input_string = "on x=-20..26,y=-36..17,z=-47..7
+        on x=-20..33,y=-21..23,z=-26..28
+        on x=-22..28,y=-29..23,z=-38..16
+        on x=-46..7,y=-6..46,z=-50..-1
+        on x=-49..1,y=-3..46,z=-24..28
+        on x=2..47,y=-22..22,z=-23..27
+        on x=-27..23,y=-28..26,z=-21..29
+        on x=-39..5,y=-6..47,z=-3..44
+        on x=-30..21,y=-8..43,z=-13..34
+        on x=-22..26,y=-27..20,z=-29..19
+        off x=-48..-32,y=26..41,z=-47..-37
+        on x=-12..35,y=6..50,z=-50..-2
+        off x=-48..-32,y=-32..-16,z=-15..-5
+        on x=-18..26,y=-33..15,z=-7..46
+        off x=-40..-22,y=-38..-28,z=23..41
+        on x=-16..35,y=-41..10,z=-47..6
+        off x=-32..-23,y=11..30,z=-14..3
+        on x=-49..-5,y=-3..45,z=-29..18
+        off x=18..30,y=-20..-8,z=-3..13
+        on x=-41..9,y=-7..43,z=-33..15
+        on x=-54112..-39298,y=-85059..-49293,z=-27449..7877
+        on x=967..23432,y=45373..81175,z=27513..53682";
+
+        let steps = reboot_steps(input_string).unwrap();
+        let (_, init_region) = region("x=-50..50,y=-50..50,z=-50..50").unwrap();
+        let (_, far_region) = region("x=1000..1009,y=50000..50009,z=30000..30000").unwrap();
+        let (_, empty_region) = region("x=100..200,y=100..200,z=100..200").unwrap();
+
+        for engine in [RebootEngine::Recursive, RebootEngine::SignedVolumes] {
+            assert_eq!(engine.on_volume_in(&steps, &init_region), 590784);
+            assert_eq!(engine.on_volume_in(&steps, &far_region), 100);
+            assert_eq!(engine.on_volume_in(&steps, &empty_region), 0);
+        }
+    }
+
+    #[test]
+    fn region_names() {
+        let (_, init_region) = region("x=-50..50,y=-50..50,z=-50..50").unwrap();
+        assert_eq!(REGION.default, init_region);
+        assert_eq!("x=-50..50,y=-50..50,z=-50..50".parse(), Ok(init_region));
+        assert_eq!(
+            "x=3..1,y=0..0,z=-2..2".parse(),
+            Ok(Cuboid::new(Point3::new(1, 0, -2), Point3::new(4, 1, 3)))
+        );
+        assert!("x=0..1,y=0..1".parse::<Cuboid>().is_err());
+        assert!("x=0..1,y=0..1,z=0..1 extra".parse::<Cuboid>().is_err());
+    }
+
+    #[test]
+    fn engine_names() {
+        assert_eq!("recursive".parse(), Ok(RebootEngine::Recursive));
+        assert_eq!("signed".parse(), Ok(RebootEngine::SignedVolumes));
+        assert!("sweep".parse::<RebootEngine>().is_err());
+    }
+
+    #[test]
+    fn invalid_steps() {
+        assert_eq!(
+            reboot_steps("on x=0..1,y=0..1,z=0..1\n\ntoggle x=0..1,y=0..1,z=0..1"),
+            Err("Line 3: invalid reboot step 'toggle x=0..1,y=0..1,z=0..1'".to_string())
+        );
+        assert!(reboot_steps("on x=0..1,y=0..1").is_err());
     }
 }
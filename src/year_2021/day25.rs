@@ -1,38 +1,140 @@
-use hashbrown::{HashMap, HashSet};
-use itertools::Itertools;
-
 use std::fmt;
 
-type Point = (usize, usize);
+// Upper bound on the steps simulated while looking for a stall or a cycle
+const STEP_LIMIT: usize = 100_000;
 
-#[derive(Debug, PartialEq, Eq)]
-enum Direction {
-    Right,
-    Down,
+// A row of the sea floor with one bit per cell, wrapping around at `width`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BitRow {
+    words: Vec<u64>,
+    width: usize,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct SeaCucumber {
-    direction: Direction,
-    position: Point,
+impl BitRow {
+    pub fn new(width: usize) -> Self {
+        Self {
+            words: vec![0; width.div_ceil(64)],
+            width,
+        }
+    }
+
+    pub fn get(&self, col: usize) -> bool {
+        self.words[col / 64] & (1 << (col % 64)) != 0
+    }
+
+    pub fn set(&mut self, col: usize) {
+        self.words[col / 64] |= 1 << (col % 64);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn last_word_mask(&self) -> u64 {
+        match self.width % 64 {
+            0 => u64::MAX,
+            used_bits => (1 << used_bits) - 1,
+        }
+    }
+
+    fn combine(&self, other: &Self, op: impl Fn(u64, u64) -> u64) -> Self {
+        let mut words = self
+            .words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(&left, &right)| op(left, right))
+            .collect::<Vec<u64>>();
+        if let Some(last) = words.last_mut() {
+            *last &= self.last_word_mask();
+        }
+
+        Self {
+            words,
+            width: self.width,
+        }
+    }
+
+    pub fn or(&self, other: &Self) -> Self {
+        self.combine(other, |left, right| left | right)
+    }
+
+    pub fn and_not(&self, other: &Self) -> Self {
+        self.combine(other, |left, right| left & !right)
+    }
+
+    // Every cell takes the value of the one before it, so the last cell wraps to the first
+    pub fn rotate_forward(&self) -> Self {
+        let mut rotated = Self::new(self.width);
+        let mut carry = u64::from(self.get(self.width - 1));
+        for (target, &word) in rotated.words.iter_mut().zip(self.words.iter()) {
+            *target = (word << 1) | carry;
+            carry = word >> 63;
+        }
+        if let Some(last) = rotated.words.last_mut() {
+            *last &= self.last_word_mask();
+        }
+
+        rotated
+    }
+
+    // Every cell takes the value of the one after it, so the first cell wraps to the last
+    pub fn rotate_backward(&self) -> Self {
+        let mut rotated = Self::new(self.width);
+        for (idx, target) in rotated.words.iter_mut().enumerate() {
+            let next_word = self.words.get(idx + 1).copied().unwrap_or(0);
+            *target = (self.words[idx] >> 1) | (next_word << 63);
+        }
+        if let Some(last) = rotated.words.last_mut() {
+            *last &= self.last_word_mask();
+        }
+        if self.get(0) {
+            rotated.set(self.width - 1);
+        }
+
+        rotated
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// How many cucumbers of each herd moved during a step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct HerdMoves {
+    east: usize,
+    south: usize,
+}
+
+impl HerdMoves {
+    pub fn total(&self) -> usize {
+        self.east + self.south
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Settlement {
+    // Nothing moves during this step
+    Stalled { step: usize },
+    // The layout after `start` steps comes back every `period` steps
+    Oscillating { start: usize, period: usize },
+}
+
+// One bit row per herd and per row of the sea floor
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct SeaFloor {
     width: usize,
     height: usize,
-    cucumbers: Vec<SeaCucumber>,
-    by_position: HashMap<Point, usize>,
-    could_move: HashSet<usize>,
-    timestep: usize,
+    east_herd: Vec<BitRow>,
+    south_herd: Vec<BitRow>,
 }
 
 impl SeaFloor {
     fn new(input: &str) -> Self {
         let height = input.lines().count();
-        let width = input.lines().next().unwrap().len();
+        let width = input.lines().next().unwrap().trim().len();
 
-        let mut cucumbers: Vec<SeaCucumber> = Vec::new();
+        let mut east_herd: Vec<BitRow> = vec![BitRow::new(width); height];
+        let mut south_herd: Vec<BitRow> = vec![BitRow::new(width); height];
 
         for (row, raw_data) in input.lines().enumerate() {
             for (col, cell_data) in raw_data
@@ -41,287 +143,187 @@ impl SeaFloor {
                 .filter(|(_, cell)| *cell != '.')
             {
                 match cell_data {
-                    '>' => cucumbers.push(SeaCucumber {
-                        direction: Direction::Right,
-                        position: (row, col),
-                    }),
-                    'v' => cucumbers.push(SeaCucumber {
-                        direction: Direction::Down,
-                        position: (row, col),
-                    }),
+                    '>' => east_herd[row].set(col),
+                    'v' => south_herd[row].set(col),
                     _ => panic!("We got some strange runaway character: {}", cell_data),
                 }
             }
         }
 
-        let mut by_position: HashMap<Point, usize> = HashMap::new();
-
-        for (id, cucumb) in cucumbers.iter().enumerate() {
-            by_position.insert(cucumb.position, id);
+        Self {
+            width,
+            height,
+            east_herd,
+            south_herd,
         }
+    }
 
-        let mut could_move: HashSet<usize> = HashSet::new();
+    pub fn population(&self) -> HerdMoves {
+        HerdMoves {
+            east: self.east_herd.iter().map(BitRow::count_ones).sum(),
+            south: self.south_herd.iter().map(BitRow::count_ones).sum(),
+        }
+    }
 
-        for (id, cucumb) in cucumbers.iter().enumerate() {
-            let (row, col) = &cucumb.position;
+    // The east herd moves first, into the cells free before any of them moves, then the south
+    // herd does the same with the updated layout
+    pub fn step(&mut self) -> HerdMoves {
+        let mut moves = HerdMoves::default();
 
-            let target_position = match cucumb.direction {
-                Direction::Down => {
-                    let target_row = if *row == height - 1 { 0 } else { *row + 1 };
-                    (target_row, *col)
-                }
-                Direction::Right => {
-                    let target_col = if *col == width - 1 { 0 } else { *col + 1 };
-                    (*row, target_col)
-                }
-            };
+        for (east_row, south_row) in self.east_herd.iter_mut().zip(self.south_herd.iter()) {
+            let occupied = east_row.or(south_row);
+            let movers = east_row.and_not(&occupied.rotate_backward());
 
-            if !by_position.contains_key(&target_position) {
-                could_move.insert(id);
-            }
+            moves.east += movers.count_ones();
+            *east_row = east_row.and_not(&movers).or(&movers.rotate_forward());
         }
 
-        Self {
-            width,
-            height,
-            cucumbers,
-            by_position,
-            could_move,
-            timestep: 0,
-        }
-    }
+        let blocked_rows = (0..self.height)
+            .map(|row| {
+                let row_below = (row + 1) % self.height;
+                self.east_herd[row_below].or(&self.south_herd[row_below])
+            })
+            .collect::<Vec<BitRow>>();
+        let movers = self
+            .south_herd
+            .iter()
+            .zip(blocked_rows.iter())
+            .map(|(south_row, blocked)| south_row.and_not(blocked))
+            .collect::<Vec<BitRow>>();
 
-    pub fn find_final_state(&mut self) -> Result<(), &'static str> {
-        while !self.could_move.is_empty() && self.timestep < 1000 {
-            self.step();
+        for (row, south_row) in self.south_herd.iter_mut().enumerate() {
+            let row_above = (row + self.height - 1) % self.height;
+
+            moves.south += movers[row].count_ones();
+            *south_row = south_row.and_not(&movers[row]).or(&movers[row_above]);
         }
 
-        if self.timestep >= 1000 {
-            Err("Didn't manage to find a stable state (1000 timesteps")
-        } else {
-            Ok(())
+        moves
+    }
+
+    pub fn herd_steps(&self) -> HerdSteps {
+        HerdSteps {
+            floor: self.clone(),
         }
     }
 
-    pub fn step(&mut self) {
-        self.timestep += 1;
+    // Brent's cycle detection, keeping a single earlier layout around. A layout where nothing
+    // moves is a cycle of length 1, but it's reported as soon as it's reached
+    pub fn find_final_state(&self) -> Result<Settlement, &'static str> {
+        let mut steps = self.herd_steps();
+        let mut checkpoint = self.clone();
+        let (mut power, mut period) = (1usize, 0usize);
 
-        let possible_horizontal = self
-            .could_move
-            .iter()
-            .filter(|id| self.cucumbers[**id].direction == Direction::Right)
-            .cloned()
-            .collect_vec();
-
-        for hor_id in possible_horizontal {
-            let (row, col) = self.cucumbers[hor_id].position;
-            let target_col = if col == self.width - 1 { 0 } else { col + 1 };
-            let target_position = (row, target_col);
-            if self.by_position.contains_key(&target_position) {
-                self.could_move.remove(&hor_id);
-            } else {
-                let row_above = if row == 0 { self.height - 1 } else { row - 1 };
-
-                let previous_above = (row_above, col);
-                if let Some(prev_cucumber) = self.by_position.get(&previous_above) {
-                    if self.cucumbers[*prev_cucumber].direction == Direction::Down {
-                        self.could_move.insert(*prev_cucumber);
-                    }
-                }
-                let current_above = (row_above, target_col);
-                if let Some(curr_cucumber) = self.by_position.get(&current_above) {
-                    if self.cucumbers[*curr_cucumber].direction == Direction::Down {
-                        self.could_move.remove(curr_cucumber);
-                    }
-                }
+        for step in 1..=STEP_LIMIT {
+            if steps.next().unwrap().total() == 0 {
+                return Ok(Settlement::Stalled { step });
+            }
 
-                let prev_col = if col == 0 { self.width - 1 } else { col - 1 };
-                let prev_behind = (row, prev_col);
-                if let Some(behind_cucumber) = self.by_position.get(&prev_behind) {
-                    if self.cucumbers[*behind_cucumber].direction == Direction::Right {
-                        self.could_move.insert(*behind_cucumber);
-                    }
-                }
-                let next_col = if target_col == self.width - 1 {
-                    0
-                } else {
-                    target_col + 1
-                };
-                let curr_front = (row, next_col);
-                if self.by_position.contains_key(&curr_front) {
-                    self.could_move.remove(&hor_id);
-                }
+            period += 1;
+            if steps.floor == checkpoint {
+                return Ok(Settlement::Oscillating {
+                    start: self.cycle_start(period),
+                    period,
+                });
+            }
+            if period == power {
+                checkpoint = steps.floor.clone();
+                power *= 2;
+                period = 0;
+            }
+        }
+
+        Err("Didn't manage to find a stable state or a cycle")
+    }
 
-                self.by_position.remove(&self.cucumbers[hor_id].position);
-                self.by_position.insert(target_position, hor_id);
+    // Steps before the first layout that comes back after `period` steps
+    fn cycle_start(&self, period: usize) -> usize {
+        let mut early = self.clone();
+        let mut late = self.clone();
+        for _ in 0..period {
+            late.step();
+        }
 
-                self.cucumbers[hor_id].position = target_position;
-            }
+        let mut start = 0;
+        while early != late {
+            early.step();
+            late.step();
+            start += 1;
         }
 
-        let possible_vertical = self
-            .could_move
-            .iter()
-            .filter(|id| self.cucumbers[**id].direction == Direction::Down)
-            .cloned()
-            .collect_vec();
-
-        for vert_id in possible_vertical {
-            let (row, col) = self.cucumbers[vert_id].position;
-            let target_row = if row == self.height - 1 { 0 } else { row + 1 };
-            let target_position = (target_row, col);
-            if self.by_position.contains_key(&target_position) {
-                self.could_move.remove(&vert_id);
-            } else {
-                let col_left = if col == 0 { self.width - 1 } else { col - 1 };
-                let row_above = if row == 0 { self.height - 1 } else { row - 1 };
-
-                let previous_above = (row_above, col);
-                if let Some(prev_above) = self.by_position.get(&previous_above) {
-                    if self.cucumbers[*prev_above].direction == Direction::Down {
-                        self.could_move.insert(*prev_above);
-                    }
-                }
-                let next_row = if target_row == self.height - 1 {
-                    0
-                } else {
-                    target_row + 1
-                };
-                let curr_below = (next_row, col);
-                if self.by_position.contains_key(&curr_below) {
-                    self.could_move.remove(&vert_id);
-                }
+        start
+    }
+}
 
-                let current_left = (target_row, col_left);
-                if let Some(curr_cucumber) = self.by_position.get(&current_left) {
-                    if self.cucumbers[*curr_cucumber].direction == Direction::Right {
-                        self.could_move.remove(curr_cucumber);
-                    }
-                }
-                let prev_left = (row, col_left);
-                if let Some(prev_cucumber) = self.by_position.get(&prev_left) {
-                    if self.cucumbers[*prev_cucumber].direction == Direction::Right {
-                        self.could_move.insert(*prev_cucumber);
-                    }
-                }
+// Endless steps of a copy of the sea floor, yielding the moves of each herd
+struct HerdSteps {
+    floor: SeaFloor,
+}
 
-                self.by_position.remove(&self.cucumbers[vert_id].position);
-                self.by_position.insert(target_position, vert_id);
+impl Iterator for HerdSteps {
+    type Item = HerdMoves;
 
-                self.cucumbers[vert_id].position = target_position;
-            }
-        }
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.floor.step())
     }
 }
 
 impl fmt::Display for SeaFloor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in 0usize..self.height {
-            let mut raw_line: Vec<char> = vec!['.'; self.width];
-            for cell_id in (0usize..self.width).filter_map(|col| self.by_position.get(&(row, col)))
-            {
-                let col = self.cucumbers[*cell_id].position.1;
-                match self.cucumbers[*cell_id].direction {
-                    Direction::Down => raw_line[col] = 'v',
-                    Direction::Right => raw_line[col] = '>',
-                };
-            }
+        for (east_row, south_row) in self.east_herd.iter().zip(self.south_herd.iter()) {
+            let raw_line: String = (0..self.width)
+                .map(|col| {
+                    if east_row.get(col) {
+                        '>'
+                    } else if south_row.get(col) {
+                        'v'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
 
-            writeln!(f, "{}", raw_line.into_iter().collect::<String>())?;
+            writeln!(f, "{}", raw_line)?;
         }
         Ok(())
     }
 }
 
 pub fn part1(input: &str) {
-    let mut sea_floor = SeaFloor::new(input);
-
-    sea_floor.find_final_state().unwrap();
-
-    println!(
-        "The cucumbers stop moving at timestep {}",
-        sea_floor.timestep + 1
-    );
+    let sea_floor = SeaFloor::new(input);
+
+    match sea_floor.find_final_state() {
+        Ok(Settlement::Stalled { step }) => {
+            println!("The cucumbers stop moving at timestep {}", step);
+
+            let population = sea_floor.population();
+            let (busiest_step, busiest_moves) = sea_floor
+                .herd_steps()
+                .take(step)
+                .enumerate()
+                .max_by_key(|(idx, moves)| (moves.total(), usize::MAX - idx))
+                .unwrap();
+            println!(
+                "Busiest step: {} ({} of {} east and {} of {} south cucumbers moved)",
+                busiest_step + 1,
+                busiest_moves.east,
+                population.east,
+                busiest_moves.south,
+                population.south
+            );
+        }
+        Ok(Settlement::Oscillating { start, period }) => println!(
+            "The cucumbers never stop: after timestep {} they repeat every {} timesteps",
+            start, period
+        ),
+        Err(e) => println!("{}", e),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    impl SeaFloor {
-        pub fn _find_differences(&self, other: &Self) {
-            let self_print_data = format!("{}", self)
-                .lines()
-                .map(|line| line.to_string())
-                .collect_vec();
-            let other_print_data = format!("{}", other)
-                .lines()
-                .map(|line| line.to_string())
-                .collect_vec();
-
-            let data_width = self_print_data.len();
-            println!(
-                "{:^data_width$}\t{:^data_width$}\t{:^data_width$}",
-                "Self", "Diff", "Other"
-            );
-
-            for row in 0usize..self.height {
-                let mut raw_line: Vec<char> = vec!['.'; self.width];
-                for cell_id in
-                    (0usize..self.width).filter_map(|col| self.by_position.get(&(row, col)))
-                {
-                    let other_char = if let Some(other_cucumber) =
-                        other.by_position.get(&self.cucumbers[*cell_id].position)
-                    {
-                        match other.cucumbers[*other_cucumber].direction {
-                            Direction::Down => 'v',
-                            Direction::Right => '>',
-                        }
-                    } else {
-                        '.'
-                    };
-
-                    let col = self.cucumbers[*cell_id].position.1;
-                    let our_char = match self.cucumbers[*cell_id].direction {
-                        Direction::Down => 'v',
-                        Direction::Right => '>',
-                    };
-
-                    raw_line[col] = if other_char == our_char {
-                        our_char
-                    } else {
-                        '*'
-                    };
-                }
-
-                println!(
-                    "{}\t{}\t{}",
-                    self_print_data[row],
-                    raw_line.into_iter().collect::<String>(),
-                    other_print_data[row]
-                );
-            }
-        }
-
-        pub fn _print_movable_cucumbers(&self) {
-            for cucumber in self
-                .could_move
-                .iter()
-                .map(|id| self.cucumbers.get(*id).unwrap())
-                .sorted_by(|left, right| Ord::cmp(&left.position, &right.position))
-            {
-                print!(
-                    "\tCucumber at position [{}, {}] could move ",
-                    cucumber.position.0, cucumber.position.1
-                );
-                match cucumber.direction {
-                    Direction::Right => println!("right."),
-                    Direction::Down => println!("down."),
-                };
-            }
-        }
-    }
-
     #[test]
     fn parse_data() {
         let input_string = "..........
@@ -331,33 +333,28 @@ mod tests {
 
         let sea_floor = SeaFloor::new(input_string);
 
+        let row_words = |words: &[u64]| -> Vec<BitRow> {
+            words
+                .iter()
+                .map(|&word| BitRow {
+                    words: vec![word],
+                    width: 10,
+                })
+                .collect()
+        };
         let ref_floor = SeaFloor {
             width: 10,
             height: 4,
-            cucumbers: vec![
-                SeaCucumber {
-                    direction: Direction::Right,
-                    position: (1, 1),
-                },
-                SeaCucumber {
-                    direction: Direction::Down,
-                    position: (1, 2),
-                },
-                SeaCucumber {
-                    direction: Direction::Down,
-                    position: (1, 7),
-                },
-                SeaCucumber {
-                    direction: Direction::Right,
-                    position: (2, 7),
-                },
-            ],
-            by_position: HashMap::from([((1, 1), 0), ((1, 2), 1), ((1, 7), 2), ((2, 7), 3)]),
-            could_move: HashSet::from([1, 3]),
-            timestep: 0,
+            east_herd: row_words(&[0, 1 << 1, 1 << 7, 0]),
+            south_herd: row_words(&[0, (1 << 2) | (1 << 7), 0, 0]),
         };
 
         assert_eq!(sea_floor, ref_floor);
+        assert_eq!(
+            sea_floor.to_string(),
+            "..........\n.>v....v..\n.......>..\n..........\n"
+        );
+        assert_eq!(sea_floor.population(), HerdMoves { east: 2, south: 2 });
     }
 
     #[test]
@@ -368,18 +365,16 @@ mod tests {
 
         let ref_data = ["...>>>>.>..", "...>>>.>.>."];
 
-        for raw_state in ref_data {
-            sea_floor.step();
-
-            let ref_floor = SeaFloor::new(raw_state);
+        for (raw_state, moved) in ref_data.into_iter().zip([1, 2]) {
+            assert_eq!(
+                sea_floor.step(),
+                HerdMoves {
+                    east: moved,
+                    south: 0
+                }
+            );
 
-            for cucumber in sea_floor.cucumbers.iter() {
-                let ref_cucumber_id = ref_floor.by_position.get(&cucumber.position).unwrap();
-                assert_eq!(
-                    cucumber.direction,
-                    ref_floor.cucumbers[*ref_cucumber_id].direction
-                );
-            }
+            assert_eq!(sea_floor, SeaFloor::new(raw_state));
         }
     }
 
@@ -430,17 +425,7 @@ mod tests {
             let ref_state = SeaFloor::new(input_data);
             sea_floor.step();
 
-            println!("Status at step {}", sea_floor.timestep);
-            println!();
-            println!("{}", sea_floor);
-            println!();
-
-            for current_cucumber in sea_floor.cucumbers.iter() {
-                let position = current_cucumber.position;
-                let ref_id = ref_state.by_position.get(&position).unwrap();
-                let ref_cucumber = ref_state.cucumbers.get(*ref_id).unwrap();
-                assert_eq!(current_cucumber.direction, ref_cucumber.direction);
-            }
+            assert_eq!(sea_floor.to_string(), ref_state.to_string());
         }
     }
 
@@ -456,12 +441,15 @@ mod tests {
         v.v..>>v.v
         ....v..v.>";
 
-        let mut sea_floor = SeaFloor::new(input_string);
+        let sea_floor = SeaFloor::new(input_string);
 
-        sea_floor.find_final_state().unwrap();
-        assert_eq!(sea_floor.timestep + 1, 58);
+        assert_eq!(
+            sea_floor.find_final_state(),
+            Ok(Settlement::Stalled { step: 58 })
+        );
 
         let mut sea_floor = SeaFloor::new(input_string);
+        let mut timestep = 0;
 
         let ref_data = vec![
             (
@@ -637,16 +625,93 @@ mod tests {
         for (step, step_ref_data) in ref_data {
             let ref_state = SeaFloor::new(step_ref_data);
 
-            while sea_floor.timestep < step {
+            while timestep < step {
                 sea_floor.step();
+                timestep += 1;
             }
 
-            for current_cucumber in sea_floor.cucumbers.iter() {
-                let position = current_cucumber.position;
-                let ref_id = ref_state.by_position.get(&position).unwrap();
-                let ref_cucumber = ref_state.cucumbers.get(*ref_id).unwrap();
-                assert_eq!(current_cucumber.direction, ref_cucumber.direction);
-            }
+            assert_eq!(sea_floor.to_string(), ref_state.to_string());
         }
     }
+
+    #[test]
+    fn wide_rows() {
+        let mut row = BitRow::new(70);
+        for col in [0, 63, 64, 69] {
+            row.set(col);
+        }
+
+        let forward = row.rotate_forward();
+        assert_eq!(
+            (0..70).filter(|&col| forward.get(col)).collect::<Vec<_>>(),
+            vec![0, 1, 64, 65]
+        );
+        let backward = row.rotate_backward();
+        assert_eq!(
+            (0..70).filter(|&col| backward.get(col)).collect::<Vec<_>>(),
+            vec![62, 63, 68, 69]
+        );
+
+        let input_string = format!("{}>.>\n{}", ".".repeat(62), ".".repeat(65));
+        let mut sea_floor = SeaFloor::new(&input_string);
+        assert_eq!(sea_floor.step(), HerdMoves { east: 2, south: 0 });
+        assert_eq!(
+            sea_floor.to_string(),
+            format!(">{}>.\n{}\n", ".".repeat(62), ".".repeat(65))
+        );
+    }
+
+    #[test]
+    fn herd_moves() {
+        let input_string = "...>...
+        .......
+        ......>
+        v.....>
+        ......>
+        .......
+        ..vvv..";
+
+        let moves = SeaFloor::new(input_string)
+            .herd_steps()
+            .take(4)
+            .collect::<Vec<HerdMoves>>();
+
+        assert_eq!(
+            moves,
+            vec![
+                HerdMoves { east: 3, south: 2 },
+                HerdMoves { east: 3, south: 4 },
+                HerdMoves { east: 4, south: 3 },
+                HerdMoves { east: 3, south: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn oscillating_herds() {
+        let lone_cucumber = SeaFloor::new("..>..");
+        assert_eq!(
+            lone_cucumber.find_final_state(),
+            Ok(Settlement::Oscillating {
+                start: 0,
+                period: 5
+            })
+        );
+
+        // The herds get in each other's way for a few steps before settling into a loop
+        let late_loop = SeaFloor::new(">>......\n........\nv.......");
+        assert_eq!(
+            late_loop.find_final_state(),
+            Ok(Settlement::Oscillating {
+                start: 6,
+                period: 9
+            })
+        );
+
+        let stuck = SeaFloor::new(">>>\nvvv");
+        assert_eq!(
+            stuck.find_final_state(),
+            Ok(Settlement::Stalled { step: 1 })
+        );
+    }
 }
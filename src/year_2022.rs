@@ -16,6 +16,7 @@ mod day15;
 mod day16;
 mod day17;
 
+use crate::aoc_lib::params::DayParams;
 use crate::aoc_lib::DayFn;
 
 pub fn get_day(day: u8) -> (Option<DayFn>, Option<DayFn>) {
@@ -51,3 +52,14 @@ pub fn get_day(day: u8) -> (Option<DayFn>, Option<DayFn>) {
         }
     }
 }
+
+// Parameters that can be overridden from the command line
+pub fn get_params(day: u8) -> DayParams {
+    match day {
+        7 => day07::PARAMS,
+        11 => day11::PARAMS,
+        15 => day15::PARAMS,
+        17 => day17::PARAMS,
+        _ => &[],
+    }
+}
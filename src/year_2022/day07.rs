@@ -9,6 +9,12 @@ use petgraph::visit::DfsPostOrder;
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 
+use crate::aoc_lib::params::{DayParams, Param};
+
+const UPDATE_SIZE: Param<usize> = Param::new("update", 30_000_000);
+const DISK_SIZE: Param<usize> = Param::new("disk", 70_000_000);
+pub const PARAMS: DayParams = &[&UPDATE_SIZE, &DISK_SIZE];
+
 enum CLILogLine {
     Entry(FSType),
     Command(CLICommand),
@@ -158,7 +164,8 @@ pub fn part1(input: &str) {
 
 pub fn part2(input: &str) {
     let tree = build_fs_tree(input);
-    let smallest_folder_to_delete = find_folder_to_delete(&tree, 30000000, 70000000).unwrap();
+    let smallest_folder_to_delete =
+        find_folder_to_delete(&tree, UPDATE_SIZE.get(), DISK_SIZE.get()).unwrap();
 
     println!(
         "Size of the smallest folder to delete if we want to install update: {}",
@@ -7,6 +7,8 @@ use nom::sequence::{pair, preceded};
 use nom::IResult;
 use std::fmt;
 
+use crate::aoc_lib::ocr;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Operation {
     Addx(i64),
@@ -73,6 +75,10 @@ impl SimpleCpu {
             }
         }
     }
+
+    pub fn crt_grid(&self) -> Vec<Vec<bool>> {
+        self.crt_out.chunks(40).map(|line| line.to_vec()).collect()
+    }
 }
 
 impl fmt::Display for SimpleCpu {
@@ -90,8 +96,13 @@ pub fn only_part(input: &str) {
     cpu.execute_program();
 
     println!("Signal pulse sum: {}", cpu.signal_sum);
-    println!("CRT out: ");
-    println!("{}", cpu);
+    match ocr::decode(&cpu.crt_grid()) {
+        Ok(text) => println!("CRT out: {}", text),
+        Err(e) => {
+            println!("Couldn't read the CRT ({}), here's the output: ", e);
+            println!("{}", cpu);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -252,4 +263,19 @@ mod tests {
 
         assert_eq!(cpu.signal_sum, 13140);
     }
+
+    #[test]
+    fn crt_output() {
+        let mut cpu = SimpleCpu::new(INPUT_STRING);
+        cpu.execute_program();
+
+        let crt = cpu.crt_grid();
+        assert_eq!(crt.len(), 6);
+        assert!(crt.iter().all(|line| line.len() == 40));
+        assert_eq!(
+            crt[0],
+            ocr::grid_from_str("##..##..##..##..##..##..##..##..##..##..")[0]
+        );
+        assert!(ocr::decode(&crt).is_err());
+    }
 }
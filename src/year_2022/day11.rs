@@ -9,6 +9,12 @@ use nom::IResult;
 
 use std::collections::VecDeque;
 
+use crate::aoc_lib::params::{DayParams, Param};
+
+const CALM_ROUNDS: Param<usize> = Param::new("rounds", 20);
+const SWEATY_ROUNDS: Param<usize> = Param::new("rounds2", 10_000);
+pub const PARAMS: DayParams = &[&CALM_ROUNDS, &SWEATY_ROUNDS];
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Operation {
     Sum(usize),
@@ -112,8 +118,7 @@ impl InspectionPlant {
             .unwrap();
     }
 
-    pub fn run_inspection(&mut self, rounds: usize) {
-        let is_worrisome = rounds > 20;
+    pub fn run_inspection(&mut self, rounds: usize, is_worrisome: bool) {
         for _ in 0..rounds {
             self.inspection_round(is_worrisome);
         }
@@ -179,8 +184,9 @@ fn initialize_inspection(input: &str) -> InspectionPlant {
 }
 
 pub fn part1(input: &str) {
+    let rounds = CALM_ROUNDS.get();
     let mut plant = initialize_inspection(input);
-    plant.run_inspection(20);
+    plant.run_inspection(rounds, false);
 
     let most_inspections: Vec<usize> = plant
         .inspectors
@@ -193,12 +199,16 @@ pub fn part1(input: &str) {
 
     let monkey_business = most_inspections[0] * most_inspections[1];
 
-    println!("Monkey business after 20 turns: {}", monkey_business)
+    println!(
+        "Monkey business after {} turns: {}",
+        rounds, monkey_business
+    )
 }
 
 pub fn part2(input: &str) {
+    let rounds = SWEATY_ROUNDS.get();
     let mut plant = initialize_inspection(input);
-    plant.run_inspection(10000);
+    plant.run_inspection(rounds, true);
 
     let most_inspections: Vec<usize> = plant
         .inspectors
@@ -212,8 +222,8 @@ pub fn part2(input: &str) {
     let monkey_business = most_inspections[0] * most_inspections[1];
 
     println!(
-        "Monkey business after 10000 sweaty turns: {}",
-        monkey_business
+        "Monkey business after {} sweaty turns: {}",
+        rounds, monkey_business
     )
 }
 
@@ -252,7 +262,7 @@ mod tests {
     #[test]
     fn simple_monkeys() {
         let mut plant = initialize_inspection(INPUT_STRING);
-        plant.run_inspection(20);
+        plant.run_inspection(20, false);
 
         let most_inspections: Vec<usize> = plant
             .inspectors
@@ -269,7 +279,7 @@ mod tests {
     #[test]
     fn long_monkeys() {
         let mut plant = initialize_inspection(INPUT_STRING);
-        plant.run_inspection(10000);
+        plant.run_inspection(10000, true);
 
         let most_inspections: Vec<usize> = plant
             .inspectors
@@ -7,6 +7,12 @@ use nom::IResult;
 use rayon::prelude::*;
 
 use crate::aoc_lib::jazz_parser::i32;
+use crate::aoc_lib::params::{DayParams, Param};
+
+// Row scanned in part 1 and size of the area searched in part 2. The examples use 10 and 20
+const SCANNED_ROW: Param<i32> = Param::new("row", 2_000_000);
+const SEARCH_LIMIT: Param<i32> = Param::new("limit", 4_000_000);
+pub const PARAMS: DayParams = &[&SCANNED_ROW, &SEARCH_LIMIT];
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 struct Point {
@@ -206,9 +212,10 @@ pub fn part1(input: &str) {
         beacons.push(b);
     }
 
-    let (empty_cells, _) = y_coverage(2000000, &sensors, &beacons, None);
+    let row = SCANNED_ROW.get();
+    let (empty_cells, _) = y_coverage(row, &sensors, &beacons, None);
 
-    println!("Amount of empty spots on line 2000000: {}", empty_cells);
+    println!("Amount of empty spots on line {}: {}", row, empty_cells);
 }
 
 pub fn part2(input: &str) {
@@ -220,7 +227,7 @@ pub fn part2(input: &str) {
         beacons.push(b);
     }
 
-    let point = find_distress_point(4000000, &sensors, &beacons);
+    let point = find_distress_point(SEARCH_LIMIT.get(), &sensors, &beacons);
 
     let disdress_frequency =
         usize::try_from(point.x).unwrap() * 4000000 + usize::try_from(point.y).unwrap();
@@ -2,6 +2,12 @@ use ansi_term::Colour;
 use itertools::Itertools;
 use std::ops::{Add, AddAssign};
 
+use crate::aoc_lib::params::{DayParams, Param};
+
+const ROCKS: Param<usize> = Param::new("rocks", 2022);
+const MANY_ROCKS: Param<usize> = Param::new("rocks2", 1_000_000_000_000);
+pub const PARAMS: DayParams = &[&ROCKS, &MANY_ROCKS];
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Direction {
     Left,
@@ -194,7 +200,13 @@ impl StoneTetris {
     }
 
     pub fn find_max_height(&mut self, turn: usize) -> i64 {
+        self.reset();
         let (base, rep, height) = self.find_repetition(false);
+        if turn < base {
+            self.reset();
+            self.simulate(turn);
+            return self.max_height;
+        }
         let full_reps = i64::try_from((turn - base) / rep).unwrap();
         let easy_height = full_reps * height;
         self.reset();
@@ -373,7 +385,7 @@ impl StoneTetris {
         let mut y: usize = tetris_lines.len();
         while let Some(line) = tetris_lines.pop() {
             y -= 1;
-            if y % 10 == 0 {
+            if y.is_multiple_of(10) {
                 print!("{:5} ", y);
             } else {
                 print!("      ");
@@ -496,14 +508,14 @@ impl StoneTetris {
 
 pub fn part1(input: &str) {
     let mut tetris = StoneTetris::new(input);
-    let max_height = tetris.find_max_height(2022);
+    let max_height = tetris.find_max_height(ROCKS.get());
 
     println!("Highest rock at: {}", max_height + 1);
 }
 
 pub fn part2(input: &str) {
     let mut tetris = StoneTetris::new(input);
-    let max_height = tetris.find_max_height(1000000000000);
+    let max_height = tetris.find_max_height(MANY_ROCKS.get());
 
     println!(
         "Highest rock after an unreasonable amount of time: {}",